@@ -1,30 +1,36 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
+use crate::agent::controller::{SearchController, SearchLimits, SearchResult};
 use crate::agent::rack::Rack;
 use crate::agent::scrabble_move::ScrabbleMove;
-use crate::anagrams::anagrams::{Anagram, Anagrams};
+use crate::anagrams::anagrams::Anagram;
 use crate::board::board::Board;
+use crate::utils::error::Error;
 use crate::utils::trieguy::TrieTree;
 use crate::utils::weighted_group::WeightedGroups;
 
 /// # Agent
 /// An agent has a rack, board, and score group.
 /// The agent will use the rack, board, and score group to
-/// make the best first move possible.
+/// make the best first move possible, spreading the search
+/// over `threads` threads.
 /// ### Functions
-/// - `solve()`, `find_best_move()`, `make_move()`, `get_final_score()`
+/// - `solve()`, `analyze()`, `find_best_move()`, `make_move()`, `get_final_score()`
 pub struct Agent {
     pub rack: Rack,
     pub board: Board,
     pub score_group: WeightedGroups,
+    pub threads: usize,
 }
 
 impl Agent {
-    pub fn new() -> Agent {
+    /// Create an agent that solves the given `Rack`.
+    pub fn with_rack(rack: Rack) -> Agent {
         Agent {
-            rack: Rack::new(),
+            rack,
             board: Board::new(),
             score_group: WeightedGroups::new(),
+            threads: 1,
         }
     }
 
@@ -32,32 +38,43 @@ impl Agent {
     /// This function will use the `Rack`, `Board`, `TrieTree` & `score_group`
     /// to solve.
     /// First display the `Rack` and `Board` to user.
-    /// Then create a `TrieTree` and generate `Anagrams` from the `Rack`.
-    /// After sorting by estimated score, find the best move from the list.
+    /// Then let the `SearchController` generate `Anagrams` from the `Rack`
+    /// using the `TrieTree`, within its limits.
+    /// After sorting by score, the best move is at the front of the list.
+    /// If the limits stopped the search before any move was found, the
+    /// board is left empty.
     /// The `Agent` then makes the move, and displays for the user.
-    pub fn solve(&mut self) -> () {
+    pub fn solve(
+        &mut self,
+        tree: &TrieTree,
+        controller: &mut SearchController,
+    ) -> Result<SearchResult, Error> {
         // Display the rack and board
         self.rack.display();
         self.board.display();
         println!("\n...beep boop...solving...");
 
-        // Read in the scrabble dictionary
-        let mut tree = TrieTree::new();
-        // Generate anagrams
-        let mut anagrams = Anagrams::new();
-        anagrams.generate("".to_string(), &mut self.rack.clone(), &mut tree);
-
-        // Iterate over the generated anagrams and find the best move
-        let mut best_moves: Vec<ScrabbleMove> = vec![];
-        for anagram in anagrams {
-            best_moves.push(self.find_best_move(&anagram.clone()));
+        // Find and score every candidate move
+        let result = controller.run(self, tree);
+        // Make the best move, if the search found one in time
+        if let Some(best) = result.best() {
+            self.make_move(&best.anagram, best.starting_element)?;
+            self.board.set_score(best.score);
         }
-        // Sort best_moves by score
-        // best_moves.sort();
-        // Make the best move
-        self.make_move(&best_moves[0].anagram, best_moves[0].starting_element);
         // Display the board
         self.board.display();
+        Ok(result)
+    }
+
+    /// ### analyze()
+    /// Generate `Anagrams` from the `Rack` and find the best placement for
+    /// each of them, with no time or node limit. Returns one `ScrabbleMove`
+    /// per word and wildcard placement, highest score first. The board is
+    /// left untouched.
+    pub fn analyze(&self, tree: &TrieTree) -> Vec<ScrabbleMove> {
+        SearchController::new(SearchLimits::default())
+            .run(self, tree)
+            .moves
     }
 
     /// ### find_best_move()
@@ -68,16 +85,18 @@ impl Agent {
     /// letters on the double letter score.
     /// Given an `Anagram`, determine the best placement for the word and its actual score.
     /// Return a `ScrabbleMove` object (`Anagram` & `starting_element`)
-    pub fn find_best_move(&mut self, anagram: &Anagram) -> ScrabbleMove {
+    pub fn find_best_move(&self, anagram: &Anagram) -> ScrabbleMove {
         // Set up the word, final_score
-        // and a hashmap to easily access the letters and their score
+        // and an ordered map to easily access the letters and their score;
+        // ordered so ties between equal letters are always broken the same way
         let word = anagram.word.clone();
         let mut final_score: u32 = 0;
-        let mut index_vs_weight_hashmap: HashMap<usize, u32> = HashMap::new();
+        let mut index_vs_weight_hashmap: BTreeMap<usize, u32> = BTreeMap::new();
         for letter in word.chars() {
             let letter_weight = self.score_group.get_score(letter);
-            let index = word.chars().position(|c| c == letter).unwrap();
-            index_vs_weight_hashmap.insert(index, letter_weight);
+            if let Some(index) = word.chars().position(|c| c == letter) {
+                index_vs_weight_hashmap.insert(index, letter_weight);
+            }
         }
 
         // If the word is less than 5 characters it must start on center square
@@ -86,54 +105,51 @@ impl Agent {
             for letter in word.chars() {
                 final_score += self.score_group.get_score(letter);
             }
-            self.board.set_score(final_score);
-            return ScrabbleMove::new(anagram.clone(), 6);
+            return ScrabbleMove::new(anagram.clone(), 6, final_score);
         }
         // Find the index of the letter with the highest score
         let highest_scoring_letter_index = index_vs_weight_hashmap
             .iter()
             .max_by_key(|x| x.1)
-            .unwrap()
-            .0;
+            .map_or(0, |(index, _)| *index);
         // Set the score
-        final_score = self.get_final_score(&word, *highest_scoring_letter_index);
-        self.board.set_score(final_score);
+        final_score = self.get_final_score(&word, highest_scoring_letter_index);
         // If its 5 characters
         // Check the first and last letter, whichever is higher determines the placement
         // If its the first letter, place starting on element 3; otherwise start on center square
         if word.len() == 5 {
-            if *highest_scoring_letter_index == 0 {
-                return ScrabbleMove::new(anagram.clone(), 3);
+            if highest_scoring_letter_index == 0 {
+                ScrabbleMove::new(anagram.clone(), 3, final_score)
             } else {
-                return ScrabbleMove::new(anagram.clone(), 6);
+                ScrabbleMove::new(anagram.clone(), 6, final_score)
             }
         }
         // If its 6 characters
         else if word.len() == 6 {
-            if *highest_scoring_letter_index == 0 {
-                return ScrabbleMove::new(anagram.clone(), 3);
-            } else if *highest_scoring_letter_index == 1 {
-                return ScrabbleMove::new(anagram.clone(), 2);
-            } else if *highest_scoring_letter_index == 5 {
-                return ScrabbleMove::new(anagram.clone(), 5);
+            if highest_scoring_letter_index == 0 {
+                ScrabbleMove::new(anagram.clone(), 3, final_score)
+            } else if highest_scoring_letter_index == 1 {
+                ScrabbleMove::new(anagram.clone(), 2, final_score)
+            } else if highest_scoring_letter_index == 5 {
+                ScrabbleMove::new(anagram.clone(), 5, final_score)
             } else {
-                return ScrabbleMove::new(anagram.clone(), 4);
+                ScrabbleMove::new(anagram.clone(), 4, final_score)
             }
         }
         // Otherwise, its 7 letters
         else {
-            if *highest_scoring_letter_index == 0 {
-                return ScrabbleMove::new(anagram.clone(), 3);
-            } else if *highest_scoring_letter_index == 1 {
-                return ScrabbleMove::new(anagram.clone(), 2);
-            } else if *highest_scoring_letter_index == 2 {
-                return ScrabbleMove::new(anagram.clone(), 1);
-            } else if *highest_scoring_letter_index == 5 {
-                return ScrabbleMove::new(anagram.clone(), 5);
-            } else if *highest_scoring_letter_index == 4 {
-                return ScrabbleMove::new(anagram.clone(), 4);
+            if highest_scoring_letter_index == 0 {
+                ScrabbleMove::new(anagram.clone(), 3, final_score)
+            } else if highest_scoring_letter_index == 1 {
+                ScrabbleMove::new(anagram.clone(), 2, final_score)
+            } else if highest_scoring_letter_index == 2 {
+                ScrabbleMove::new(anagram.clone(), 1, final_score)
+            } else if highest_scoring_letter_index == 5 {
+                ScrabbleMove::new(anagram.clone(), 5, final_score)
+            } else if highest_scoring_letter_index == 4 {
+                ScrabbleMove::new(anagram.clone(), 4, final_score)
             } else {
-                return ScrabbleMove::new(anagram.clone(), 3);
+                ScrabbleMove::new(anagram.clone(), 3, final_score)
             }
         }
     }
@@ -141,18 +157,34 @@ impl Agent {
     pub fn get_final_score(&self, word: &str, double_letter_index: usize) -> u32 {
         let mut final_score: u32 = 0;
         let word_chars: Vec<char> = word.chars().collect();
-        let double_score = self.score_group.get_score(word_chars[double_letter_index]);
+        let double_score = word_chars
+            .get(double_letter_index)
+            .map_or(0, |letter| self.score_group.get_score(*letter));
         for letter in word_chars {
             final_score += self.score_group.get_score(letter);
         }
-        return final_score + double_score;
+        final_score + double_score
     }
 
-    pub fn make_move(&mut self, anagram: &Anagram, mut starting_element: u32) {
-        let word = anagram.word.clone();
-        for letter in word.chars() {
-            self.board.spaces[starting_element as usize] = Some(letter);
-            starting_element += 1;
+    /// ### make_move()
+    /// Place the word on the board from `starting_element`, or fail
+    /// without touching the board if it would run off the end.
+    pub fn make_move(&mut self, anagram: &Anagram, starting_element: u32) -> Result<(), Error> {
+        let start = starting_element as usize;
+        let end = start + anagram.word.chars().count();
+        if end > self.board.spaces.len() {
+            return Err(Error::IllegalMove(format!(
+                "{} does not fit on the board from square {}",
+                anagram.word,
+                start + 1
+            )));
+        }
+        for (space, letter) in self.board.spaces[start..end]
+            .iter_mut()
+            .zip(anagram.word.chars())
+        {
+            *space = Some(letter);
         }
+        Ok(())
     }
 }
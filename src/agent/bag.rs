@@ -0,0 +1,127 @@
+use rand::Rng;
+
+/// How many of each tile a standard set has, 100 in all; a wildcard is a
+/// `_`.
+pub const TILE_COUNTS: [(char, usize); 27] = [
+    ('A', 9),
+    ('B', 2),
+    ('C', 2),
+    ('D', 4),
+    ('E', 12),
+    ('F', 2),
+    ('G', 3),
+    ('H', 2),
+    ('I', 9),
+    ('J', 1),
+    ('K', 1),
+    ('L', 4),
+    ('M', 2),
+    ('N', 6),
+    ('O', 8),
+    ('P', 2),
+    ('Q', 1),
+    ('R', 6),
+    ('S', 4),
+    ('T', 6),
+    ('U', 4),
+    ('V', 2),
+    ('W', 2),
+    ('X', 1),
+    ('Y', 2),
+    ('Z', 1),
+    ('_', 2),
+];
+
+/// # Bag
+/// The tiles that have not been drawn yet. A new bag holds a standard set,
+/// as counted in `TILE_COUNTS`. Unlike `Rack::from_rng()`, drawing from a `Bag`
+/// takes the tiles out, so a whole game can be played from one bag.
+/// Functions:
+/// - `new()`, `len()`, `is_empty()`, `tiles()`, `draw()`, `put_back()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bag {
+    tiles: Vec<char>,
+}
+
+impl Default for Bag {
+    fn default() -> Self {
+        Bag::new()
+    }
+}
+
+impl Bag {
+    pub fn new() -> Bag {
+        let tiles = TILE_COUNTS
+            .iter()
+            .flat_map(|&(tile, count)| std::iter::repeat_n(tile, count))
+            .collect();
+        Bag { tiles }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// ### tiles()
+    /// The tiles still in the bag, in no particular order.
+    pub fn tiles(&self) -> &[char] {
+        &self.tiles
+    }
+
+    /// ### draw()
+    /// Take up to `count` tiles at random; fewer when the bag runs out.
+    pub fn draw<R: Rng>(&mut self, count: usize, rng: &mut R) -> Vec<char> {
+        let mut drawn = Vec::new();
+        for _ in 0..count.min(self.tiles.len()) {
+            let i = rng.gen_range(0..self.tiles.len());
+            drawn.push(self.tiles.swap_remove(i));
+        }
+        drawn
+    }
+
+    /// ### put_back()
+    /// Return tiles to the bag, e.g. after an exchange. A letter played
+    /// as a wildcard (lowercase) goes back as a `_`.
+    pub fn put_back(&mut self, tiles: &[char]) {
+        for tile in tiles {
+            match tile.is_ascii_lowercase() {
+                true => self.tiles.push('_'),
+                false => self.tiles.push(*tile),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn new_bag_holds_a_standard_set() {
+        let bag = Bag::new();
+        assert_eq!(bag.len(), 100);
+        for (tile, count) in TILE_COUNTS {
+            let found = bag.tiles().iter().filter(|t| **t == tile).count();
+            assert_eq!(found, count, "{}", tile);
+        }
+    }
+
+    #[test]
+    fn drawing_takes_tiles_out_and_wildcards_go_back_as_blanks() {
+        let mut bag = Bag::new();
+        let mut rng = StdRng::seed_from_u64(7);
+        let drawn = bag.draw(7, &mut rng);
+        assert_eq!((drawn.len(), bag.len()), (7, 93));
+        bag.put_back(&['q', 'Z']);
+        assert_eq!(bag.len(), 95);
+        assert!(bag.tiles().ends_with(&['_', 'Z']));
+        assert_eq!(bag.draw(200, &mut rng).len(), 95);
+        assert!(bag.is_empty());
+    }
+}
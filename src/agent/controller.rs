@@ -0,0 +1,435 @@
+use std::cmp::Ordering as Order;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::agent::agent::Agent;
+use crate::agent::movegen::MoveGenerator;
+use crate::agent::rack::Rack;
+use crate::agent::scrabble_move::ScrabbleMove;
+use crate::anagrams::anagrams::{Anagrams, CHECK_INTERVAL};
+use crate::board::play::Play;
+use crate::board::position::Position;
+use crate::utils::trieguy::TrieTree;
+
+/// ## Candidate
+/// A move a `SearchController` can search for: anything that can be
+/// put in best-first order.
+pub trait Candidate {
+    fn compare(&self, other: &Self) -> Order;
+}
+
+impl Candidate for ScrabbleMove {
+    /// By score, longer words first on a tie, then A-Z.
+    fn compare(&self, other: &Self) -> Order {
+        other
+            .score
+            .cmp(&self.score)
+            .then(other.anagram.word.len().cmp(&self.anagram.word.len()))
+            .then(self.anagram.word.cmp(&other.anagram.word))
+    }
+}
+
+impl Candidate for Play {
+    fn compare(&self, other: &Self) -> Order {
+        Play::compare(self, other)
+    }
+}
+
+/// ## SearchLimits
+/// When a search should give up. `None` means no limit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchLimits {
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+/// ## CancelHandle
+/// A cloneable flag for stopping a running search from another thread.
+#[derive(Clone, Default)]
+pub struct CancelHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        CancelHandle::default()
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+/// ## StopReason
+/// Why a search finished.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopReason {
+    /// Every candidate was generated and scored
+    Complete,
+    TimeLimit,
+    NodeLimit,
+    Cancelled,
+}
+
+impl StopReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StopReason::Complete => "complete",
+            StopReason::TimeLimit => "time limit",
+            StopReason::NodeLimit => "node limit",
+            StopReason::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// ## Progress
+/// A snapshot passed to the progress callback each time a piece of the
+/// search (a first letter, or a line of the board) finishes.
+pub struct Progress<'a, M = ScrabbleMove> {
+    pub elapsed: Duration,
+    pub nodes: u64,
+    pub candidates: usize,
+    pub units_done: usize,
+    pub units_total: usize,
+    pub best: Option<&'a M>,
+}
+
+/// Called with a `Progress` snapshot as the search goes.
+type ProgressCallback<'a, M> = Box<dyn FnMut(&Progress<M>) + Send + 'a>;
+
+/// ## SearchResult
+/// Everything found before the search stopped, best first.
+pub struct SearchResult<M = ScrabbleMove> {
+    pub moves: Vec<M>,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub stop_reason: StopReason,
+}
+
+impl<M> SearchResult<M> {
+    pub fn best(&self) -> Option<&M> {
+        self.moves.first()
+    }
+
+    /// ### stats()
+    /// How the search went, without the moves themselves.
+    pub fn stats(&self) -> Value {
+        json!({
+            "candidates": self.moves.len(),
+            "nodes": self.nodes,
+            "elapsed_ms": self.elapsed.as_millis() as u64,
+            "stop": self.stop_reason.as_str(),
+        })
+    }
+}
+
+/// # SearchController
+/// Runs a move search as an anytime search: the work is split into
+/// independent units (first letters for the `Agent`'s opening search,
+/// lines of the board for a `Position`), the most promising units are
+/// searched first, and the best move found so far is always available.
+/// The search stops when the time or node limit is reached or when a
+/// `CancelHandle` is cancelled, and returns whatever it has found by then.
+/// Functions:
+/// - `cancel_handle()`, `on_progress()`, `run()`, `run_position()`
+pub struct SearchController<'a, M = ScrabbleMove> {
+    pub limits: SearchLimits,
+    cancel: CancelHandle,
+    progress: Option<ProgressCallback<'a, M>>,
+}
+
+impl<'a, M: Candidate + Send> SearchController<'a, M> {
+    pub fn new(limits: SearchLimits) -> Self {
+        SearchController {
+            limits,
+            cancel: CancelHandle::new(),
+            progress: None,
+        }
+    }
+
+    /// ### cancel_handle()
+    /// A handle that stops this controller's searches when cancelled.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// ### on_progress()
+    /// Call `callback` on the searching thread whenever a unit of the
+    /// search has been fully searched.
+    pub fn on_progress<F: FnMut(&Progress<M>) + Send + 'a>(&mut self, callback: F) {
+        self.progress = Some(Box::new(callback));
+    }
+
+    /// ### search()
+    /// Search `units` units of work using `threads` worker threads. Each
+    /// worker claims the next unsearched unit, runs `work` on it and sends
+    /// the moves back; this thread merges the results, reports progress
+    /// and enforces the time limit. `work` gets a `should_stop` check to
+    /// call every `CHECK_INTERVAL` nodes, so a stop is noticed quickly
+    /// even in the middle of a large unit, and returns the moves found,
+    /// the nodes visited and whether it was stopped.
+    fn search<W>(&mut self, units: usize, threads: usize, work: W) -> SearchResult<M>
+    where
+        W: Fn(usize, &dyn Fn(u64) -> bool) -> (Vec<M>, u64, bool) + Sync,
+    {
+        let start = Instant::now();
+        let deadline = self.limits.time.map(|t| start + t);
+        let next = AtomicUsize::new(0);
+        let nodes = AtomicU64::new(0);
+        let halt = AtomicBool::new(false);
+        let max_nodes = self.limits.nodes;
+        let cancel = &self.cancel;
+
+        let should_stop = |_: u64| {
+            let total = nodes.fetch_add(CHECK_INTERVAL, Ordering::Relaxed) + CHECK_INTERVAL;
+            halt.load(Ordering::Relaxed)
+                || cancel.is_cancelled()
+                || max_nodes.is_some_and(|max| total >= max)
+        };
+
+        let mut moves: Vec<M> = Vec::new();
+        let mut units_done = 0;
+        let mut timed_out = false;
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel::<(Vec<M>, bool)>();
+            for _ in 0..threads.max(1).min(units.max(1)) {
+                let sender = sender.clone();
+                let (next, nodes, halt, work) = (&next, &nodes, &halt, &work);
+                let should_stop = &should_stop;
+                scope.spawn(move || loop {
+                    if halt.load(Ordering::Relaxed) || cancel.is_cancelled() {
+                        break;
+                    }
+                    let unit = next.fetch_add(1, Ordering::Relaxed);
+                    if unit >= units {
+                        break;
+                    }
+                    let (found, visited, stopped) = work(unit, should_stop);
+                    nodes.fetch_add(visited % CHECK_INTERVAL, Ordering::Relaxed);
+                    if stopped {
+                        halt.store(true, Ordering::Relaxed);
+                    }
+                    if sender.send((found, stopped)).is_err() || stopped {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            loop {
+                let message = match deadline {
+                    Some(deadline) => {
+                        receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    }
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match message {
+                    Ok((found, stopped)) => {
+                        for m in found {
+                            // Keep the best move at the front as results arrive
+                            if moves
+                                .first()
+                                .is_some_and(|best| m.compare(best) == Order::Less)
+                            {
+                                moves.insert(0, m);
+                            } else {
+                                moves.push(m);
+                            }
+                        }
+                        if !stopped {
+                            units_done += 1;
+                        }
+                        if let Some(callback) = &mut self.progress {
+                            callback(&Progress {
+                                elapsed: start.elapsed(),
+                                nodes: nodes.load(Ordering::Relaxed),
+                                candidates: moves.len(),
+                                units_done,
+                                units_total: units,
+                                best: moves.first(),
+                            });
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        timed_out = true;
+                        halt.store(true, Ordering::Relaxed);
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+        sort_moves(&mut moves);
+        let stop_reason = if units_done == units {
+            StopReason::Complete
+        } else if cancel.is_cancelled() {
+            StopReason::Cancelled
+        } else if timed_out || deadline.is_some_and(|d| Instant::now() >= d) {
+            StopReason::TimeLimit
+        } else {
+            StopReason::NodeLimit
+        };
+        SearchResult {
+            moves,
+            nodes: nodes.load(Ordering::Relaxed),
+            elapsed: start.elapsed(),
+            stop_reason,
+        }
+    }
+}
+
+impl<'a> SearchController<'a, ScrabbleMove> {
+    /// ### run()
+    /// Search for the `Agent`'s opening moves using `agent.threads`
+    /// threads, one first letter at a time.
+    pub fn run(&mut self, agent: &Agent, tree: &TrieTree) -> SearchResult {
+        let first_letters = SearchController::order_letters(agent, tree);
+        self.search(first_letters.len(), agent.threads, |unit, should_stop| {
+            let mut anagrams = Anagrams::new();
+            anagrams.generate(&agent.rack, tree, &[first_letters[unit]], should_stop);
+            let (nodes, stopped) = (anagrams.nodes, anagrams.stopped);
+            let placed = anagrams.map(|a| agent.find_best_move(&a)).collect();
+            (placed, nodes, stopped)
+        })
+    }
+
+    /// ### order_letters()
+    /// The first letters worth searching, best first: with a wildcard any
+    /// letter can start a word, otherwise only the letters on the rack can.
+    fn order_letters(agent: &Agent, tree: &TrieTree) -> Vec<char> {
+        let has_wildcard = agent.rack.letters.contains(&'_');
+        let mut letters: Vec<char> = tree
+            .root
+            .children
+            .keys()
+            .filter(|letter| has_wildcard || agent.rack.letters.contains(letter))
+            .cloned()
+            .collect();
+        letters.sort_by(|a, b| {
+            let on_rack = |l: &char| agent.rack.letters.contains(l);
+            on_rack(b)
+                .cmp(&on_rack(a))
+                .then(
+                    agent
+                        .score_group
+                        .get_score(*b)
+                        .cmp(&agent.score_group.get_score(*a)),
+                )
+                .then(a.cmp(b))
+        });
+        letters
+    }
+}
+
+impl<'a> SearchController<'a, Play> {
+    /// ### run_position()
+    /// Search for every `Play` of `rack` on `position` using `threads`
+    /// threads, one line of the board at a time, busiest lines first.
+    pub fn run_position(
+        &mut self,
+        position: &Position,
+        rack: &Rack,
+        tree: &TrieTree,
+        threads: usize,
+    ) -> SearchResult<Play> {
+        let generator = MoveGenerator::new(position, rack, tree);
+        let lines = generator.lines();
+        self.search(lines.len(), threads, |unit, should_stop| {
+            generator.line(lines[unit], should_stop)
+        })
+    }
+}
+
+/// Put moves in best-first order.
+pub fn sort_moves<M: Candidate>(moves: &mut [M]) {
+    moves.sort_by(|a, b| a.compare(b));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::lexicon::Lexicon;
+
+    /// Every word of two to four letters spelled with `AEIRST`, so a
+    /// search has many thousands of nodes to visit.
+    fn lexicon() -> Lexicon {
+        let mut words = vec![String::new()];
+        let mut all = Vec::new();
+        for _ in 0..4 {
+            words = words
+                .iter()
+                .flat_map(|word| "AEIRST".chars().map(move |c| format!("{}{}", word, c)))
+                .collect();
+            all.extend(words.iter().filter(|word| word.len() >= 2).cloned());
+        }
+        Lexicon::from_words(all)
+    }
+
+    fn search(
+        lexicon: &Lexicon,
+        limits: SearchLimits,
+        threads: usize,
+        cancelled: bool,
+    ) -> SearchResult<Play> {
+        let rack = Rack::from_letters("AEIRST?").unwrap();
+        let mut controller = SearchController::new(limits);
+        if cancelled {
+            controller.cancel_handle().cancel();
+        }
+        controller.run_position(&Position::new(), &rack, &lexicon.trie, threads)
+    }
+
+    fn notations(result: &SearchResult<Play>) -> Vec<String> {
+        result.moves.iter().map(|play| play.notation()).collect()
+    }
+
+    #[test]
+    fn a_node_limit_stops_the_search_the_same_way_every_time() {
+        // One lexicon for both runs: each trie walks its HashMap children in
+        // its own order, so only the same trie stops at the same node
+        let lexicon = lexicon();
+        let limits = SearchLimits {
+            time: None,
+            nodes: Some(CHECK_INTERVAL),
+        };
+        let first = search(&lexicon, limits, 1, false);
+        let second = search(&lexicon, limits, 1, false);
+        assert_eq!(first.stop_reason, StopReason::NodeLimit);
+        assert!(first.nodes >= CHECK_INTERVAL);
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(notations(&first), notations(&second));
+
+        let complete = search(&lexicon, SearchLimits::default(), 1, false);
+        assert_eq!(complete.stop_reason, StopReason::Complete);
+        assert!(complete.nodes > first.nodes);
+        assert!(complete.moves.len() > first.moves.len());
+    }
+
+    #[test]
+    fn a_search_cancelled_before_it_starts_finds_nothing() {
+        let lexicon = lexicon();
+        let result = search(&lexicon, SearchLimits::default(), 2, true);
+        assert_eq!(result.stop_reason, StopReason::Cancelled);
+        assert!(result.moves.is_empty());
+        assert_eq!(result.nodes, 0);
+    }
+
+    #[test]
+    fn threads_find_what_one_thread_finds() {
+        let lexicon = lexicon();
+        let single = search(&lexicon, SearchLimits::default(), 1, false);
+        let threaded = search(&lexicon, SearchLimits::default(), 4, false);
+        assert!(!single.moves.is_empty());
+        assert_eq!(notations(&single), notations(&threaded));
+        assert_eq!(single.nodes, threaded.nodes);
+    }
+}
@@ -0,0 +1,367 @@
+use std::collections::BTreeMap;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::agent::controller::{SearchController, SearchLimits};
+use crate::agent::rack::Rack;
+use crate::agent::strategy::leave_value;
+use crate::board::play::Play;
+use crate::board::position::Position;
+use crate::game::game::{Action, Game};
+use crate::utils::error::Error;
+use crate::utils::trieguy::TrieTree;
+
+/// How many leaves `infer()` tries unless told otherwise.
+pub const DEFAULT_SAMPLES: usize = 64;
+
+/// How far in equity, in points, a play may fall short of the best one and
+/// still be likely: a play this much worse is `e` times less likely.
+pub const TEMPERATURE: f64 = 4.0;
+
+/// # RackInference
+/// What the opponent kept after their last move, as seen by the player to
+/// move. A play says something about the tiles behind it: a player holding
+/// an S and a good spot for it rarely plays a small word keeping it. Each
+/// leave the opponent could have kept is weighed by how likely they were
+/// to make their play had they held it, assuming they pick plays by
+/// equity; the rest of their rack was drawn from the bag, so it is
+/// uniform over the unseen tiles.
+/// Functions:
+/// - `uniform()`, `infer()`, `from_game()`, `rack_probability()`,
+///   `prior_probability()`, `draw_rack()`
+#[derive(Clone, Debug)]
+pub struct RackInference {
+    /// Every tile the player to move can't see: the opponent's rack and
+    /// the bag
+    pub pool: Vec<char>,
+    /// Each leave the opponent may have kept, sorted, with its probability,
+    /// most likely first; a single empty leave when nothing is known
+    pub leaves: Vec<(String, f64)>,
+    /// How many tiles the opponent holds now
+    pub rack_size: usize,
+}
+
+impl RackInference {
+    /// ### uniform()
+    /// Nothing is known: every rack of `rack_size` tiles from `pool` is as
+    /// likely as its draw.
+    pub fn uniform(pool: &[char], rack_size: usize) -> RackInference {
+        RackInference {
+            pool: pool.to_vec(),
+            leaves: vec![(String::new(), 1.0)],
+            rack_size: rack_size.min(pool.len()),
+        }
+    }
+
+    /// ### infer()
+    /// The opponent made `play` on `before`, keeping `kept` tiles, and now
+    /// holds `rack_size` tiles from `pool`. Tries `samples` leaves drawn
+    /// from the pool, as their rack was, and weighs each by the equity of
+    /// `play` against the best play the full rack had. The same `seed`
+    /// gives the same estimate.
+    #[allow(clippy::too_many_arguments)]
+    pub fn infer(
+        before: &Position,
+        play: &Play,
+        kept: usize,
+        pool: &[char],
+        rack_size: usize,
+        tree: &TrieTree,
+        samples: usize,
+        seed: u64,
+    ) -> RackInference {
+        let kept = kept.min(pool.len());
+        if kept == 0 {
+            return RackInference::uniform(pool, rack_size);
+        }
+        let played = play
+            .tiles
+            .chars()
+            .map(|tile| match tile.is_ascii_lowercase() {
+                true => '_',
+                false => tile,
+            })
+            .collect::<String>();
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut shuffled = pool.to_vec();
+        // The weight of each leave tried, by how often it was drawn
+        let mut weights: BTreeMap<String, f64> = BTreeMap::new();
+        let mut likelihoods: BTreeMap<String, f64> = BTreeMap::new();
+        for _ in 0..samples.max(1) {
+            let (chosen, _) = shuffled.partial_shuffle(&mut rng, kept);
+            let mut leave = chosen.to_vec();
+            leave.sort_by_key(|tile| (*tile == '_', *tile));
+            let leave: String = leave.into_iter().collect();
+            let likelihood = *likelihoods
+                .entry(leave.clone())
+                .or_insert_with(|| likelihood(before, play, &played, &leave, tree));
+            *weights.entry(leave).or_default() += likelihood;
+        }
+
+        let total: f64 = weights.values().sum();
+        if total <= 0.0 {
+            return RackInference::uniform(pool, rack_size);
+        }
+        let mut leaves = weights
+            .into_iter()
+            .map(|(leave, weight)| (leave, weight / total))
+            .collect::<Vec<(String, f64)>>();
+        leaves.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        RackInference {
+            pool: pool.to_vec(),
+            leaves,
+            rack_size: rack_size.min(pool.len()),
+        }
+    }
+
+    /// ### from_game()
+    /// Infer what the opponent of the player to move kept with their last
+    /// move. Only a play that stands says anything; after an exchange, a
+    /// pass or a withdrawn phony every rack is as likely as its draw.
+    pub fn from_game(
+        game: &Game,
+        tree: &TrieTree,
+        samples: usize,
+        seed: u64,
+    ) -> Result<RackInference, Error> {
+        let viewer = game.turn;
+        let opponent = 1 - viewer;
+        let pool = game.unseen(viewer);
+        let rack_size = game.racks[opponent].letters.len();
+        let last = game
+            .history
+            .iter()
+            .rposition(|turn| turn.player == opponent && turn.action != Action::Challenge);
+        let Some(last) = last else {
+            return Ok(RackInference::uniform(&pool, rack_size));
+        };
+        let turn = &game.history[last];
+        let play = match &turn.action {
+            Action::Play(play) if !turn.withdrawn => play,
+            _ => return Ok(RackInference::uniform(&pool, rack_size)),
+        };
+
+        // The board the play was made on; only public moves are replayed
+        let mut before = Game::new(game.seed);
+        before.challenge_rule = game.challenge_rule;
+        for turn in &game.history[..last] {
+            before.apply(&turn.action, tree)?;
+        }
+        // How many tiles they held is public, even if which ones isn't
+        let kept = turn.rack.chars().count() - play.tiles.chars().count();
+        Ok(RackInference::infer(
+            &before.position,
+            play,
+            kept,
+            &pool,
+            rack_size,
+            tree,
+            samples,
+            seed,
+        ))
+    }
+
+    /// ### rack_probability()
+    /// The chance the opponent holds at least one `tile` now, among the
+    /// tiles they kept or the ones they drew since.
+    pub fn rack_probability(&self, tile: char) -> f64 {
+        self.leaves
+            .iter()
+            .map(|(leave, p)| {
+                if leave.contains(tile) {
+                    return *p;
+                }
+                // The rest of the rack was drawn from what the leave left
+                let rest = self.pool.len() - leave.chars().count();
+                let others = rest - (self.pool.iter().filter(|t| **t == tile).count());
+                let drawn = self.rack_size.saturating_sub(leave.chars().count());
+                p * at_least_one(rest, others, drawn)
+            })
+            .sum()
+    }
+
+    /// ### prior_probability()
+    /// What `rack_probability()` would be had the play said nothing: the
+    /// chance a rack drawn blindly from the pool holds at least one `tile`.
+    pub fn prior_probability(&self, tile: char) -> f64 {
+        let others = self.pool.iter().filter(|t| **t != tile).count();
+        at_least_one(self.pool.len(), others, self.rack_size)
+    }
+
+    /// ### draw_rack()
+    /// A rack the opponent may hold: a leave drawn by its probability,
+    /// filled up with tiles drawn blindly from the rest of the pool.
+    pub fn draw_rack<R: Rng>(&self, rng: &mut R) -> Vec<char> {
+        let mut pick = rng.gen::<f64>();
+        let mut leave = "";
+        for (candidate, p) in &self.leaves {
+            leave = candidate;
+            pick -= p;
+            if pick <= 0.0 {
+                break;
+            }
+        }
+        let mut rest = self.pool.clone();
+        let mut rack = Vec::with_capacity(self.rack_size);
+        for tile in leave.chars() {
+            if let Some(i) = rest.iter().position(|t| *t == tile) {
+                rack.push(rest.swap_remove(i));
+            }
+        }
+        let fill = self.rack_size.saturating_sub(rack.len()).min(rest.len());
+        let (drawn, _) = rest.partial_shuffle(rng, fill);
+        rack.extend_from_slice(drawn);
+        rack
+    }
+}
+
+/// The chance that `drawn` tiles drawn from `total`, `others` of which
+/// aren't the tile wanted, include the tile: 1 - C(others, drawn) /
+/// C(total, drawn).
+fn at_least_one(total: usize, others: usize, drawn: usize) -> f64 {
+    let none: f64 = (0..drawn)
+        .map(|i| others.saturating_sub(i) as f64 / total.saturating_sub(i).max(1) as f64)
+        .product();
+    1.0 - none
+}
+
+/// How likely a player holding `played` and `leave` was to make `play`:
+/// 1 for the best play by equity, less the further below it `play` falls.
+fn likelihood(before: &Position, play: &Play, played: &str, leave: &str, tree: &TrieTree) -> f64 {
+    let Ok(rack) = Rack::from_letters(&format!("{}{}", played, leave)) else {
+        return 0.0;
+    };
+    let chosen = play.score as f64 + leave_value(leave);
+    let best = SearchController::new(SearchLimits::default())
+        .run_position(before, &rack, tree, 1)
+        .moves
+        .iter()
+        .map(|other| other.score as f64 + leave_value(&rack.leave(&other.tiles)))
+        .fold(chosen, f64::max);
+    ((chosen - best) / TEMPERATURE).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::lexicon::Lexicon;
+    use crate::utils::weighted_group::WeightedGroups;
+
+    fn lexicon() -> Lexicon {
+        let words = ["QI", "QIS", "ZA", "ZAS", "AS", "SI", "IS"];
+        Lexicon::from_words(words.iter().map(|w| w.to_string()))
+    }
+
+    /// `8H QI`, checked against `lexicon`, on an empty board.
+    fn qi(lexicon: &Lexicon) -> Play {
+        let play = Play::from_notation("8H QI").unwrap();
+        Position::new()
+            .check(&play, &lexicon.trie, &WeightedGroups::new())
+            .unwrap()
+    }
+
+    fn inference(pool: &str, leaves: &[(&str, f64)], rack_size: usize) -> RackInference {
+        RackInference {
+            pool: pool.chars().collect(),
+            leaves: leaves.iter().map(|(l, p)| (l.to_string(), *p)).collect(),
+            rack_size,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn the_prior_is_one_less_the_chance_of_no_s() {
+        // Two S's among eight tiles, three drawn: 1 - C(6, 3) / C(8, 3)
+        let pool: Vec<char> = "SSABCDEF".chars().collect();
+        let prior = RackInference::uniform(&pool, 3);
+        assert_close(prior.prior_probability('S'), 1.0 - 20.0 / 56.0);
+        assert_close(prior.rack_probability('S'), 1.0 - 20.0 / 56.0);
+        assert_close(prior.prior_probability('Z'), 0.0);
+        // No more is drawn than the pool holds
+        assert_eq!(RackInference::uniform(&pool, 20).rack_size, 8);
+    }
+
+    #[test]
+    fn keeping_nothing_says_nothing() {
+        let lexicon = lexicon();
+        let pool: Vec<char> = "SAZEIOU".chars().collect();
+        let inferred = RackInference::infer(
+            &Position::new(),
+            &qi(&lexicon),
+            0,
+            &pool,
+            7,
+            &lexicon.trie,
+            16,
+            1,
+        );
+        assert_eq!(inferred.leaves, [(String::new(), 1.0)]);
+        assert_close(
+            inferred.rack_probability('S'),
+            inferred.prior_probability('S'),
+        );
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_estimate() {
+        let lexicon = lexicon();
+        let play = qi(&lexicon);
+        let pool: Vec<char> = "SAZEIOU".chars().collect();
+        let infer = |seed| {
+            RackInference::infer(
+                &Position::new(),
+                &play,
+                1,
+                &pool,
+                3,
+                &lexicon.trie,
+                16,
+                seed,
+            )
+        };
+        let inferred = infer(7);
+        assert_eq!(inferred.leaves, infer(7).leaves);
+        assert_close(inferred.leaves.iter().map(|(_, p)| p).sum(), 1.0);
+        for (leave, _) in &inferred.leaves {
+            assert_eq!(leave.chars().count(), 1);
+            assert!(pool.contains(&leave.chars().next().unwrap()));
+        }
+        // Most likely first
+        assert!(inferred.leaves.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[test]
+    fn the_rack_holds_the_leave_and_a_blind_draw() {
+        // Kept the S or the A, then drew one of the three tiles left
+        let inferred = inference("SABC", &[("S", 0.5), ("A", 0.5)], 2);
+        assert_close(inferred.rack_probability('S'), 0.5 + 0.5 / 3.0);
+        assert_close(inferred.rack_probability('B'), 0.5 / 3.0 + 0.5 / 3.0);
+        assert_close(inferred.rack_probability('Z'), 0.0);
+    }
+
+    #[test]
+    fn drawn_racks_hold_the_leave() {
+        let inferred = inference("SABCD", &[("S", 1.0)], 3);
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..20 {
+            let mut rack = inferred.draw_rack(&mut rng);
+            assert_eq!(rack.len(), 3);
+            assert!(rack.contains(&'S'));
+            rack.sort();
+            rack.dedup();
+            assert_eq!(rack.len(), 3);
+            assert!(rack.iter().all(|tile| inferred.pool.contains(tile)));
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use std::fs;
+
+use crate::game::game::{Action, Game};
+use crate::game::record::Record;
+use crate::utils::error::Error;
+use crate::utils::trieguy::TrieTree;
+
+/// First line of a leave table written by `LeaveTable::save()`. Every
+/// following line is a term and its worth in points: a single tile (`S`),
+/// a duo (`ER`, or `EE` for a duplicate), or a vowel and consonant balance
+/// (`v1c4` for one vowel and four consonants). Wildcards are written `?`.
+pub const LEAVES_HEADER: &str = "#scrabble-leaves";
+
+/// How hard the fit pulls every term toward zero, in samples: a term seen
+/// in a handful of leaves stays small instead of fitting their noise.
+pub const DEFAULT_RIDGE: f64 = 20.0;
+
+/// Tiles are indexed `A` to `Z`, then the wildcard.
+const TILES: usize = 27;
+const DUOS: usize = TILES * (TILES + 1) / 2;
+/// Leaves hold at most seven tiles, so at most seven of either kind.
+const BALANCES: usize = 8 * 8;
+const TERMS: usize = TILES + DUOS + BALANCES;
+
+/// ## LeaveSample
+/// One leave a player kept and how it went: the points they scored on
+/// their next turn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeaveSample {
+    pub leave: String,
+    pub next_score: i32,
+}
+
+/// # LeaveTable
+/// The worth in points of keeping a leave, learned from games instead of
+/// guessed: each single tile, each duo (the synergy of two tiles together,
+/// or the cost of a duplicate) and each balance of vowels and consonants
+/// has a value, and a leave is worth the sum of its terms. A leave worth
+/// 0 scores as much next turn as the average leave did.
+/// Functions:
+/// - `value()`, `fit()`, `load()`, `save()`, `singles()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeaveTable {
+    pub singles: [f64; TILES],
+    /// Indexed by the two tiles, lower index first
+    pub duos: [[f64; TILES]; TILES],
+    /// Indexed by vowels, then consonants
+    pub balance: [[f64; 8]; 8],
+}
+
+impl Default for LeaveTable {
+    fn default() -> Self {
+        LeaveTable {
+            singles: [0.0; TILES],
+            duos: [[0.0; TILES]; TILES],
+            balance: [[0.0; 8]; 8],
+        }
+    }
+}
+
+impl LeaveTable {
+    /// ### value()
+    /// The worth of keeping `leave`.
+    pub fn value(&self, leave: &str) -> f64 {
+        terms(leave)
+            .into_iter()
+            .map(|term| match term {
+                Term::Single(a) => self.singles[a],
+                Term::Duo(a, b) => self.duos[a][b],
+                Term::Balance(v, c) => self.balance[v][c],
+            })
+            .sum()
+    }
+
+    /// ### fit()
+    /// The table that best predicts `samples`: a ridge regression of each
+    /// sample's next score, less the average, on the terms of its leave.
+    /// Only the next turn counts, not the leave that turn keeps in turn:
+    /// the tiles kept are spent or carried into that leave alongside fresh
+    /// draws, so its worth is mostly the draw's, and valuing it would need
+    /// the very table being fit. The same samples always give the same
+    /// table.
+    pub fn fit(samples: &[LeaveSample], ridge: f64) -> LeaveTable {
+        let mean =
+            samples.iter().map(|s| s.next_score as f64).sum::<f64>() / samples.len().max(1) as f64;
+
+        // The normal equations (XᵀX + ridge·I) w = Xᵀy
+        let mut matrix = vec![vec![0.0; TERMS]; TERMS];
+        let mut target = vec![0.0; TERMS];
+        for sample in samples {
+            let y = sample.next_score as f64 - mean;
+            let features = terms(&sample.leave)
+                .into_iter()
+                .map(|term| term.index())
+                .collect::<Vec<usize>>();
+            for &i in &features {
+                target[i] += y;
+                for &j in &features {
+                    matrix[i][j] += 1.0;
+                }
+            }
+        }
+        for (i, row) in matrix.iter_mut().enumerate() {
+            row[i] += ridge.max(1e-6);
+        }
+        let weights = solve(matrix, target);
+
+        let mut table = LeaveTable::default();
+        for a in 0..TILES {
+            table.singles[a] = weights[Term::Single(a).index()];
+            for b in a..TILES {
+                table.duos[a][b] = weights[Term::Duo(a, b).index()];
+            }
+        }
+        for v in 0..8 {
+            for c in 0..8 {
+                table.balance[v][c] = weights[Term::Balance(v, c).index()];
+            }
+        }
+        table
+    }
+
+    /// ### load()
+    /// Read a table written by `save()`; terms it doesn't list are worth 0.
+    pub fn load(filename: &str) -> Result<LeaveTable, Error> {
+        let text = fs::read_to_string(filename).map_err(|e| Error::in_file("read", filename, e))?;
+        let mut table = LeaveTable::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let failed = || {
+                Error::Parse(format!(
+                    "{}:{}: expected a term and its value, got \"{}\"",
+                    filename,
+                    number + 1,
+                    line
+                ))
+            };
+            let (key, value) = line.split_once(char::is_whitespace).ok_or_else(failed)?;
+            let value: f64 = value.trim().parse().map_err(|_| failed())?;
+            let indexes = key.chars().map(tile_index).collect::<Option<Vec<usize>>>();
+            match (indexes.as_deref(), balance_key(key)) {
+                (_, Some((v, c))) => table.balance[v][c] = value,
+                (Some(&[a]), _) => table.singles[a] = value,
+                (Some(&[a, b]), _) => table.duos[a.min(b)][a.max(b)] = value,
+                _ => return Err(failed()),
+            }
+        }
+        Ok(table)
+    }
+
+    /// ### save()
+    /// Write the table for `load()`, leaving out the terms worth 0.
+    pub fn save(&self, filename: &str) -> Result<(), Error> {
+        let mut text = format!("{}\n", LEAVES_HEADER);
+        for (a, value) in self.singles.iter().enumerate() {
+            if *value != 0.0 {
+                text.push_str(&format!("{} {:.3}\n", tile_name(a), value));
+            }
+        }
+        for a in 0..TILES {
+            for b in a..TILES {
+                if self.duos[a][b] != 0.0 {
+                    let duo = format!("{}{}", tile_name(a), tile_name(b));
+                    text.push_str(&format!("{} {:.3}\n", duo, self.duos[a][b]));
+                }
+            }
+        }
+        for (v, row) in self.balance.iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                if *value != 0.0 {
+                    text.push_str(&format!("v{}c{} {:.3}\n", v, c, value));
+                }
+            }
+        }
+        fs::write(filename, text).map_err(|e| Error::in_file("write", filename, e))?;
+        Ok(())
+    }
+
+    /// Each tile and its value, most valuable first.
+    pub fn singles(&self) -> Vec<(char, f64)> {
+        let mut singles = (0..TILES)
+            .map(|a| (tile_name(a), self.singles[a]))
+            .collect::<Vec<(char, f64)>>();
+        singles.sort_by(|a, b| b.1.total_cmp(&a.1));
+        singles
+    }
+}
+
+/// ### samples()
+/// Replay `record` and collect a sample for every leave kept by a play or
+/// an exchange while the bag still had tiles to draw, paired with what the
+/// player scored on their next turn. Withdrawn phonies are left out, as are
+/// moves the player never followed.
+pub fn samples(record: &Record, tree: &TrieTree) -> Result<Vec<LeaveSample>, Error> {
+    let mut game = Game::new(record.seed);
+    game.challenge_rule = record.challenge_rule;
+    // Each turn's player and leave, if it kept one worth sampling
+    let mut kept: Vec<(usize, Option<String>)> = Vec::new();
+    for turn in &record.turns {
+        let player = game.turn;
+        let leave = match &turn.action {
+            Action::Play(play) => Some(game.rack().leave(&play.tiles)),
+            Action::Exchange(tiles) => Some(game.rack().leave(tiles)),
+            _ => None,
+        };
+        game.apply(&turn.action, tree)?;
+        kept.push((player, leave.filter(|_| !game.bag.is_empty())));
+    }
+
+    let mut samples = Vec::new();
+    for (i, (player, leave)) in kept.iter().enumerate() {
+        let Some(leave) = leave else { continue };
+        if game.history[i].withdrawn {
+            continue;
+        }
+        let next = game.history[i + 1..]
+            .iter()
+            .find(|turn| turn.player == *player && turn.action != Action::Challenge);
+        if let Some(next) = next {
+            samples.push(LeaveSample {
+                leave: leave.clone(),
+                next_score: match next.withdrawn {
+                    true => 0,
+                    false => next.score,
+                },
+            });
+        }
+    }
+    Ok(samples)
+}
+
+/// A part of a leave that carries a value.
+#[derive(Clone, Copy, Debug)]
+enum Term {
+    Single(usize),
+    Duo(usize, usize),
+    Balance(usize, usize),
+}
+
+impl Term {
+    /// Where the term sits among the fit's unknowns.
+    fn index(self) -> usize {
+        match self {
+            Term::Single(a) => a,
+            // Duos with `a <= b`, row by row
+            Term::Duo(a, b) => TILES + a * TILES - a * (a.saturating_sub(1)) / 2 - a + b,
+            Term::Balance(v, c) => TILES + DUOS + v * 8 + c,
+        }
+    }
+}
+
+/// The terms of `leave`: a single per tile, a duo per pair of tiles and
+/// the balance of the whole.
+fn terms(leave: &str) -> Vec<Term> {
+    let tiles = leave.chars().filter_map(tile_index).collect::<Vec<usize>>();
+    let mut terms = tiles
+        .iter()
+        .map(|&a| Term::Single(a))
+        .collect::<Vec<Term>>();
+    for (i, &a) in tiles.iter().enumerate() {
+        for &b in &tiles[i + 1..] {
+            terms.push(Term::Duo(a.min(b), a.max(b)));
+        }
+    }
+    let vowels = leave.chars().filter(|c| "AEIOU".contains(*c)).count();
+    let consonants = tiles.iter().filter(|&&a| a < 26).count() - vowels;
+    terms.push(Term::Balance(vowels.min(7), consonants.min(7)));
+    terms
+}
+
+fn tile_index(tile: char) -> Option<usize> {
+    match tile {
+        'A'..='Z' => Some((tile as u8 - b'A') as usize),
+        '_' | '?' => Some(26),
+        _ => None,
+    }
+}
+
+fn tile_name(index: usize) -> char {
+    match index {
+        26 => '?',
+        _ => (b'A' + index as u8) as char,
+    }
+}
+
+/// `v1c4` as one vowel and four consonants.
+fn balance_key(key: &str) -> Option<(usize, usize)> {
+    let (vowels, consonants) = key.strip_prefix('v')?.split_once('c')?;
+    let (v, c) = (vowels.parse().ok()?, consonants.parse().ok()?);
+    (v < 8 && c < 8).then_some((v, c))
+}
+
+/// Solve `matrix · x = target` by Gaussian elimination with partial
+/// pivoting; the ridge keeps the matrix from being singular.
+fn solve(mut matrix: Vec<Vec<f64>>, mut target: Vec<f64>) -> Vec<f64> {
+    let n = target.len();
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))
+            .unwrap_or(column);
+        matrix.swap(column, pivot);
+        target.swap(column, pivot);
+        let (above, below) = matrix.split_at_mut(column + 1);
+        let row = &above[column];
+        for (offset, other) in below.iter_mut().enumerate() {
+            let factor = other[column] / row[column];
+            if factor == 0.0 {
+                continue;
+            }
+            for k in column..n {
+                other[k] -= factor * row[k];
+            }
+            target[column + 1 + offset] -= factor * target[column];
+        }
+    }
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (target[row] - sum) / matrix[row][row];
+    }
+    solution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_term_has_its_own_index() {
+        let mut terms: Vec<Term> = (0..TILES).map(Term::Single).collect();
+        for a in 0..TILES {
+            terms.extend((a..TILES).map(|b| Term::Duo(a, b)));
+        }
+        for v in 0..8 {
+            terms.extend((0..8).map(|c| Term::Balance(v, c)));
+        }
+        let mut seen = vec![false; TERMS];
+        for term in terms {
+            assert!(!seen[term.index()], "{:?}", term);
+            seen[term.index()] = true;
+        }
+        assert!(seen.iter().all(|s| *s));
+    }
+
+    #[test]
+    fn value_sums_singles_duos_and_balance() {
+        let mut table = LeaveTable::default();
+        table.singles[tile_index('S').unwrap()] = 8.0;
+        table.singles[26] = 25.0;
+        table.duos[tile_index('E').unwrap()][tile_index('S').unwrap()] = 1.5;
+        table.balance[1][1] = -2.0;
+        assert_eq!(table.value("ES"), 8.0 + 1.5 - 2.0);
+        assert_eq!(table.value("S?"), 8.0 + 25.0);
+    }
+
+    #[test]
+    fn fit_finds_the_tile_that_scores() {
+        // Leaves holding an S score 30 next turn, the rest 20
+        let samples: Vec<LeaveSample> = ["S", "AS", "ES", "A", "E", "AE"]
+            .iter()
+            .cycle()
+            .take(600)
+            .map(|leave| LeaveSample {
+                leave: leave.to_string(),
+                next_score: if leave.contains('S') { 30 } else { 20 },
+            })
+            .collect();
+        let table = LeaveTable::fit(&samples, DEFAULT_RIDGE);
+        assert_eq!(table.singles()[0].0, 'S');
+        assert!(table.value("S") - table.value("A") > 8.0);
+        assert_eq!(table, LeaveTable::fit(&samples, DEFAULT_RIDGE));
+    }
+
+    #[test]
+    fn save_and_load_keep_the_table() {
+        let mut table = LeaveTable::default();
+        table.singles[tile_index('Q').unwrap()] = -6.25;
+        table.duos[tile_index('Q').unwrap()][tile_index('U').unwrap()] = 4.5;
+        table.balance[0][5] = -3.0;
+        let file = std::env::temp_dir().join(format!("leaves-{}.txt", std::process::id()));
+        let filename = file.to_str().unwrap();
+        table.save(filename).unwrap();
+        let text = fs::read_to_string(filename).unwrap();
+        let loaded = LeaveTable::load(filename);
+        fs::remove_file(filename).unwrap();
+        assert_eq!(text, "#scrabble-leaves\nQ -6.250\nQU 4.500\nv0c5 -3.000\n");
+        assert_eq!(loaded.unwrap(), table);
+    }
+}
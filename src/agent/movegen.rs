@@ -0,0 +1,372 @@
+use crate::agent::rack::Rack;
+use crate::anagrams::anagrams::CHECK_INTERVAL;
+use crate::board::play::{Direction, Play};
+use crate::board::position::{Position, CENTER, SIZE};
+use crate::utils::trieguy::{TrieNode, TrieTree};
+use crate::utils::weighted_group::WeightedGroups;
+
+/// Every letter allowed on a square.
+const ANY_LETTER: u32 = (1 << 26) - 1;
+
+/// # MoveGenerator
+/// Finds every legal `Play` of a `Rack` on a `Position`. The board is
+/// searched one line at a time (15 rows read across, then 15 columns read
+/// down). For each line we work out which letters each empty square
+/// accepts given the tiles above and below it (its cross-check), then walk
+/// the `TrieTree` from every square a word may start on, placing rack
+/// tiles on empty squares and following the tiles already on the board.
+/// A word is kept when it ends in front of an empty square, places at
+/// least one tile and covers an anchor: an empty square next to a tile,
+/// or the center on an empty board.
+/// Functions:
+/// - `new()`, `generate()`, `lines()`, `line()`
+pub struct MoveGenerator<'a> {
+    position: &'a Position,
+    tree: &'a TrieTree,
+    values: WeightedGroups,
+    counts: [u8; 26],
+    wildcards: u8,
+}
+
+/// The number of lines `line()` can search: every row, then every column.
+pub const LINES: usize = 2 * SIZE;
+
+impl<'a> MoveGenerator<'a> {
+    pub fn new(position: &'a Position, rack: &Rack, tree: &'a TrieTree) -> Self {
+        let mut counts = [0; 26];
+        let mut wildcards = 0;
+        for letter in &rack.letters {
+            match letter {
+                '_' => wildcards += 1,
+                'A'..='Z' => counts[(*letter as u8 - b'A') as usize] += 1,
+                _ => {}
+            }
+        }
+        MoveGenerator {
+            position,
+            tree,
+            values: WeightedGroups::new(),
+            counts,
+            wildcards,
+        }
+    }
+
+    /// ### generate()
+    /// Every play on the board, best first.
+    pub fn generate(&self) -> Vec<Play> {
+        let mut plays: Vec<Play> = self
+            .lines()
+            .into_iter()
+            .flat_map(|unit| self.line(unit, &|_| false).0)
+            .collect();
+        plays.sort_by(|a, b| a.compare(b));
+        plays
+    }
+
+    /// ### line()
+    /// Every play along line `unit` (rows `0..15` across, then columns
+    /// `15..30` down), with the number of trie nodes visited and whether
+    /// `should_stop` cut the search short. `should_stop` is called every
+    /// `CHECK_INTERVAL` nodes, as in `Anagrams::generate()`.
+    pub fn line(&self, unit: usize, should_stop: &dyn Fn(u64) -> bool) -> (Vec<Play>, u64, bool) {
+        let (direction, line) = unit_line(unit);
+        let mut walk = Line {
+            direction,
+            line,
+            tiles: [None; SIZE],
+            allowed: [ANY_LETTER; SIZE],
+            crossed: [false; SIZE],
+            anchor: [false; SIZE],
+            counts: self.counts,
+            wildcards: self.wildcards,
+            word: String::new(),
+            plays: Vec::new(),
+            nodes: 0,
+            stopped: false,
+            should_stop,
+        };
+        self.prepare(&mut walk);
+
+        let rack_size = self.counts.iter().sum::<u8>() as usize + self.wildcards as usize;
+        for start in 0..SIZE {
+            // A word can't start right after a tile, it would include it
+            if start > 0 && walk.tiles[start - 1].is_some() {
+                continue;
+            }
+            // Skip starts that run out of tiles before reaching an anchor
+            let anchor = match (start..SIZE).find(|i| walk.anchor[*i]) {
+                Some(anchor) => anchor,
+                None => break,
+            };
+            let empty = (start..=anchor)
+                .filter(|i| walk.tiles[*i].is_none())
+                .count();
+            if empty > rack_size {
+                continue;
+            }
+            self.extend(&mut walk, start, start, &self.tree.root, 0, false);
+            if walk.stopped {
+                break;
+            }
+        }
+        (walk.plays, walk.nodes, walk.stopped)
+    }
+
+    /// ### lines()
+    /// The lines worth searching, best first: only lines with an anchor
+    /// can hold a play, and the more anchors a line has the more plays it
+    /// is likely to hold, so an anytime search looks there first.
+    pub fn lines(&self) -> Vec<usize> {
+        let empty_board = self.position.is_empty();
+        let mut lines: Vec<(usize, usize)> = (0..LINES)
+            .map(|unit| {
+                let (direction, line) = unit_line(unit);
+                let anchors = (0..SIZE)
+                    .filter(|i| {
+                        let (row, col) = direction.square(line, *i);
+                        self.position.get(row, col).is_none()
+                            && self.is_anchor(row, col, empty_board)
+                    })
+                    .count();
+                (unit, anchors)
+            })
+            .filter(|(_, anchors)| *anchors > 0)
+            .collect();
+        lines.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        lines.into_iter().map(|(unit, _)| unit).collect()
+    }
+
+    /// An empty square next to a tile, or the center on an empty board.
+    fn is_anchor(&self, row: usize, col: usize, empty_board: bool) -> bool {
+        if empty_board {
+            return (row, col) == (CENTER, CENTER);
+        }
+        let neighbours = [
+            (row.wrapping_sub(1), col),
+            (row + 1, col),
+            (row, col.wrapping_sub(1)),
+            (row, col + 1),
+        ];
+        neighbours
+            .iter()
+            .any(|(r, c)| *r < SIZE && *c < SIZE && self.position.get(*r, *c).is_some())
+    }
+
+    /// Find the tiles, cross-checks and anchors of the line.
+    fn prepare(&self, walk: &mut Line) {
+        let empty_board = self.position.is_empty();
+        for i in 0..SIZE {
+            let (row, col) = walk.direction.square(walk.line, i);
+            walk.tiles[i] = self.position.get(row, col);
+            if walk.tiles[i].is_some() {
+                continue;
+            }
+            if let Some(cross) = self.position.cross_word(row, col, walk.direction.other()) {
+                walk.crossed[i] = true;
+                walk.allowed[i] = 0;
+                let cross = cross.to_ascii_uppercase();
+                for (bit, letter) in ('A'..='Z').enumerate() {
+                    if self.tree.search(&cross.replace('.', &letter.to_string())) {
+                        walk.allowed[i] |= 1 << bit;
+                    }
+                }
+            }
+            walk.anchor[i] = self.is_anchor(row, col, empty_board);
+        }
+    }
+
+    /// ### extend()
+    /// Extend the word ending before square `index` with the trie `node`
+    /// reached so far: record it if it is complete, then follow the tile
+    /// on the square, or try every rack tile the square accepts.
+    fn extend(
+        &self,
+        walk: &mut Line,
+        start: usize,
+        index: usize,
+        node: &TrieNode,
+        placed: usize,
+        anchored: bool,
+    ) {
+        walk.nodes += 1;
+        if walk.nodes.is_multiple_of(CHECK_INTERVAL) && (walk.should_stop)(walk.nodes) {
+            walk.stopped = true;
+        }
+        if walk.stopped {
+            return;
+        }
+        let open = index == SIZE || walk.tiles[index].is_none();
+        if open && node.is_end_of_word && placed > 0 && anchored && walk.word.len() >= 2 {
+            self.record(walk, start, placed);
+        }
+        if index == SIZE {
+            return;
+        }
+
+        if let Some(tile) = walk.tiles[index] {
+            // Play through the tile on the board
+            if let Some(child) = node.children.get(&tile.to_ascii_uppercase()) {
+                walk.word.push(tile);
+                self.extend(walk, start, index + 1, child, placed, anchored);
+                walk.word.pop();
+            }
+            return;
+        }
+        let anchored = anchored || walk.anchor[index];
+        for (letter, child) in &node.children {
+            if !letter.is_ascii_uppercase() {
+                continue;
+            }
+            let i = (*letter as u8 - b'A') as usize;
+            if walk.allowed[index] & (1 << i) == 0 {
+                continue;
+            }
+            // Play the letter from the rack
+            if walk.counts[i] > 0 {
+                walk.counts[i] -= 1;
+                walk.word.push(*letter);
+                self.extend(walk, start, index + 1, child, placed + 1, anchored);
+                walk.word.pop();
+                walk.counts[i] += 1;
+            }
+            // Or play a wildcard as the letter
+            if walk.wildcards > 0 {
+                walk.wildcards -= 1;
+                walk.word.push(letter.to_ascii_lowercase());
+                self.extend(walk, start, index + 1, child, placed + 1, anchored);
+                walk.word.pop();
+                walk.wildcards += 1;
+            }
+        }
+    }
+
+    /// Score the word found from `start` and add it to the plays.
+    fn record(&self, walk: &mut Line, start: usize, placed: usize) {
+        let placed_at: Vec<usize> = (start..start + walk.word.len())
+            .filter(|i| walk.tiles[*i].is_none())
+            .collect();
+        // A single tile that also forms a word across was already found
+        // when searching the rows
+        if walk.direction == Direction::Down && placed == 1 && walk.crossed[placed_at[0]] {
+            return;
+        }
+        let (row, col) = walk.direction.square(walk.line, start);
+        let tiles = walk
+            .word
+            .chars()
+            .enumerate()
+            .filter(|(i, _)| walk.tiles[start + i].is_none())
+            .map(|(_, letter)| letter)
+            .collect();
+        let mut play = Play {
+            row,
+            col,
+            direction: walk.direction,
+            word: walk.word.clone(),
+            tiles,
+            score: 0,
+        };
+        play.score = self.position.score(&play, &self.values);
+        walk.plays.push(play);
+    }
+}
+
+/// The direction and row or column of line `unit`.
+fn unit_line(unit: usize) -> (Direction, usize) {
+    match unit < SIZE {
+        true => (Direction::Across, unit),
+        false => (Direction::Down, unit - SIZE),
+    }
+}
+
+/// The state of a search along one line: its tiles, which letters each
+/// empty square accepts, the tiles left on the rack and the plays found.
+struct Line<'a> {
+    direction: Direction,
+    line: usize,
+    tiles: [Option<char>; SIZE],
+    allowed: [u32; SIZE],
+    crossed: [bool; SIZE],
+    anchor: [bool; SIZE],
+    counts: [u8; 26],
+    wildcards: u8,
+    word: String,
+    plays: Vec<Play>,
+    nodes: u64,
+    stopped: bool,
+    should_stop: &'a dyn Fn(u64) -> bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::lexicon::Lexicon;
+
+    fn lexicon(words: &[&str]) -> Lexicon {
+        Lexicon::from_words(words.iter().map(|word| word.to_string()))
+    }
+
+    /// An empty board but for `row` on row 8.
+    fn with_row_8(row: &str) -> Position {
+        let empty = ["15"; 7].join("/");
+        Position::parse(&format!("{}/{}/{}", empty, row, empty)).unwrap()
+    }
+
+    fn generate(position: &Position, rack: &str, lexicon: &Lexicon) -> Vec<Play> {
+        let rack = Rack::from_letters(rack).unwrap();
+        MoveGenerator::new(position, &rack, &lexicon.trie).generate()
+    }
+
+    fn notations(plays: &[Play]) -> Vec<String> {
+        plays.iter().map(|play| play.notation()).collect()
+    }
+
+    #[test]
+    fn first_plays_cover_the_center_both_ways() {
+        let lexicon = lexicon(&["QUIZ"]);
+        let plays = generate(&Position::new(), "QUIZXYZ", &lexicon);
+        // Four squares to start on across, four down
+        assert_eq!(plays.len(), 8);
+        assert_eq!(plays[0].score, 44);
+        let values = WeightedGroups::new();
+        for play in &plays {
+            let checked = Position::new().check(play, &lexicon.trie, &values);
+            assert_eq!(checked.unwrap().score, play.score, "{}", play.notation());
+        }
+    }
+
+    #[test]
+    fn plays_hook_extend_and_cross_tiles_on_the_board() {
+        let lexicon = lexicon(&["AS", "CAT", "CATS", "TA"]);
+        let position = with_row_8("7CAT5");
+        let plays = generate(&position, "AS", &lexicon);
+        let found = notations(&plays);
+        for expected in ["8H CATS", "K7 AS", "J8 TA"] {
+            assert!(found.contains(&expected.to_string()), "{:?}", found);
+        }
+        let values = WeightedGroups::new();
+        for play in &plays {
+            let checked = position.check(play, &lexicon.trie, &values);
+            assert_eq!(checked.unwrap().score, play.score, "{}", play.notation());
+        }
+        let best = plays.iter().map(|play| play.score).max();
+        assert_eq!(plays[0].score, best.unwrap());
+    }
+
+    #[test]
+    fn wildcards_play_as_any_letter_for_nothing() {
+        let lexicon = lexicon(&["QUIZ"]);
+        let plays = generate(&Position::new(), "QUI?", &lexicon);
+        assert!(!plays.is_empty());
+        assert!(plays.iter().all(|play| play.word == "QUIz"));
+        assert_eq!(plays[0].score, 2 * (10 + 1 + 1));
+    }
+
+    #[test]
+    fn no_plays_without_the_letters() {
+        let lexicon = lexicon(&["QUIZ"]);
+        assert!(generate(&Position::new(), "AEIOU", &lexicon).is_empty());
+        let position = with_row_8("7CAT5");
+        assert!(generate(&position, "Q", &lexicon).is_empty());
+    }
+}
@@ -1,5 +1,8 @@
+use crate::agent::bag::Bag;
+use crate::utils::error::Error;
 use crate::utils::weighted_group::WeightedGroups;
 use rand::seq::SliceRandom;
+use rand::Rng;
 
 /// # Rack
 /// The rack is the 7 `letters` the agent has to solve the puzzle. Every rack is a
@@ -7,7 +10,7 @@ use rand::seq::SliceRandom;
 /// Letters are broken up into `frequency_groups` that represent the
 /// frequency of those letters in a bag of scrabble letters.
 /// Functions:
-/// - `display()`
+/// - `from_rng()`, `from_bag()`, `from_letters()`, `leave()`, `display()`
 pub struct Rack {
     pub letters: Vec<char>,
     pub weighted_groups: WeightedGroups,
@@ -22,6 +25,12 @@ impl Clone for Rack {
     }
 }
 
+impl Default for Rack {
+    fn default() -> Self {
+        Rack::new()
+    }
+}
+
 impl Rack {
     /// Create a new rack with 7 letters.
     /// ## Overview
@@ -31,25 +40,26 @@ impl Rack {
     /// we have our rack.
     /// ## Example
     /// ```
+    /// use scrabble::Rack;
+    ///
     /// let rack = Rack::new();
-    /// rack.display();
+    /// assert_eq!(rack.letters.len(), 7);
     /// ```
     pub fn new() -> Rack {
+        Rack::from_rng(&mut rand::thread_rng())
+    }
+
+    /// ### from_rng()
+    /// Same as `new()`, but the bag is shuffled with the given random
+    /// number generator so a seeded generator always draws the same rack.
+    pub fn from_rng<R: Rng>(rng: &mut R) -> Rack {
         let mut letters: Vec<char> = Vec::new();
         let weighted_groups = WeightedGroups::new();
-        let mut bag: Vec<char> = Vec::new();
-        // Iterate through weighted groups, adding each letter in each group
-        // as many times as its weight
-        for entry in &weighted_groups.frequency_groups {
-            for _ in 0..entry.weight {
-                for letter in &entry.letters {
-                    bag.push(*letter);
-                }
-            }
-        }
+        // Every letter of the weighted groups, as many times as its weight
+        let mut bag: Vec<char> = Bag::new().tiles().to_vec();
         for _ in 0..7 {
             // Shuffle the bag
-            bag.shuffle(&mut rand::thread_rng());
+            bag.shuffle(rng);
             // Iterate over the shuffled bag
             for ltr in &bag {
                 // If the letter is a wildcard & letters already contains 2
@@ -68,6 +78,66 @@ impl Rack {
         }
     }
 
+    /// ### from_bag()
+    /// Draw a rack of up to 7 tiles from `bag`, taking them out of it.
+    pub fn from_bag<R: Rng>(bag: &mut Bag, rng: &mut R) -> Rack {
+        Rack {
+            letters: bag.draw(7, rng),
+            weighted_groups: WeightedGroups::new(),
+        }
+    }
+
+    /// ### from_letters()
+    /// Build a rack from user input such as `"AEINST?"`. Letters are
+    /// case-insensitive and a wildcard may be written as `_` or `?`.
+    /// A rack holds between 1 and 7 tiles, at most 2 of them wildcards.
+    pub fn from_letters(input: &str) -> Result<Rack, Error> {
+        let mut letters: Vec<char> = Vec::new();
+        for c in input.chars() {
+            match c {
+                '_' | '?' => letters.push('_'),
+                c if c.is_ascii_alphabetic() => letters.push(c.to_ascii_uppercase()),
+                _ => {
+                    return Err(Error::InvalidRack(format!(
+                        "'{}' is not a valid tile in rack \"{}\"",
+                        c, input
+                    )))
+                }
+            }
+        }
+        if letters.is_empty() || letters.len() > 7 {
+            return Err(Error::InvalidRack(format!(
+                "a rack holds 1 to 7 tiles, \"{}\" has {}",
+                input,
+                letters.len()
+            )));
+        }
+        if letters.iter().filter(|&x| *x == '_').count() > 2 {
+            return Err(Error::InvalidRack(format!(
+                "rack \"{}\" has more than 2 wildcards",
+                input
+            )));
+        }
+        Ok(Rack {
+            letters,
+            weighted_groups: WeightedGroups::new(),
+        })
+    }
+
+    /// ### leave()
+    /// The tiles left on the rack after playing `tiles`; a lowercase
+    /// letter was played with a wildcard.
+    pub fn leave(&self, tiles: &str) -> String {
+        let mut left = self.letters.clone();
+        for tile in tiles.chars() {
+            let tile = if tile.is_ascii_lowercase() { '_' } else { tile };
+            if let Some(i) = left.iter().position(|l| *l == tile) {
+                left.remove(i);
+            }
+        }
+        left.into_iter().collect()
+    }
+
     pub fn display(&self) {
         println!("\n\nRack:");
         for c in &self.letters {
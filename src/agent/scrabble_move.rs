@@ -1,18 +1,44 @@
+use serde_json::{json, Value};
+
 use crate::anagrams::anagrams::Anagram;
 
 /// ## ScrabbleMove
 /// A simple struct that holds the result of
-/// the find_best_move() function
+/// the find_best_move() function: the word, the element
+/// it starts on and the score it earns there.
+/// Functions:
+/// - `coordinates()`, `to_json()`
 pub struct ScrabbleMove {
     pub anagram: Anagram,
     pub starting_element: u32,
+    pub score: u32,
 }
 
 impl ScrabbleMove {
-    pub fn new(anagram: Anagram, starting_element: u32) -> Self {
+    pub fn new(anagram: Anagram, starting_element: u32, score: u32) -> Self {
         ScrabbleMove {
             anagram,
             starting_element,
+            score,
         }
     }
+
+    /// ### coordinates()
+    /// The square the word starts on in standard notation. The `Board` is
+    /// row 8 of the full board from column B to N, so element 6 is `8H`.
+    pub fn coordinates(&self) -> String {
+        format!("8{}", (b'B' + self.starting_element as u8) as char)
+    }
+
+    /// ### to_json()
+    /// The word, where it goes, its score and the anagram's estimate.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "word": self.anagram.word,
+            "start": self.starting_element,
+            "coordinates": self.coordinates(),
+            "score": self.score,
+            "estimate": self.anagram.score,
+        })
+    }
 }
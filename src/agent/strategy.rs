@@ -0,0 +1,356 @@
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::agent::controller::{SearchController, SearchLimits};
+use crate::agent::inference::RackInference;
+use crate::agent::leaves::LeaveTable;
+use crate::agent::rack::Rack;
+use crate::board::play::Play;
+use crate::board::position::Position;
+use crate::game::game::{Action, Game, RACK_SIZE};
+use crate::utils::error::Error;
+use crate::utils::trieguy::TrieTree;
+
+/// The names `by_name()` knows, strongest first. `equity:FILE` is the
+/// equity strategy valuing leaves by the `LeaveTable` in `FILE`, and
+/// `sim:uniform` simulates without inferring the opponent's rack.
+pub const STRATEGIES: [&str; 4] = ["sim", "equity", "greedy", "random"];
+
+/// How many of the best plays by equity `Simulation` looks further into.
+pub const SIM_CANDIDATES: usize = 4;
+
+/// How many opponent racks `Simulation` tries against each candidate.
+pub const SIM_ITERATIONS: usize = 8;
+
+/// How many leaves `Simulation` weighs when inferring the opponent's rack.
+pub const SIM_SAMPLES: usize = 24;
+
+/// ## Strategy
+/// How a player picks a move on the full board. Given the game, with the
+/// player to move being the one to choose for, a strategy returns a
+/// play, an exchange or a pass; `Game` then checks and makes it.
+pub trait Strategy: Send {
+    /// The name the strategy is chosen by, e.g. on the command line.
+    fn name(&self) -> &str;
+
+    /// Pick the move for the player to move in `game`.
+    fn choose(&mut self, game: &Game, tree: &TrieTree) -> Action;
+
+    /// Whether to challenge the play just made, when the rules allow it.
+    /// By default a strategy knows the lexicon and challenges exactly the
+    /// phonies.
+    fn challenge(&mut self, game: &Game, tree: &TrieTree) -> bool {
+        !game.pending_phonies(tree).is_empty()
+    }
+}
+
+/// ### by_name()
+/// The strategy called `name`, searching with `limits` on `threads`
+/// threads. `seed` makes the random strategy repeatable.
+pub fn by_name(
+    name: &str,
+    limits: SearchLimits,
+    threads: usize,
+    seed: u64,
+) -> Result<Box<dyn Strategy>, Error> {
+    if let Some(("equity", file)) = name.split_once(':') {
+        return Ok(Box::new(Equity {
+            name: name.to_string(),
+            limits,
+            threads,
+            leaves: Some(LeaveTable::load(file)?),
+        }));
+    }
+    match name {
+        "equity" => Ok(Box::new(Equity {
+            name: name.to_string(),
+            limits,
+            threads,
+            leaves: None,
+        })),
+        "greedy" => Ok(Box::new(Greedy { limits, threads })),
+        "sim" | "sim:uniform" => Ok(Box::new(Simulation {
+            name: name.to_string(),
+            limits,
+            threads,
+            infer: name == "sim",
+            rng: StdRng::seed_from_u64(seed),
+        })),
+        "random" => Ok(Box::new(RandomPlay {
+            threads,
+            rng: StdRng::seed_from_u64(seed),
+        })),
+        _ => Err(Error::Parse(format!(
+            "unknown strategy \"{}\", expected one of {}, sim:uniform or equity:FILE",
+            name,
+            STRATEGIES.join(", ")
+        ))),
+    }
+}
+
+/// ## Greedy
+/// Always makes the highest scoring play, whatever it leaves on the rack.
+pub struct Greedy {
+    pub limits: SearchLimits,
+    pub threads: usize,
+}
+
+impl Strategy for Greedy {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn choose(&mut self, game: &Game, tree: &TrieTree) -> Action {
+        match plays(game, tree, self.limits, self.threads)
+            .into_iter()
+            .next()
+        {
+            Some(best) => Action::Play(best),
+            None => fallback(game),
+        }
+    }
+}
+
+/// ## Equity
+/// Weighs each play's score against the tiles it keeps: the play with the
+/// best score plus the leave's value wins. While the bag holds a full
+/// rack, exchanging is weighed too, keeping whichever tiles leave the most.
+/// Leaves are valued by `leaves` when a table is loaded, by the hand-tuned
+/// `leave_value()` otherwise.
+pub struct Equity {
+    pub name: String,
+    pub limits: SearchLimits,
+    pub threads: usize,
+    pub leaves: Option<LeaveTable>,
+}
+
+impl Equity {
+    fn leave_value(&self, leave: &str) -> f64 {
+        match &self.leaves {
+            Some(table) => table.value(leave),
+            None => leave_value(leave),
+        }
+    }
+}
+
+impl Strategy for Equity {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose(&mut self, game: &Game, tree: &TrieTree) -> Action {
+        let rack = game.rack();
+        let mut best: Option<(f64, Action)> = None;
+        for play in plays(game, tree, self.limits, self.threads) {
+            let equity = play.score as f64 + self.leave_value(&rack.leave(&play.tiles));
+            if best.as_ref().is_none_or(|(value, _)| equity > *value) {
+                best = Some((equity, Action::Play(play)));
+            }
+        }
+
+        if game.bag.len() >= RACK_SIZE {
+            // Every way to split the rack into tiles kept and tiles returned
+            let letters = &rack.letters;
+            for mask in 0..(1u32 << letters.len()) - 1 {
+                let (mut kept, mut returned) = (String::new(), String::new());
+                for (i, tile) in letters.iter().enumerate() {
+                    match mask & (1 << i) != 0 {
+                        true => kept.push(*tile),
+                        false => returned.push(*tile),
+                    }
+                }
+                let equity = self.leave_value(&kept);
+                if best.as_ref().is_none_or(|(value, _)| equity > *value) {
+                    best = Some((equity, Action::Exchange(returned)));
+                }
+            }
+        }
+
+        match best {
+            Some((_, action)) => action,
+            None => fallback(game),
+        }
+    }
+}
+
+/// ## Simulation
+/// Looks one move further than `Equity`: each of the best plays by equity
+/// is tried against opponent racks drawn from the unseen tiles, and the
+/// opponent's best reply is taken off its equity. With `infer`, the racks
+/// are drawn from what a `RackInference` makes of the opponent's last play
+/// rather than uniformly. Every candidate meets the same racks, and with a
+/// time limit the racks stop coming once it has passed.
+pub struct Simulation {
+    pub name: String,
+    pub limits: SearchLimits,
+    pub threads: usize,
+    pub infer: bool,
+    pub rng: StdRng,
+}
+
+impl Strategy for Simulation {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose(&mut self, game: &Game, tree: &TrieTree) -> Action {
+        let deadline = self.limits.time.map(|time| Instant::now() + time);
+        let rack = game.rack();
+        let mut candidates = plays(game, tree, self.limits, self.threads)
+            .into_iter()
+            .map(|play| {
+                let equity = play.score as f64 + leave_value(&rack.leave(&play.tiles));
+                (equity, play)
+            })
+            .collect::<Vec<(f64, Play)>>();
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        candidates.truncate(SIM_CANDIDATES);
+        if candidates.len() < 2 {
+            return match candidates.pop() {
+                Some((_, play)) => Action::Play(play),
+                None => fallback(game),
+            };
+        }
+
+        let seed = self.rng.gen();
+        let opponent = match self.infer {
+            true => RackInference::from_game(game, tree, SIM_SAMPLES, seed).ok(),
+            false => None,
+        };
+        let opponent = opponent.unwrap_or_else(|| {
+            RackInference::uniform(
+                &game.unseen(game.turn),
+                game.racks[1 - game.turn].letters.len(),
+            )
+        });
+        let racks = (0..SIM_ITERATIONS)
+            .filter_map(|_| {
+                let letters: String = opponent.draw_rack(&mut self.rng).into_iter().collect();
+                Rack::from_letters(&letters).ok()
+            })
+            .collect::<Vec<Rack>>();
+        if racks.is_empty() {
+            return Action::Play(candidates.swap_remove(0).1);
+        }
+
+        let positions = candidates
+            .iter()
+            .map(|(_, play)| {
+                let mut after = game.position.clone();
+                after.place(play);
+                after
+            })
+            .collect::<Vec<Position>>();
+        // Rack by rack, so every candidate has met as many when time is up
+        let mut replies = vec![0.0; candidates.len()];
+        let mut tried = 0;
+        for rack in &racks {
+            for (after, total) in positions.iter().zip(replies.iter_mut()) {
+                *total += SearchController::new(SearchLimits::default())
+                    .run_position(after, rack, tree, self.threads)
+                    .moves
+                    .first()
+                    .map_or(0.0, |reply| reply.score as f64);
+            }
+            tried += 1;
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+        }
+
+        let mut best: Option<(f64, Play)> = None;
+        for ((equity, play), total) in candidates.into_iter().zip(replies) {
+            let value = equity - total / tried as f64;
+            if best.as_ref().is_none_or(|(top, _)| value > *top) {
+                best = Some((value, play));
+            }
+        }
+        match best {
+            Some((_, play)) => Action::Play(play),
+            None => fallback(game),
+        }
+    }
+}
+
+/// ## RandomPlay
+/// Makes any legal play at random: a baseline every other strategy should
+/// beat comfortably.
+pub struct RandomPlay {
+    pub threads: usize,
+    pub rng: StdRng,
+}
+
+impl Strategy for RandomPlay {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn choose(&mut self, game: &Game, tree: &TrieTree) -> Action {
+        let plays = plays(game, tree, SearchLimits::default(), self.threads);
+        match plays.choose(&mut self.rng) {
+            Some(play) => Action::Play(play.clone()),
+            None => fallback(game),
+        }
+    }
+}
+
+/// ### leave_value()
+/// A rough, hand-tuned worth in points of keeping `leave` for the next
+/// turn: wildcards and S help build bingos, Q, V and duplicates get in
+/// the way, and a mix of vowels and consonants beats a lopsided one.
+pub fn leave_value(leave: &str) -> f64 {
+    let mut value = 0.0;
+    let mut counts = [0usize; 27];
+    for tile in leave.chars() {
+        value += match tile {
+            '_' | '?' => 25.0,
+            'S' => 8.0,
+            'X' => 3.5,
+            'Z' => 2.5,
+            'E' | 'R' => 1.5,
+            'A' | 'H' => 1.0,
+            'C' | 'D' | 'M' | 'N' => 0.5,
+            'T' => 0.0,
+            'I' | 'L' | 'P' | 'Y' => -0.5,
+            'O' => -1.0,
+            'K' => -1.5,
+            'B' | 'F' => -2.0,
+            'G' | 'J' => -2.5,
+            'U' => -3.0,
+            'W' => -4.0,
+            'V' => -5.5,
+            'Q' => -7.0,
+            _ => 0.0,
+        };
+        match tile {
+            'A'..='Z' => counts[(tile as u8 - b'A') as usize] += 1,
+            _ => counts[26] += 1,
+        }
+    }
+    let duplicates: usize = counts[..26].iter().map(|n| n.saturating_sub(1)).sum();
+    value -= 3.0 * duplicates as f64;
+
+    let vowels = leave.chars().filter(|c| "AEIOU".contains(*c)).count() as f64;
+    let consonants = (leave.chars().count() - counts[26]) as f64 - vowels;
+    value -= 2.0 * ((vowels - consonants).abs() - 1.0).max(0.0);
+    value
+}
+
+/// Every play for the player to move, best scoring first.
+fn plays(game: &Game, tree: &TrieTree, limits: SearchLimits, threads: usize) -> Vec<Play> {
+    SearchController::new(limits)
+        .run_position(&game.position, game.rack(), tree, threads)
+        .moves
+}
+
+/// With no play to make: exchange the whole rack if the bag allows it,
+/// otherwise pass.
+fn fallback(game: &Game) -> Action {
+    match game.bag.len() >= RACK_SIZE && !game.rack().letters.is_empty() {
+        true => Action::Exchange(game.rack().letters.iter().collect()),
+        false => Action::Pass,
+    }
+}
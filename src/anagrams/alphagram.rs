@@ -0,0 +1,163 @@
+use std::collections::{BTreeSet, HashMap};
+
+/// ### alphagram()
+/// The letters of a word in alphabetical order; every anagram of a
+/// word shares its alphagram, e.g. `HEART` and `EARTH` are both `AEHRT`.
+pub fn alphagram(word: &str) -> String {
+    let mut letters: Vec<char> = word.chars().collect();
+    letters.sort_unstable();
+    letters.into_iter().collect()
+}
+
+/// # AlphagramIndex
+/// Maps every alphagram in the lexicon to the words spelled with exactly
+/// those letters. Exact anagrams are a single lookup; blank anagrams and
+/// subanagrams enumerate the candidate alphagrams of the rack and look
+/// each one up, instead of searching letter by letter.
+/// Functions:
+/// - `insert()`, `anagrams()`, `blank_anagrams()`, `subanagrams()`, `groups()`
+pub struct AlphagramIndex {
+    words: HashMap<String, Vec<String>>,
+}
+
+impl Default for AlphagramIndex {
+    fn default() -> Self {
+        AlphagramIndex::new()
+    }
+}
+
+impl AlphagramIndex {
+    pub fn new() -> Self {
+        AlphagramIndex {
+            words: HashMap::new(),
+        }
+    }
+
+    /// ### insert()
+    /// Add a word under its alphagram.
+    pub fn insert(&mut self, word: &str) {
+        self.words
+            .entry(alphagram(word))
+            .or_default()
+            .push(word.to_string());
+    }
+
+    /// ### insert_group()
+    /// Add words that are already known to share `key` as their
+    /// alphagram, as read from a compiled lexicon.
+    pub fn insert_group(&mut self, key: &str, words: Vec<String>) {
+        self.words.entry(key.to_string()).or_default().extend(words);
+    }
+
+    /// ### anagrams()
+    /// Words that use exactly these letters.
+    pub fn anagrams(&self, letters: &str) -> &[String] {
+        self.words
+            .get(&alphagram(letters))
+            .map_or(&[], |words| words.as_slice())
+    }
+
+    /// ### blank_anagrams()
+    /// Words that use every tile of the rack, where `?` or `_` is a
+    /// blank standing for any letter.
+    pub fn blank_anagrams(&self, rack: &str) -> Vec<String> {
+        let (letters, blanks) = split_blanks(rack);
+        if blanks == 0 {
+            return self.anagrams(&letters).to_vec();
+        }
+        let mut keys: BTreeSet<String> = BTreeSet::new();
+        for fill in blank_fills(blanks) {
+            keys.insert(alphagram(&format!("{}{}", letters, fill)));
+        }
+        self.collect(keys, 0)
+    }
+
+    /// ### subanagrams()
+    /// Words of at least `min_length` letters that use any of the tiles on
+    /// the rack, longest first. Every distinct sub-multiset of the rack
+    /// (with each blank either unused or filled with a letter) is looked up
+    /// once.
+    pub fn subanagrams(&self, rack: &str, min_length: usize) -> Vec<String> {
+        let (letters, blanks) = split_blanks(rack);
+        let letters: Vec<char> = alphagram(&letters).chars().collect();
+        let mut subsets: BTreeSet<String> = BTreeSet::new();
+        sub_multisets(&letters, 0, &mut String::new(), &mut subsets);
+
+        let mut keys: BTreeSet<String> = BTreeSet::new();
+        for used in 0..=blanks {
+            let fills = blank_fills(used);
+            for subset in &subsets {
+                for fill in &fills {
+                    if subset.len() + fill.len() >= min_length.max(1) {
+                        keys.insert(alphagram(&format!("{}{}", subset, fill)));
+                    }
+                }
+            }
+        }
+        self.collect(keys, min_length)
+    }
+
+    /// ### groups()
+    /// Every alphagram with its words, sorted by alphagram.
+    pub fn groups(&self) -> Vec<(&String, &Vec<String>)> {
+        let mut groups: Vec<(&String, &Vec<String>)> = self.words.iter().collect();
+        groups.sort();
+        groups
+    }
+
+    /// Look up every key and return the words, longest first then A-Z.
+    fn collect(&self, keys: BTreeSet<String>, min_length: usize) -> Vec<String> {
+        let mut words: Vec<String> = keys
+            .iter()
+            .filter_map(|key| self.words.get(key))
+            .flatten()
+            .filter(|word| word.len() >= min_length)
+            .cloned()
+            .collect();
+        words.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        words
+    }
+}
+
+/// Separate a rack into its upper-cased letters and the number of blanks.
+fn split_blanks(rack: &str) -> (String, usize) {
+    let letters: String = rack
+        .to_uppercase()
+        .chars()
+        .filter(|c| c.is_ascii_uppercase())
+        .collect();
+    let blanks = rack.chars().filter(|c| *c == '?' || *c == '_').count();
+    (letters, blanks)
+}
+
+/// Every way of filling `blanks` blanks, ignoring order: 26 for one
+/// blank, 351 for two.
+fn blank_fills(blanks: usize) -> Vec<String> {
+    let mut fills = vec![String::new()];
+    for _ in 0..blanks {
+        let mut next = Vec::new();
+        for fill in &fills {
+            // Only add letters at or after the last one to skip reorderings
+            let from = fill.chars().last().unwrap_or('A');
+            for letter in from..='Z' {
+                next.push(format!("{}{}", fill, letter));
+            }
+        }
+        fills = next;
+    }
+    fills
+}
+
+/// Collect every distinct sub-multiset of sorted `letters`.
+fn sub_multisets(letters: &[char], start: usize, path: &mut String, out: &mut BTreeSet<String>) {
+    out.insert(path.clone());
+    for i in start..letters.len() {
+        // Skip a repeated letter at the same depth, it gives the same subsets
+        if i > start && letters[i] == letters[i - 1] {
+            continue;
+        }
+        path.push(letters[i]);
+        sub_multisets(letters, i + 1, path, out);
+        path.pop();
+    }
+}
@@ -1,10 +1,14 @@
 use crate::{
     agent::rack::Rack,
-    utils::{trieguy::TrieTree, weighted_group::WeightedGroups},
+    utils::{
+        trieguy::{TrieNode, TrieTree},
+        weighted_group::WeightedGroups,
+    },
 };
 
 /// # Anagram
 /// A single anagram is made up of the word (String) and the score.
+/// Letters played with a wildcard are lowercase.
 #[derive(Clone)]
 pub struct Anagram {
     pub word: String,
@@ -22,9 +26,12 @@ impl Anagram {
 /// estimated score. Each anagram is at least 40% of the
 /// max possible score of the rack.
 /// Functions:
-/// - `get_best_anagram()`, `estimate_score()`, `generate()`
+/// - `estimate_score()`, `generate()`
 pub struct Anagrams {
     anagrams: Vec<Anagram>,
+    score_group: WeightedGroups,
+    pub nodes: u64,
+    pub stopped: bool,
 }
 
 impl Iterator for Anagrams {
@@ -34,16 +41,25 @@ impl Iterator for Anagrams {
     }
 }
 
+impl Default for Anagrams {
+    fn default() -> Self {
+        Anagrams::new()
+    }
+}
+
 impl Anagrams {
     pub fn new() -> Self {
         Anagrams {
             anagrams: Vec::new(),
+            score_group: WeightedGroups::new(),
+            nodes: 0,
+            stopped: false,
         }
     }
-    /// ### get_best_anagram()
-    /// Returns the `Anagram` with the highest score
-    /// removing it from the object; freeing up the
-    /// next best anagram to be returned
+    // ### get_best_anagram()
+    // Returns the `Anagram` with the highest score
+    // removing it from the object; freeing up the
+    // next best anagram to be returned
     // pub fn get_best_anagram(&mut self) -> Option<&Anagram> {
     //     self.anagrams.sort_by(|a, b| {
     //         let score_cmp = b.score.cmp(&a.score); // Descending order for scores
@@ -62,18 +78,12 @@ impl Anagrams {
     fn estimate_score(&self, word: String) -> u32 {
         let mut score: u32 = 0;
         let mut local_max: u32 = 0;
-        let wg: WeightedGroups = WeightedGroups::new();
-        let score_groups = wg.score_groups.clone();
-        // If the word is less than
         for letter in word.chars() {
-            for group in score_groups.clone() {
-                if group.letters.contains(&letter) {
-                    if local_max < group.weight {
-                        local_max = group.weight;
-                    }
-                    score += group.weight;
-                }
+            let weight = self.score_group.get_score(letter);
+            if local_max < weight {
+                local_max = weight;
             }
+            score += weight;
         }
         // If its possible to hit the double letter square, double it
         if word.len() > 4 {
@@ -83,46 +93,152 @@ impl Anagrams {
     }
 
     /// ### generate()
-    /// This is where the magic happens. We recursively call `generate()` until a word
-    /// of at least 4 characters is found. If we've got a word and its a high scoring word,
-    /// add it to the list.
-    /// Otherwise, we keep walking down the tree until a word is found. We handle
-    /// wildcards by ordering the alphabet by frequency of letters.
-    pub fn generate(&mut self, path: String, rack: &mut Rack, trie_guy: &mut TrieTree) {
-        let word = path.clone();
+    /// This is where the magic happens. The rack is turned into letter counts
+    /// and we walk down the `TrieTree`, only following a child when its letter
+    /// is still on the rack, so dead prefixes are never explored. Repeated
+    /// tiles are counted rather than listed, which means each word is reached
+    /// once no matter how many copies of a letter the rack holds.
+    /// A wildcard can stand in for any child letter; it is written in lowercase
+    /// so every (word, wildcard placement) pair is found exactly once and
+    /// scores nothing. Words of at least 4 characters whose estimated score is
+    /// at least 40% of the rack's are added to the list.
+    /// Only words starting with one of `first_letters` are searched; each
+    /// first letter is an independent subtree, which lets several threads
+    /// share out the work. Every `CHECK_INTERVAL` nodes `should_stop` is
+    /// called with the number of nodes visited so far. Once it returns `true`
+    /// the walk unwinds, `stopped` is set and the anagrams found up to that
+    /// point are kept.
+    pub fn generate(
+        &mut self,
+        rack: &Rack,
+        trie_guy: &TrieTree,
+        first_letters: &[char],
+        should_stop: &dyn Fn(u64) -> bool,
+    ) {
         let rack_word: String = rack.letters.iter().collect();
-        let max_score: u32 = (self.estimate_score(rack_word) as f32 * 0.40).floor() as u32;
+        let mut walk = Walk {
+            counts: [0; 26],
+            wildcards: 0,
+            min_score: (self.estimate_score(rack_word) as f32 * 0.40).floor() as u32,
+            should_stop,
+        };
+        for letter in &rack.letters {
+            match letter {
+                '_' => walk.wildcards += 1,
+                'A'..='Z' => walk.counts[(*letter as u8 - b'A') as usize] += 1,
+                _ => {}
+            }
+        }
+        for letter in first_letters {
+            if let Some(child) = trie_guy.root.children.get(letter) {
+                self.follow(*letter, child, &mut String::new(), &mut walk);
+            }
+        }
+    }
 
-        if trie_guy.search(&word) && word.len() >= 4 {
-            let score = self.estimate_score(word.clone());
-            if score >= max_score {
-                self.anagrams.push(Anagram::new(word, score));
+    /// ### walk()
+    /// One step of `generate()`: record the word ending at `node` if it is
+    /// good enough, then try every child the remaining tiles can reach.
+    fn walk(&mut self, node: &TrieNode, path: &mut String, walk: &mut Walk) {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_INTERVAL) && (walk.should_stop)(self.nodes) {
+            self.stopped = true;
+        }
+        if self.stopped {
+            return;
+        }
+        if node.is_end_of_word && path.len() >= 4 {
+            let score = self.estimate_score(path.clone());
+            if score >= walk.min_score {
+                self.anagrams.push(Anagram::new(path.clone(), score));
             }
         }
 
-        for (i, char) in rack.letters.iter().enumerate() {
-            // If we have a wildcard
-            if *char == '_' {
-                for common_char in "AEIOULNRDBCGMFHPSVWYJQKZ".chars() {
-                    // Create a new path for each recursive call
-                    let mut new_path = path.clone();
-                    new_path.push(common_char);
-                    // Create a new rack for each recursive call
-                    let mut new_rack = rack.clone();
-                    new_rack.letters.remove(i);
-                    // Start over less one letter
-                    self.generate(new_path, &mut new_rack, trie_guy);
-                }
-            } else {
-                // Create a new path for each recursive call
-                let mut new_path = path.clone();
-                new_path.push(*char);
-                // Create a new rack for each recursive call
-                let mut new_rack = rack.clone();
-                new_rack.letters.remove(i);
-                // Start over less one letter
-                self.generate(new_path, &mut new_rack, trie_guy);
+        for (letter, child) in &node.children {
+            self.follow(*letter, child, path, walk);
+        }
+    }
+
+    /// ### follow()
+    /// Move down to `child` by playing `letter` from the rack, or by playing
+    /// a wildcard as the letter, or both when the rack allows it.
+    fn follow(&mut self, letter: char, child: &TrieNode, path: &mut String, walk: &mut Walk) {
+        if !letter.is_ascii_uppercase() {
+            return;
+        }
+        let i = (letter as u8 - b'A') as usize;
+        // Play the letter from the rack
+        if walk.counts[i] > 0 {
+            walk.counts[i] -= 1;
+            path.push(letter);
+            self.walk(child, path, walk);
+            path.pop();
+            walk.counts[i] += 1;
+        }
+        // Or play a wildcard as the letter
+        if walk.wildcards > 0 {
+            walk.wildcards -= 1;
+            path.push(letter.to_ascii_lowercase());
+            self.walk(child, path, walk);
+            path.pop();
+            walk.wildcards += 1;
+        }
+    }
+}
+
+/// How many trie nodes `generate()` visits between stop checks.
+pub const CHECK_INTERVAL: u64 = 1024;
+
+/// The state of a `generate()` walk: the tiles left on the rack, the
+/// score a word needs to be kept and when to give up.
+struct Walk<'a> {
+    counts: [u8; 26],
+    wildcards: u8,
+    min_score: u32,
+    should_stop: &'a dyn Fn(u64) -> bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::lexicon::Lexicon;
+
+    fn anagrams(words: &[&str], rack: &str) -> Vec<String> {
+        let lexicon = Lexicon::from_words(words.iter().map(|w| w.to_string()));
+        let letters: Vec<char> = ('A'..='Z').collect();
+        let mut anagrams = Anagrams::new();
+        let rack = Rack::from_letters(rack).unwrap();
+        anagrams.generate(&rack, &lexicon.trie, &letters, &|_| false);
+        anagrams.map(|anagram| anagram.word).collect()
+    }
+
+    #[test]
+    fn each_word_and_wildcard_placement_once() {
+        let mut found = anagrams(&["TEST", "SETT", "STET", "TESTS", "QUIZ"], "TTES?");
+        found.sort();
+        let mut distinct = found.clone();
+        distinct.dedup();
+        assert_eq!(found, distinct);
+
+        // Each four-letter word from the tiles, or with the wildcard in
+        // any one of its four places; TESTS needs it as one of its S's
+        let mut expected = vec!["TEsTS".to_string(), "TESTs".to_string()];
+        for word in ["TEST", "SETT", "STET"] {
+            expected.push(word.to_string());
+            for i in 0..word.len() {
+                let mut placed = word.to_string();
+                placed.replace_range(i..=i, &word[i..=i].to_lowercase());
+                expected.push(placed);
             }
         }
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn repeated_tiles_give_one_word() {
+        let mut found = anagrams(&["TEST", "TESTS"], "TTTEESS");
+        found.sort();
+        assert_eq!(found, ["TEST", "TESTS"]);
     }
 }
@@ -1,13 +1,21 @@
+use serde_json::{json, Value};
+
 /// # Board
 /// The board represents the middle row of the Scrabble Board. It's a vector of "spaces"
 /// and the final score, which the agent sets.
 /// Functions:
-/// - `set_score()`, `display()`
+/// - `set_score()`, `display()`, `to_json()`
 pub struct Board {
     pub spaces: Vec<Option<char>>,
     pub score: u32,
 }
 
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
+}
+
 impl Board {
     pub fn new() -> Board {
         Board {
@@ -31,4 +39,10 @@ impl Board {
         println!("\n\nScore: {:?}", self.score);
         println!("\n\n--\n");
     }
+
+    /// ### to_json()
+    /// The spaces, each a letter or `null`, and the score.
+    pub fn to_json(&self) -> Value {
+        json!({ "spaces": self.spaces, "score": self.score })
+    }
 }
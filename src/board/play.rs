@@ -0,0 +1,163 @@
+use std::cmp::Ordering;
+
+use serde_json::{json, Value};
+
+use crate::board::position::SIZE;
+use crate::utils::error::Error;
+
+/// ## Direction
+/// Which way a play reads on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Direction {
+    Across,
+    Down,
+}
+
+impl Direction {
+    /// ### square()
+    /// The (row, column) of the `index`th square of `line`, where a line
+    /// is a row for `Across` and a column for `Down`.
+    pub fn square(self, line: usize, index: usize) -> (usize, usize) {
+        match self {
+            Direction::Across => (line, index),
+            Direction::Down => (index, line),
+        }
+    }
+
+    /// ### other()
+    /// The perpendicular direction.
+    pub fn other(self) -> Direction {
+        match self {
+            Direction::Across => Direction::Down,
+            Direction::Down => Direction::Across,
+        }
+    }
+}
+
+/// # Play
+/// A word placed on the full board, in standard notation: `8H QUIZ` reads
+/// across from row 8, column H and `H8 QUIZ` reads down. `word` is the
+/// whole word including any tiles it plays through, `tiles` only the
+/// tiles taken from the rack. Letters played with a wildcard are lowercase.
+/// Functions:
+/// - `parse()`, `from_notation()`, `coordinates()`, `notation()`, `compare()`, `to_json()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Play {
+    pub row: usize,
+    pub col: usize,
+    pub direction: Direction,
+    pub word: String,
+    pub tiles: String,
+    pub score: u32,
+}
+
+impl Play {
+    /// ### parse()
+    /// Read a play such as `8H QUIZ` or `h8 quiZ`. Coordinates are case
+    /// insensitive; in the word, lowercase letters are wildcards. Tiles
+    /// already on the board may be written in parentheses, e.g. `QU(I)Z`.
+    /// The `tiles` and `score` are filled in by `Position::check()`.
+    pub fn parse(coordinates: &str, word: &str) -> Result<Play, Error> {
+        let coordinates = coordinates.to_ascii_uppercase();
+        let invalid = || format!("\"{}\" is not a square such as 8H or H8", coordinates);
+        let digits: String = coordinates.chars().filter(|c| c.is_ascii_digit()).collect();
+        let letters: String = coordinates
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .collect();
+        if letters.len() != 1 || digits.len() + letters.len() != coordinates.len() {
+            return Err(Error::Parse(invalid()));
+        }
+        let row = digits
+            .parse::<usize>()
+            .map_err(|_| Error::Parse(invalid()))?;
+        let col = (letters.as_bytes()[0] - b'A') as usize;
+        if row == 0 || row > SIZE || col >= SIZE {
+            return Err(Error::Parse(invalid()));
+        }
+        // A leading number reads across, a leading letter reads down
+        let direction = if coordinates.starts_with(|c: char| c.is_ascii_digit()) {
+            Direction::Across
+        } else {
+            Direction::Down
+        };
+
+        let word: String = word.chars().filter(|c| *c != '(' && *c != ')').collect();
+        if word.len() < 2 || !word.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(Error::Parse(format!(
+                "\"{}\" is not a word of at least 2 letters",
+                word
+            )));
+        }
+        Ok(Play {
+            row: row - 1,
+            col,
+            direction,
+            word,
+            tiles: String::new(),
+            score: 0,
+        })
+    }
+
+    /// ### from_notation()
+    /// Read a play written as one string, e.g. `"8H QUIZ"`.
+    pub fn from_notation(notation: &str) -> Result<Play, Error> {
+        match notation.split_whitespace().collect::<Vec<&str>>()[..] {
+            [coordinates, word] => Play::parse(coordinates, word),
+            _ => Err(Error::Parse(format!(
+                "\"{}\" is not a play such as 8H QUIZ",
+                notation.trim()
+            ))),
+        }
+    }
+
+    /// ### coordinates()
+    /// The starting square: row first for across, column first for down.
+    pub fn coordinates(&self) -> String {
+        let col = (b'A' + self.col as u8) as char;
+        match self.direction {
+            Direction::Across => format!("{}{}", self.row + 1, col),
+            Direction::Down => format!("{}{}", col, self.row + 1),
+        }
+    }
+
+    /// ### notation()
+    /// The play as it is written on a score sheet, e.g. `8H QUIZ`.
+    pub fn notation(&self) -> String {
+        format!("{} {}", self.coordinates(), self.word)
+    }
+
+    /// ### compare()
+    /// Best play first: higher score, then more tiles used, then A-Z and
+    /// by square, so every search orders its results the same way.
+    pub fn compare(&self, other: &Play) -> Ordering {
+        other
+            .score
+            .cmp(&self.score)
+            .then(other.tiles.len().cmp(&self.tiles.len()))
+            .then(self.word.cmp(&other.word))
+            .then((self.row, self.col, self.direction).cmp(&(
+                other.row,
+                other.col,
+                other.direction,
+            )))
+    }
+
+    /// ### to_json()
+    /// The play with its square split out: 1-based row, column letter and
+    /// direction.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "move": self.notation(),
+            "row": self.row + 1,
+            "column": ((b'A' + self.col as u8) as char).to_string(),
+            "direction": match self.direction {
+                Direction::Across => "across",
+                Direction::Down => "down",
+            },
+            "word": self.word,
+            "tiles": self.tiles,
+            "score": self.score,
+        })
+    }
+}
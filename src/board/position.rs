@@ -0,0 +1,634 @@
+use crate::board::play::{Direction, Play};
+use crate::utils::error::Error;
+use crate::utils::trieguy::TrieTree;
+use crate::utils::weighted_group::WeightedGroups;
+
+/// Squares along each side of the board.
+pub const SIZE: usize = 15;
+
+/// The center square, which the first play must cover.
+pub const CENTER: usize = 7;
+
+/// Bonus for playing all 7 tiles of the rack at once.
+pub const BINGO_BONUS: u32 = 50;
+
+/// The premium squares of the standard board: `T` triple word, `D` double
+/// word, `t` triple letter, `d` double letter.
+const PREMIUMS: [&str; SIZE] = [
+    "T..d...T...d..T",
+    ".D...t...t...D.",
+    "..D...d.d...D..",
+    "d..D...d...D..d",
+    "....D.....D....",
+    ".t...t...t...t.",
+    "..d...d.d...d..",
+    "T..d...D...d..T",
+    "..d...d.d...d..",
+    ".t...t...t...t.",
+    "....D.....D....",
+    "d..D...d...D..d",
+    "..D...d.d...D..",
+    ".D...t...t...D.",
+    "T..d...T...d..T",
+];
+
+/// ## Premium
+/// The bonus printed on a square, which only counts for the tile
+/// placed on it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Premium {
+    Plain,
+    DoubleLetter,
+    TripleLetter,
+    DoubleWord,
+    TripleWord,
+}
+
+impl Premium {
+    /// ### at()
+    /// The premium of the square at `row`, `col`.
+    pub fn at(row: usize, col: usize) -> Premium {
+        match PREMIUMS[row].as_bytes()[col] {
+            b'T' => Premium::TripleWord,
+            b'D' => Premium::DoubleWord,
+            b't' => Premium::TripleLetter,
+            b'd' => Premium::DoubleLetter,
+            _ => Premium::Plain,
+        }
+    }
+
+    /// ### multipliers()
+    /// The (letter, word) multipliers of the square.
+    pub fn multipliers(self) -> (u32, u32) {
+        match self {
+            Premium::Plain => (1, 1),
+            Premium::DoubleLetter => (2, 1),
+            Premium::TripleLetter => (3, 1),
+            Premium::DoubleWord => (1, 2),
+            Premium::TripleWord => (1, 3),
+        }
+    }
+}
+
+/// # Position
+/// The full 15x15 board, as opposed to the single opening row of `Board`.
+/// Each square holds a tile or nothing; tiles played with a wildcard are
+/// lowercase. Rows are numbered 1-15 and columns A-O, as on a score sheet.
+/// Functions:
+/// - `get()`, `is_empty()`, `parse()`, `notation()`, `check()`,
+///   `check_placement()`, `phonies()`, `score()`, `apply()`, `place()`,
+///   `play_from_tiles()`, `display()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Position {
+    squares: [[Option<char>; SIZE]; SIZE],
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::new()
+    }
+}
+
+impl Position {
+    pub fn new() -> Position {
+        Position {
+            squares: [[None; SIZE]; SIZE],
+        }
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<char> {
+        self.squares[row][col]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.squares.iter().flatten().all(|square| square.is_none())
+    }
+
+    /// ### parse()
+    /// Read a board written row by row from the top, rows separated by `/`.
+    /// A letter is a tile (lowercase for a wildcard), `.` an empty square
+    /// and a number that many empty squares, so the empty board is
+    /// `15/15/15/15/15/15/15/15/15/15/15/15/15/15/15`.
+    pub fn parse(notation: &str) -> Result<Position, Error> {
+        let rows: Vec<&str> = notation.trim().split('/').collect();
+        if rows.len() != SIZE {
+            return Err(Error::Parse(format!(
+                "a board has {} rows separated by /, got {}",
+                SIZE,
+                rows.len()
+            )));
+        }
+        let mut position = Position::new();
+        for (row, text) in rows.iter().enumerate() {
+            let mut col = 0;
+            let mut empty = String::new();
+            // Push the pending run of empty squares once it is complete
+            let flush = |empty: &mut String, col: &mut usize| -> Result<(), Error> {
+                if !empty.is_empty() {
+                    let run = empty.parse::<usize>().map_err(|_| {
+                        Error::Parse(format!("bad run of empty squares in row {}", row + 1))
+                    })?;
+                    *col = col
+                        .checked_add(run)
+                        .filter(|col| *col <= SIZE)
+                        .ok_or_else(|| {
+                            Error::Parse(format!("row {} has more than {} squares", row + 1, SIZE))
+                        })?;
+                    empty.clear();
+                }
+                Ok(())
+            };
+            for c in text.chars() {
+                match c {
+                    '0'..='9' => empty.push(c),
+                    '.' => {
+                        flush(&mut empty, &mut col)?;
+                        col += 1;
+                    }
+                    c if c.is_ascii_alphabetic() => {
+                        flush(&mut empty, &mut col)?;
+                        if col >= SIZE {
+                            return Err(Error::Parse(format!(
+                                "row {} has more than {} squares",
+                                row + 1,
+                                SIZE
+                            )));
+                        }
+                        position.squares[row][col] = Some(c);
+                        col += 1;
+                    }
+                    _ => {
+                        return Err(Error::Parse(format!(
+                            "'{}' is not a tile, . or number in row {}",
+                            c,
+                            row + 1
+                        )))
+                    }
+                }
+            }
+            flush(&mut empty, &mut col)?;
+            if col != SIZE {
+                return Err(Error::Parse(format!(
+                    "row {} has {} squares, not {}",
+                    row + 1,
+                    col,
+                    SIZE
+                )));
+            }
+        }
+        Ok(position)
+    }
+
+    /// ### notation()
+    /// The board in the form read by `parse()`, with runs of empty
+    /// squares written as numbers.
+    pub fn notation(&self) -> String {
+        let rows: Vec<String> = self
+            .squares
+            .iter()
+            .map(|row| {
+                let mut text = String::new();
+                let mut empty = 0;
+                for square in row {
+                    match square {
+                        Some(tile) => {
+                            if empty > 0 {
+                                text.push_str(&empty.to_string());
+                                empty = 0;
+                            }
+                            text.push(*tile);
+                        }
+                        None => empty += 1,
+                    }
+                }
+                if empty > 0 {
+                    text.push_str(&empty.to_string());
+                }
+                text
+            })
+            .collect();
+        rows.join("/")
+    }
+
+    /// ### check()
+    /// Make sure `play` is legal here: it passes `check_placement()` and
+    /// every word it forms is in the `TrieTree`. Returns the play with its
+    /// `tiles` and `score` filled in.
+    pub fn check(
+        &self,
+        play: &Play,
+        tree: &TrieTree,
+        values: &WeightedGroups,
+    ) -> Result<Play, Error> {
+        let checked = self.check_placement(play, values)?;
+        let phonies = self.phonies(play, tree);
+        if !phonies.is_empty() {
+            return Err(Error::IllegalMove(format!(
+                "not in the lexicon: {}",
+                phonies.join(", ")
+            )));
+        }
+        Ok(checked)
+    }
+
+    /// ### check_placement()
+    /// Everything `check()` does except looking the words up: the play
+    /// fits on the board, agrees with the tiles it plays through, places
+    /// at least one tile, and covers the center on an empty board or
+    /// touches a tile otherwise. Used where a phony may be played and
+    /// only challenged later.
+    pub fn check_placement(&self, play: &Play, values: &WeightedGroups) -> Result<Play, Error> {
+        let line = match play.direction {
+            Direction::Across => play.row,
+            Direction::Down => play.col,
+        };
+        let start = match play.direction {
+            Direction::Across => play.col,
+            Direction::Down => play.row,
+        };
+        let end = start + play.word.len();
+        if end > SIZE {
+            return Err(Error::IllegalMove(format!(
+                "{} runs off the board",
+                play.notation()
+            )));
+        }
+        if start > 0 && self.at(play.direction, line, start - 1).is_some() {
+            return Err(Error::IllegalMove(format!(
+                "{} does not include the tile before it",
+                play.notation()
+            )));
+        }
+        if end < SIZE && self.at(play.direction, line, end).is_some() {
+            return Err(Error::IllegalMove(format!(
+                "{} does not include the tile after it",
+                play.notation()
+            )));
+        }
+
+        let mut tiles = String::new();
+        let mut touches = false;
+        for (i, letter) in play.word.chars().enumerate() {
+            let (row, col) = play.direction.square(line, start + i);
+            match self.squares[row][col] {
+                Some(tile) if tile.eq_ignore_ascii_case(&letter) => touches = true,
+                Some(tile) => {
+                    return Err(Error::IllegalMove(format!(
+                        "{} needs {} on {}{} but {} is there",
+                        play.notation(),
+                        letter,
+                        row + 1,
+                        (b'A' + col as u8) as char,
+                        tile
+                    )))
+                }
+                None => {
+                    tiles.push(letter);
+                    touches |= (row, col) == (CENTER, CENTER) && self.is_empty();
+                    touches |= self.cross_word(row, col, play.direction.other()).is_some();
+                }
+            }
+        }
+        if tiles.is_empty() {
+            return Err(Error::IllegalMove(format!(
+                "{} does not place any tiles",
+                play.notation()
+            )));
+        }
+        if !touches {
+            return Err(Error::IllegalMove(if self.is_empty() {
+                format!(
+                    "the first play must cover the center square, {} does not",
+                    play.notation()
+                )
+            } else {
+                format!("{} does not touch any tile on the board", play.notation())
+            }));
+        }
+        let mut checked = play.clone();
+        checked.tiles = tiles;
+        checked.score = self.score(&checked, values);
+        Ok(checked)
+    }
+
+    /// ### phonies()
+    /// The words `play` would form here that are not in the `TrieTree`.
+    pub fn phonies(&self, play: &Play, tree: &TrieTree) -> Vec<String> {
+        self.words_formed(play)
+            .into_iter()
+            .filter(|word| !tree.search(word))
+            .collect()
+    }
+
+    /// ### score()
+    /// Score a legal `play`: the main word plus every word formed across
+    /// it, premiums counting only for newly placed tiles, plus the bingo
+    /// bonus for using all 7 tiles. Wildcards are worth nothing.
+    pub fn score(&self, play: &Play, values: &WeightedGroups) -> u32 {
+        let mut main = 0;
+        let mut word_multiplier = 1;
+        let mut cross_words = 0;
+        let mut placed = 0;
+        for (i, letter) in play.word.chars().enumerate() {
+            let (row, col) = self.square_of(play, i);
+            // A tile already down scores as it lies, so a wildcard played
+            // earlier stays worth nothing whatever letter the play names
+            if let Some(tile) = self.squares[row][col] {
+                main += values.get_score(tile);
+                continue;
+            }
+            let value = values.get_score(letter);
+            placed += 1;
+            let (letter_multiplier, multiplier) = Premium::at(row, col).multipliers();
+            main += value * letter_multiplier;
+            word_multiplier *= multiplier;
+            if let Some(cross) = self.cross_word(row, col, play.direction.other()) {
+                let cross_value: u32 = cross.chars().map(|c| values.get_score(c)).sum();
+                cross_words += (cross_value + value * letter_multiplier) * multiplier;
+            }
+        }
+        let bingo = if placed == 7 { BINGO_BONUS } else { 0 };
+        main * word_multiplier + cross_words + bingo
+    }
+
+    /// ### apply()
+    /// `check()` the play and, if it is legal, place it. Returns the
+    /// checked play with its tiles and score.
+    pub fn apply(
+        &mut self,
+        play: &Play,
+        tree: &TrieTree,
+        values: &WeightedGroups,
+    ) -> Result<Play, Error> {
+        let play = self.check(play, tree, values)?;
+        self.place(&play);
+        Ok(play)
+    }
+
+    /// ### place()
+    /// Put the tiles of `play` on the board.
+    pub fn place(&mut self, play: &Play) {
+        for (i, letter) in play.word.chars().enumerate() {
+            let (row, col) = self.square_of(play, i);
+            if self.squares[row][col].is_none() {
+                self.squares[row][col] = Some(letter);
+            }
+        }
+    }
+
+    /// ### play_from_tiles()
+    /// The `Play` made by putting `tiles` (row, column, letter) on empty
+    /// squares, as a GUI would collect them. The tiles must share a row or
+    /// a column and, with the tiles already on the board, form one
+    /// unbroken word. A single tile reads the way it forms a word, across
+    /// when it forms one both ways. Legality is left to `check()`.
+    pub fn play_from_tiles(&self, tiles: &[(usize, usize, char)]) -> Result<Play, Error> {
+        let (first_row, first_col, _) = *tiles
+            .first()
+            .ok_or_else(|| Error::IllegalMove("no tiles placed".to_string()))?;
+        for (i, (row, col, _)) in tiles.iter().enumerate() {
+            if *row >= SIZE || *col >= SIZE || self.squares[*row][*col].is_some() {
+                return Err(Error::IllegalMove(format!(
+                    "{}{} is not an empty square",
+                    row + 1,
+                    (b'A' + *col as u8) as char
+                )));
+            }
+            if tiles[..i].iter().any(|(r, c, _)| (r, c) == (row, col)) {
+                return Err(Error::IllegalMove("two tiles on one square".to_string()));
+            }
+        }
+        let direction = if tiles.len() > 1 {
+            if tiles.iter().all(|(row, _, _)| *row == first_row) {
+                Direction::Across
+            } else if tiles.iter().all(|(_, col, _)| *col == first_col) {
+                Direction::Down
+            } else {
+                return Err(Error::IllegalMove(
+                    "the tiles must be in one row or one column".to_string(),
+                ));
+            }
+        } else if self
+            .cross_word(first_row, first_col, Direction::Across)
+            .is_none()
+            && self
+                .cross_word(first_row, first_col, Direction::Down)
+                .is_some()
+        {
+            Direction::Down
+        } else {
+            Direction::Across
+        };
+
+        let line = match direction {
+            Direction::Across => first_row,
+            Direction::Down => first_col,
+        };
+        let index = |(row, col): (usize, usize)| match direction {
+            Direction::Across => col,
+            Direction::Down => row,
+        };
+        let placed = |i: usize| {
+            tiles
+                .iter()
+                .find(|(row, col, _)| index((*row, *col)) == i)
+                .map(|(_, _, letter)| *letter)
+        };
+        let mut start = tiles
+            .iter()
+            .map(|(r, c, _)| index((*r, *c)))
+            .min()
+            .unwrap_or(0);
+        let mut end = tiles
+            .iter()
+            .map(|(r, c, _)| index((*r, *c)))
+            .max()
+            .unwrap_or(0);
+        // Take in the tiles already on the board at either end
+        while start > 0 && self.at(direction, line, start - 1).is_some() {
+            start -= 1;
+        }
+        while end + 1 < SIZE && self.at(direction, line, end + 1).is_some() {
+            end += 1;
+        }
+        let mut word = String::new();
+        for i in start..=end {
+            match self.at(direction, line, i).or_else(|| placed(i)) {
+                Some(letter) => word.push(letter),
+                None => {
+                    return Err(Error::IllegalMove(
+                        "the tiles must form one unbroken word".to_string(),
+                    ))
+                }
+            }
+        }
+        let (row, col) = direction.square(line, start);
+        Ok(Play {
+            row,
+            col,
+            direction,
+            word,
+            tiles: String::new(),
+            score: 0,
+        })
+    }
+
+    /// ### display()
+    /// Print the board with its coordinates; empty premium squares show
+    /// their premium (`=` triple word, `-` double word, `"` triple
+    /// letter, `'` double letter).
+    pub fn display(&self) {
+        print!("   ");
+        for col in 0..SIZE {
+            print!(" {}", (b'A' + col as u8) as char);
+        }
+        println!();
+        for row in 0..SIZE {
+            print!("{:>2} ", row + 1);
+            for col in 0..SIZE {
+                let square = match self.squares[row][col] {
+                    Some(tile) => tile,
+                    None => match Premium::at(row, col) {
+                        Premium::TripleWord => '=',
+                        Premium::DoubleWord => '-',
+                        Premium::TripleLetter => '"',
+                        Premium::DoubleLetter => '\'',
+                        Premium::Plain => '.',
+                    },
+                };
+                print!(" {}", square);
+            }
+            println!();
+        }
+    }
+
+    /// The tile on the `index`th square of `line` read in `direction`.
+    pub fn at(&self, direction: Direction, line: usize, index: usize) -> Option<char> {
+        let (row, col) = direction.square(line, index);
+        self.squares[row][col]
+    }
+
+    /// ### cross_word()
+    /// The tiles next to the empty square at `row`, `col` in `direction`,
+    /// with a `.` where the square is, e.g. `C.T`; `None` when both
+    /// neighbours are empty, so a tile there forms no word that way.
+    pub fn cross_word(&self, row: usize, col: usize, direction: Direction) -> Option<String> {
+        let (line, index) = match direction {
+            Direction::Across => (row, col),
+            Direction::Down => (col, row),
+        };
+        let mut first = index;
+        while first > 0 && self.at(direction, line, first - 1).is_some() {
+            first -= 1;
+        }
+        let mut last = index;
+        while last + 1 < SIZE && self.at(direction, line, last + 1).is_some() {
+            last += 1;
+        }
+        if first == last {
+            return None;
+        }
+        let word = (first..=last)
+            .map(|i| match i == index {
+                true => '.',
+                false => self.at(direction, line, i).unwrap_or('.'),
+            })
+            .collect();
+        Some(word)
+    }
+
+    /// ### words_formed()
+    /// Every word `play` forms, in upper case: the main word first, then
+    /// one for each placed tile that has neighbours across it.
+    pub fn words_formed(&self, play: &Play) -> Vec<String> {
+        let mut words = vec![play.word.to_ascii_uppercase()];
+        for (i, letter) in play.word.chars().enumerate() {
+            let (row, col) = self.square_of(play, i);
+            if self.squares[row][col].is_some() {
+                continue;
+            }
+            if let Some(cross) = self.cross_word(row, col, play.direction.other()) {
+                words.push(cross.replace('.', &letter.to_string()).to_ascii_uppercase());
+            }
+        }
+        words
+    }
+
+    fn square_of(&self, play: &Play, i: usize) -> (usize, usize) {
+        match play.direction {
+            Direction::Across => (play.row, play.col + i),
+            Direction::Down => (play.row + i, play.col),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty board but for `row` on row 8.
+    fn with_row_8(row: &str) -> Position {
+        let empty = ["15"; 7].join("/");
+        Position::parse(&format!("{}/{}/{}", empty, row, empty)).unwrap()
+    }
+
+    fn score(position: &Position, notation: &str) -> u32 {
+        let values = WeightedGroups::new();
+        let play = Play::from_notation(notation).unwrap();
+        position.check_placement(&play, &values).unwrap().score
+    }
+
+    #[test]
+    fn parse_rejects_rows_that_run_past_the_edge() {
+        let huge = "A18446744073709551615/15/15/15/15/15/15/15/15/15/15/15/15/15/15";
+        assert!(matches!(Position::parse(huge), Err(Error::Parse(_))));
+        let long = "16/15/15/15/15/15/15/15/15/15/15/15/15/15/15";
+        assert!(matches!(Position::parse(long), Err(Error::Parse(_))));
+        let rows = "7CAT5/15/15/15/15/15/15/15/15/15/15/15/15/15/15";
+        assert_eq!(Position::parse(rows).unwrap().get(0, 8), Some('A'));
+    }
+
+    #[test]
+    fn first_play_doubles_on_the_center() {
+        assert_eq!(score(&Position::new(), "8H QUIZ"), 44);
+    }
+
+    #[test]
+    fn wildcards_are_worth_nothing() {
+        assert_eq!(score(&Position::new(), "8F CaT"), 8);
+    }
+
+    #[test]
+    fn playing_through_a_wildcard_scores_it_as_nothing() {
+        let position = with_row_8("6a8");
+        assert_eq!(score(&position, "8F CAT"), 8);
+        assert_eq!(score(&position, "8F CaT"), 8);
+    }
+
+    #[test]
+    fn premiums_under_old_tiles_do_not_count() {
+        let position = with_row_8("7CAT5");
+        assert_eq!(score(&position, "8H CATS"), 6);
+    }
+
+    #[test]
+    fn cross_words_score_with_the_main_word() {
+        let position = with_row_8("7CAT5");
+        assert_eq!(score(&position, "K7 AS"), 8);
+    }
+
+    #[test]
+    fn words_formed_are_the_main_word_then_cross_words() {
+        let position = with_row_8("7CAT5");
+        let play = Play::from_notation("K7 aS").unwrap();
+        assert_eq!(position.words_formed(&play), vec!["AS", "CATS"]);
+        let through = Play::from_notation("8H CATS").unwrap();
+        assert_eq!(position.words_formed(&through), vec!["CATS"]);
+    }
+
+    #[test]
+    fn seven_tiles_earn_the_bingo_bonus() {
+        // Seven 1-point tiles, the R on a double letter, the word doubled
+        assert_eq!(score(&Position::new(), "8D RETAINS"), 2 * 8 + BINGO_BONUS);
+    }
+}
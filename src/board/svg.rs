@@ -0,0 +1,163 @@
+use crate::board::play::{Direction, Play};
+use crate::board::position::{Position, Premium, CENTER, SIZE};
+use crate::utils::weighted_group::WeightedGroups;
+
+/// Side of one square of the diagram, in SVG user units.
+const SQUARE: usize = 40;
+
+/// Room left of and above the board for the coordinates.
+const MARGIN: usize = 24;
+
+const BOARD_COLOR: &str = "#1e5c3a";
+const PLAIN_COLOR: &str = "#dfe6d4";
+const TRIPLE_WORD_COLOR: &str = "#e0523a";
+const DOUBLE_WORD_COLOR: &str = "#f2a7a0";
+const TRIPLE_LETTER_COLOR: &str = "#3a78c2";
+const DOUBLE_LETTER_COLOR: &str = "#a9d2ee";
+const TILE_COLOR: &str = "#f3dca7";
+const NEW_TILE_COLOR: &str = "#fff4c7";
+const LETTER_COLOR: &str = "#222222";
+const WILDCARD_COLOR: &str = "#c0392b";
+const HIGHLIGHT_COLOR: &str = "#ff8c00";
+
+/// ### to_svg()
+/// Draw `position` as a standalone SVG diagram: the grid with its
+/// coordinates, the premium squares in their usual colors and every tile
+/// with its point value. Tiles played with a wildcard show their letter in
+/// red and no value.
+///
+/// With `highlight`, the squares of that play are outlined, and any of its
+/// tiles not yet on the board are drawn in a lighter color, so a candidate
+/// play can be shown on the board it would be made on.
+pub fn to_svg(position: &Position, highlight: Option<&Play>) -> String {
+    let values = WeightedGroups::new();
+    let size = MARGIN + SIZE * SQUARE + MARGIN / 2;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" \
+         viewBox=\"0 0 {size} {size}\" font-family=\"Helvetica, Arial, sans-serif\">\n"
+    );
+    svg.push_str(&format!(
+        "<rect width=\"{size}\" height=\"{size}\" fill=\"white\"/>\n\
+         <rect x=\"{MARGIN}\" y=\"{MARGIN}\" width=\"{0}\" height=\"{0}\" fill=\"{BOARD_COLOR}\"/>\n",
+        SIZE * SQUARE
+    ));
+
+    // Coordinates: columns A-O along the top, rows 1-15 down the side
+    for i in 0..SIZE {
+        let middle = MARGIN + i * SQUARE + SQUARE / 2;
+        svg.push_str(&format!(
+            "<text x=\"{middle}\" y=\"{}\" font-size=\"13\" text-anchor=\"middle\">{}</text>\n\
+             <text x=\"{}\" y=\"{}\" font-size=\"13\" text-anchor=\"middle\">{}</text>\n",
+            MARGIN - 8,
+            (b'A' + i as u8) as char,
+            MARGIN / 2,
+            middle + 5,
+            i + 1
+        ));
+    }
+
+    // The tiles of the highlighted play, by square
+    let mut planned = Vec::new();
+    if let Some(play) = highlight {
+        let (line, start) = match play.direction {
+            Direction::Across => (play.row, play.col),
+            Direction::Down => (play.col, play.row),
+        };
+        for (i, letter) in play.word.chars().enumerate() {
+            if start + i < SIZE {
+                let (row, col) = play.direction.square(line, start + i);
+                planned.push((row, col, letter));
+            }
+        }
+    }
+
+    for row in 0..SIZE {
+        for col in 0..SIZE {
+            let x = MARGIN + col * SQUARE;
+            let y = MARGIN + row * SQUARE;
+            let new_tile = planned
+                .iter()
+                .find(|(r, c, _)| (*r, *c) == (row, col))
+                .map(|(_, _, letter)| *letter);
+            match (position.get(row, col), new_tile) {
+                (Some(tile), _) => svg.push_str(&tile_svg(x, y, tile, TILE_COLOR, &values)),
+                (None, Some(tile)) => svg.push_str(&tile_svg(x, y, tile, NEW_TILE_COLOR, &values)),
+                (None, None) => svg.push_str(&square_svg(x, y, row, col)),
+            }
+        }
+    }
+
+    for (row, col, _) in &planned {
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" \
+             stroke=\"{HIGHLIGHT_COLOR}\" stroke-width=\"3\"/>\n",
+            MARGIN + col * SQUARE + 2,
+            MARGIN + row * SQUARE + 2,
+            SQUARE - 4,
+            SQUARE - 4
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// An empty square, colored and labelled by its premium; the center
+/// square gets a star.
+fn square_svg(x: usize, y: usize, row: usize, col: usize) -> String {
+    let (fill, label) = match Premium::at(row, col) {
+        Premium::TripleWord => (TRIPLE_WORD_COLOR, "TW"),
+        Premium::DoubleWord => (DOUBLE_WORD_COLOR, "DW"),
+        Premium::TripleLetter => (TRIPLE_LETTER_COLOR, "TL"),
+        Premium::DoubleLetter => (DOUBLE_LETTER_COLOR, "DL"),
+        Premium::Plain => (PLAIN_COLOR, ""),
+    };
+    let label = match (row, col) == (CENTER, CENTER) {
+        true => "\u{2605}",
+        false => label,
+    };
+    let mut svg = format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{fill}\"/>\n",
+        x + 1,
+        y + 1,
+        SQUARE - 2,
+        SQUARE - 2
+    );
+    if !label.is_empty() {
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\" fill=\"{LETTER_COLOR}\">{label}</text>\n",
+            x + SQUARE / 2,
+            y + SQUARE / 2 + 4
+        ));
+    }
+    svg
+}
+
+/// A tile with its letter and, unless it is a wildcard, its value in the
+/// bottom right corner.
+fn tile_svg(x: usize, y: usize, tile: char, fill: &str, values: &WeightedGroups) -> String {
+    let wildcard = tile.is_ascii_lowercase();
+    let color = match wildcard {
+        true => WILDCARD_COLOR,
+        false => LETTER_COLOR,
+    };
+    let mut svg = format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"{fill}\" stroke=\"#a08850\"/>\n\
+         <text x=\"{}\" y=\"{}\" font-size=\"22\" font-weight=\"bold\" text-anchor=\"middle\" fill=\"{color}\">{}</text>\n",
+        x + 2,
+        y + 2,
+        SQUARE - 4,
+        SQUARE - 4,
+        x + SQUARE / 2 - 2,
+        y + SQUARE / 2 + 8,
+        tile.to_ascii_uppercase()
+    );
+    if !wildcard {
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"10\" text-anchor=\"end\" fill=\"{LETTER_COLOR}\">{}</text>\n",
+            x + SQUARE - 5,
+            y + SQUARE - 5,
+            values.get_score(tile)
+        ));
+    }
+    svg
+}
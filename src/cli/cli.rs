@@ -0,0 +1,842 @@
+use scrabble::agent::inference::DEFAULT_SAMPLES;
+use scrabble::game::game::ChallengeRule;
+use scrabble::utils::trieguy::DEFAULT_DICTIONARY;
+
+/// How long the agent thinks in `game` and `tui` unless `--time` is
+/// given, in ms.
+pub const DEFAULT_THINK_TIME: u64 = 2000;
+
+/// How the agent picks its moves in `game` and `tui` unless `--strategy`
+/// is given.
+pub const DEFAULT_STRATEGY: &str = "equity";
+
+/// Where `tournament` keeps the ratings unless `--ratings` is given.
+pub const DEFAULT_RATINGS: &str = "ratings.txt";
+
+/// Where `leaves` writes the fitted table unless `--out` is given.
+pub const DEFAULT_LEAVES: &str = "leaves.txt";
+
+/// Where `serve` listens unless `--address` is given.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+/// The top level help, printed by `scrabble help` or when no valid
+/// subcommand is given.
+pub const USAGE: &str = "\
+Usage: scrabble [OPTIONS] <COMMAND> [ARGS]
+
+Commands:
+  solve              Find the best first move for a rack (default)
+  analyze            List every candidate first move with its score
+  anagram <LETTERS>  List every word that can be made from the letters
+  search [PATTERN]   Find words by pattern, letters, length, prefix or suffix
+  check <WORD>...    Check words against the lexicon (exit status 1 if any is invalid)
+  adjudicate [<WORD>...]
+                     Judge a play as a tournament adjudicator would
+  play               Play first-move rounds against the agent
+  selfplay           Pit two strategies against each other over many games
+  tournament         Rate every strategy in a round robin, keeping the ratings in a file
+  leaves             Fit a leave-value table from self-play games
+  quiz               Anagram quiz: find the words hidden in a rack
+  lexicon build <IN> <OUT>
+                     Compile a word list into a lexicon with an anagram index
+  svg                Draw a board as an SVG diagram, optionally highlighting a play
+  unseen             Count the tiles left and the chances of drawing some
+  game               Play a full game against the agent, typing moves in notation
+  tui                Play a full game against the agent in a terminal UI
+  replay <RECORD>    Turn a game record into an HTML page that replays it
+  infer <RECORD>     Estimate what the opponent kept with their last play
+  repl               Interactive analysis shell with the lexicon kept loaded
+  engine             Speak the engine protocol on stdin/stdout for GUIs and tools
+  serve              Answer JSON requests over HTTP with the lexicon kept loaded
+  help [COMMAND]     Show help for a command
+
+Options:
+  -l, --lexicon <PATH>   Word list to use [default: src/data/dictionary.txt]
+  -s, --seed <N>         Seed the random number generator for repeatable racks
+  -t, --threads <N>      Threads used to generate and score moves [default: all cores]
+  -f, --format <FORMAT>  Output format: text or json [default: text]
+  -v, --verbose          Print extra detail such as timings
+  -q, --quiet            Only print the results
+  -h, --help             Show this help
+  -V, --version          Show the version";
+
+/// ## OutputFormat
+/// How a command prints its results.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// ## Verbosity
+/// How much a command prints besides its results.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/// ## Command
+/// One variant per subcommand, holding the arguments
+/// that are specific to it.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Solve {
+        rack: Option<String>,
+        time: Option<u64>,
+        nodes: Option<u64>,
+    },
+    Analyze {
+        rack: Option<String>,
+        top: usize,
+        time: Option<u64>,
+        nodes: Option<u64>,
+    },
+    Anagram {
+        letters: String,
+        min_length: usize,
+        exact: bool,
+    },
+    Search {
+        pattern: Option<String>,
+        anagram: Option<String>,
+        subanagram: Option<String>,
+        contains: Option<String>,
+        prefix: Option<String>,
+        suffix: Option<String>,
+        min_length: usize,
+        max_length: Option<usize>,
+        limit: Option<usize>,
+    },
+    Check {
+        words: Vec<String>,
+        play: Option<String>,
+        board: Option<String>,
+    },
+    Adjudicate {
+        words: Vec<String>,
+        log: Option<String>,
+    },
+    Play {
+        rounds: u32,
+    },
+    SelfPlay {
+        games: u32,
+        strategies: Option<[String; 2]>,
+        challenge: ChallengeRule,
+        time: Option<u64>,
+        nodes: Option<u64>,
+        output: Option<String>,
+    },
+    Tournament {
+        games: u32,
+        strategies: Option<Vec<String>>,
+        ratings: String,
+        time: Option<u64>,
+        nodes: Option<u64>,
+    },
+    Leaves {
+        games: u32,
+        strategy: String,
+        time: Option<u64>,
+        nodes: Option<u64>,
+        output: String,
+    },
+    Quiz {
+        rounds: u32,
+    },
+    LexiconBuild {
+        input: String,
+        output: String,
+    },
+    Svg {
+        board: Option<String>,
+        play: Option<String>,
+        output: Option<String>,
+    },
+    Unseen {
+        board: Option<String>,
+        rack: Option<String>,
+        draw: Option<String>,
+        count: Option<u64>,
+    },
+    Game {
+        strategy: String,
+        challenge: ChallengeRule,
+        time: u64,
+        record: Option<String>,
+    },
+    Tui {
+        strategy: String,
+        time: u64,
+        record: Option<String>,
+    },
+    Replay {
+        record: String,
+        output: Option<String>,
+    },
+    Infer {
+        record: String,
+        samples: u32,
+    },
+    Repl,
+    Engine,
+    Serve {
+        address: String,
+    },
+    Help {
+        command: Option<String>,
+    },
+    Version,
+}
+
+/// ## Options
+/// Options shared by every subcommand.
+#[derive(Debug)]
+pub struct Options {
+    pub lexicon: String,
+    pub seed: Option<u64>,
+    pub threads: usize,
+    pub format: OutputFormat,
+    pub verbosity: Verbosity,
+}
+
+/// # Cli
+/// The parsed command line: a `Command` plus the global `Options`.
+/// Global options may be given before or after the subcommand.
+/// Functions:
+/// - `parse()`, `help()`
+#[derive(Debug)]
+pub struct Cli {
+    pub command: Command,
+    pub options: Options,
+}
+
+impl Cli {
+    /// ### parse()
+    /// Parse the arguments that follow the program name. With no
+    /// arguments at all the agent solves a random rack, as it always has.
+    pub fn parse(args: &[String]) -> Result<Cli, String> {
+        let mut options = Options {
+            lexicon: DEFAULT_DICTIONARY.to_string(),
+            seed: None,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            format: OutputFormat::Text,
+            verbosity: Verbosity::Normal,
+        };
+        // Pull the global options out first, everything else belongs
+        // to the subcommand
+        let mut rest: Vec<String> = Vec::new();
+        let mut help = false;
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "-l" | "--lexicon" => options.lexicon = value(arg, iter.next())?,
+                "-s" | "--seed" => {
+                    let seed = value(arg, iter.next())?;
+                    options.seed =
+                        Some(seed.parse().map_err(|_| {
+                            format!("--seed expects a whole number, got \"{}\"", seed)
+                        })?);
+                }
+                "-t" | "--threads" => {
+                    let threads = value(arg, iter.next())?;
+                    options.threads = match threads.parse() {
+                        Ok(n) if n > 0 => n,
+                        _ => {
+                            return Err(format!(
+                                "--threads expects a number above 0, got \"{}\"",
+                                threads
+                            ))
+                        }
+                    };
+                }
+                "-f" | "--format" => {
+                    options.format = match value(arg, iter.next())?.as_str() {
+                        "text" => OutputFormat::Text,
+                        "json" => OutputFormat::Json,
+                        other => {
+                            return Err(format!(
+                                "unknown format \"{}\", expected \"text\" or \"json\"",
+                                other
+                            ))
+                        }
+                    }
+                }
+                "-v" | "--verbose" => options.verbosity = Verbosity::Verbose,
+                "-q" | "--quiet" => options.verbosity = Verbosity::Quiet,
+                "-h" | "--help" => help = true,
+                "-V" | "--version" => {
+                    return Ok(Cli {
+                        command: Command::Version,
+                        options,
+                    })
+                }
+                _ => rest.push(arg.clone()),
+            }
+        }
+
+        let name = rest.first().cloned();
+        if help {
+            return Ok(Cli {
+                command: Command::Help { command: name },
+                options,
+            });
+        }
+        let command = match name {
+            None => Command::Solve {
+                rack: None,
+                time: None,
+                nodes: None,
+            },
+            Some(name) => Cli::parse_command(&name, &rest[1..])?,
+        };
+        Ok(Cli { command, options })
+    }
+
+    /// ### parse_command()
+    /// Parse the arguments of a single subcommand.
+    fn parse_command(name: &str, args: &[String]) -> Result<Command, String> {
+        let mut flags = Flags::new(name, args);
+        let command = match name {
+            "solve" => Command::Solve {
+                rack: flags.take("--rack")?,
+                time: flags.take_optional_number("--time")?,
+                nodes: flags.take_optional_number("--nodes")?,
+            },
+            "analyze" => Command::Analyze {
+                rack: flags.take("--rack")?,
+                top: flags.take_number("--top", 10)?,
+                time: flags.take_optional_number("--time")?,
+                nodes: flags.take_optional_number("--nodes")?,
+            },
+            "anagram" => Command::Anagram {
+                min_length: flags.take_number("--min", 2)?,
+                exact: flags.take_switch("--exact"),
+                letters: flags.positional("LETTERS")?,
+            },
+            "search" => {
+                let command = Command::Search {
+                    anagram: flags.take("--anagram")?,
+                    subanagram: flags.take("--subanagram")?,
+                    contains: flags.take("--contains")?,
+                    prefix: flags.take("--prefix")?,
+                    suffix: flags.take("--suffix")?,
+                    min_length: flags.take_number("--min", 1)?,
+                    max_length: flags.take_optional_number("--max")?,
+                    limit: flags.take_optional_number("--limit")?,
+                    pattern: flags.optional(),
+                };
+                if let Command::Search {
+                    pattern: None,
+                    anagram: None,
+                    subanagram: None,
+                    contains: None,
+                    prefix: None,
+                    suffix: None,
+                    ..
+                } = command
+                {
+                    return Err(format!(
+                        "`search` needs a pattern or a letter constraint\n\n{}",
+                        Cli::help(Some("search"))
+                    ));
+                }
+                command
+            }
+            "check" => {
+                let play = flags.take("--play")?;
+                let board = flags.take("--board")?;
+                if board.is_some() && play.is_none() {
+                    return Err("`check --board` also needs a --play to judge".to_string());
+                }
+                Command::Check {
+                    // The play may be quoted, "8H QUIZ", or not
+                    words: match play {
+                        Some(_) => flags.remaining(),
+                        None => flags.rest("WORD")?,
+                    },
+                    play,
+                    board,
+                }
+            }
+            "adjudicate" => Command::Adjudicate {
+                log: flags.take("--log")?,
+                words: flags.remaining(),
+            },
+            "play" => Command::Play {
+                rounds: flags.take_number("--rounds", 1)?,
+            },
+            "selfplay" => Command::SelfPlay {
+                games: flags.take_number("--games", 10)?,
+                strategies: match flags.take("--strategies")? {
+                    Some(names) => match names.split(',').collect::<Vec<&str>>()[..] {
+                        [first, second] => Some([first.to_string(), second.to_string()]),
+                        _ => {
+                            return Err(format!(
+                                "--strategies expects two names such as equity,greedy, got \"{}\"",
+                                names
+                            ))
+                        }
+                    },
+                    None => None,
+                },
+                challenge: match flags.take("--challenge")? {
+                    Some(rule) => ChallengeRule::parse(&rule)?,
+                    None => ChallengeRule::Void,
+                },
+                time: flags.take_optional_number("--time")?,
+                nodes: flags.take_optional_number("--nodes")?,
+                output: flags.take("--out")?,
+            },
+            "tournament" => Command::Tournament {
+                games: flags.take_number("--games", 10)?,
+                strategies: flags
+                    .take("--strategies")?
+                    .map(|names| names.split(',').map(|name| name.to_string()).collect()),
+                ratings: flags
+                    .take("--ratings")?
+                    .unwrap_or_else(|| DEFAULT_RATINGS.to_string()),
+                time: flags.take_optional_number("--time")?,
+                nodes: flags.take_optional_number("--nodes")?,
+            },
+            "leaves" => Command::Leaves {
+                games: flags.take_number("--games", 200)?,
+                strategy: flags
+                    .take("--strategy")?
+                    .unwrap_or_else(|| DEFAULT_STRATEGY.to_string()),
+                time: flags.take_optional_number("--time")?,
+                nodes: flags.take_optional_number("--nodes")?,
+                output: flags
+                    .take("--out")?
+                    .unwrap_or_else(|| DEFAULT_LEAVES.to_string()),
+            },
+            "quiz" => Command::Quiz {
+                rounds: flags.take_number("--rounds", 1)?,
+            },
+            "lexicon" => {
+                let action = flags.positional("ACTION")?;
+                if action != "build" {
+                    return Err(format!(
+                        "unknown lexicon action \"{}\", expected \"build\"",
+                        action
+                    ));
+                }
+                Command::LexiconBuild {
+                    input: flags.positional("IN")?,
+                    output: flags.positional("OUT")?,
+                }
+            }
+            "svg" => Command::Svg {
+                board: flags.take("--board")?,
+                play: flags.take("--play")?,
+                output: flags.take("--out")?,
+            },
+            "unseen" => Command::Unseen {
+                board: flags.take("--board")?,
+                rack: flags.take("--rack")?,
+                draw: flags.take("--draw")?,
+                count: flags.take_optional_number("--count")?,
+            },
+            "game" => Command::Game {
+                strategy: flags
+                    .take("--strategy")?
+                    .unwrap_or_else(|| DEFAULT_STRATEGY.to_string()),
+                challenge: match flags.take("--challenge")? {
+                    Some(rule) => ChallengeRule::parse(&rule)?,
+                    None => ChallengeRule::Void,
+                },
+                time: flags.take_number("--time", DEFAULT_THINK_TIME)?,
+                record: flags.take("--record")?,
+            },
+            "tui" => Command::Tui {
+                strategy: flags
+                    .take("--strategy")?
+                    .unwrap_or_else(|| DEFAULT_STRATEGY.to_string()),
+                time: flags.take_number("--time", DEFAULT_THINK_TIME)?,
+                record: flags.take("--record")?,
+            },
+            "replay" => Command::Replay {
+                output: flags.take("--out")?,
+                record: flags.positional("RECORD")?,
+            },
+            "infer" => Command::Infer {
+                samples: flags.take_number("--samples", DEFAULT_SAMPLES as u32)?,
+                record: flags.positional("RECORD")?,
+            },
+            "repl" => Command::Repl,
+            "engine" => Command::Engine,
+            "serve" => Command::Serve {
+                address: flags
+                    .take("--address")?
+                    .unwrap_or_else(|| DEFAULT_ADDRESS.to_string()),
+            },
+            "help" => Command::Help {
+                command: flags.args.pop(),
+            },
+            other => return Err(format!("unknown command \"{}\"", other)),
+        };
+        flags.finish()?;
+        Ok(command)
+    }
+
+    /// ### help()
+    /// The help text for a subcommand, or the overall usage.
+    pub fn help(command: Option<&str>) -> &'static str {
+        match command {
+            Some("solve") => {
+                "Usage: scrabble solve [--rack LETTERS] [--time MS] [--nodes N]\n\n\
+                 Find the best first move. Without --rack a random rack is drawn.\n\
+                 Wildcards are written as ? or _.\n\n\
+                 With --time or --nodes the search stops after MS milliseconds or\n\
+                 N trie nodes and plays the best move found so far."
+            }
+            Some("analyze") => {
+                "Usage: scrabble analyze [--rack LETTERS] [--top N] [--time MS] [--nodes N]\n\n\
+                 List the N best candidate first moves [default: 10]. With --time\n\
+                 or --nodes only the moves found within the limit are listed."
+            }
+            Some("anagram") => {
+                "Usage: scrabble anagram <LETTERS> [--min N] [--exact]\n\n\
+                 List every word of at least N letters [default: 2] that can be\n\
+                 made from LETTERS, or with --exact only the words that use all\n\
+                 of them. Wildcards are written as ? or _."
+            }
+            Some("search") => {
+                "Usage: scrabble search [PATTERN] [--anagram LETTERS] [--subanagram LETTERS]\n\
+                 \x20                      [--contains LETTERS] [--prefix P] [--suffix S]\n\
+                 \x20                      [--min N] [--max N] [--limit N]\n\n\
+                 Find words matching every constraint given. In PATTERN:\n\
+                 \x20 ? or .   any single letter\n\
+                 \x20 *        zero or more letters\n\
+                 \x20 [ABC]    one of A, B or C; [A-F] a range; [^AEIOU] anything else\n\n\
+                 --anagram uses every letter given, --subanagram any of them; ? is a blank.\n\
+                 --contains requires the letters to appear somewhere in the word.\n\n\
+                 Examples: scrabble search '?A?E*'    scrabble search --anagram RETAINS?"
+            }
+            Some("check") => {
+                "Usage: scrabble check <WORD>...\n\
+                 \x20      scrabble check --play MOVE [--board ROWS]\n\n\
+                 Check each word against the lexicon and show its front and back\n\
+                 hooks, the letters that can be added to make another word.\n\n\
+                 With --play the words are those a play such as \"8H QUIZ\" forms on\n\
+                 the board (empty unless --board gives its rows, as for svg): the\n\
+                 main word and every cross-word. The play is only acceptable if\n\
+                 every word is valid.\n\n\
+                 Exit status: 0 if every word is valid, 1 if any word is not,\n\
+                 2 on errors. Combine with --quiet for scripting."
+            }
+            Some("adjudicate") => {
+                "Usage: scrabble adjudicate [--log PATH] [<WORD>...]\n\n\
+                 Judge the words formed by one play and answer only whether the\n\
+                 play is acceptable, never which word failed. Without words, read\n\
+                 one play per line from standard input. Decisions are appended to\n\
+                 the --log file when given.\n\n\
+                 Exit status for a single play: 0 if acceptable, 1 if not."
+            }
+            Some("play") => {
+                "Usage: scrabble play [--rounds N]\n\n\
+                 You and the agent get the same rack; enter your best first move.\n\
+                 Use lowercase letters for tiles played with a wildcard."
+            }
+            Some("selfplay") => {
+                "Usage: scrabble selfplay [--games N] [--strategies A,B] [--challenge RULE]\n\
+                 \x20                        [--time MS] [--nodes N] [--out FILE]\n\n\
+                 With --strategies, play N full games [default: 10] between the\n\
+                 strategies A and B (sim, sim:uniform, equity, greedy, random or\n\
+                 equity:FILE with a leave table from the leaves command) and\n\
+                 report each one's win rate, average score and spread, bingos per game and time per\n\
+                 move. Game i is shuffled by the seed plus i and the strategies take\n\
+                 turns moving first, so --seed repeats a whole match. Games are\n\
+                 played in parallel on --threads threads. --time and --nodes limit\n\
+                 each move's search; RULE is the challenge rule, as in `game`.\n\
+                 --out writes every game to FILE, as CSV if it ends in .csv and as\n\
+                 JSON otherwise.\n\n\
+                 Without --strategies, solve N random opening racks and report\n\
+                 score and timing stats."
+            }
+            Some("tournament") => {
+                "Usage: scrabble tournament [--games N] [--strategies A,B,...] [--ratings FILE]\n\
+                 \x20                          [--time MS] [--nodes N]\n\n\
+                 Play a round robin in which every strategy meets every other for N\n\
+                 games [default: 10], then update each one's Glicko rating and save\n\
+                 the ratings to FILE [default: ratings.txt]. Run it again after\n\
+                 changing a strategy to see whether it got stronger; a strategy's\n\
+                 deviation says how far its rating can still be trusted.\n\n\
+                 Every registered strategy plays unless --strategies names some.\n\
+                 --seed repeats a tournament; --time and --nodes limit each move."
+            }
+            Some("leaves") => {
+                "Usage: scrabble leaves [--games N] [--strategy NAME] [--time MS] [--nodes N]\n\
+                 \x20                      [--out FILE]\n\n\
+                 Play N games [default: 200] of a strategy [default: equity] against\n\
+                 itself, then replay them and note every leave kept while the bag\n\
+                 still had tiles, with the points scored on the following turn. A\n\
+                 table of single tiles, duos and vowel/consonant balances is fitted\n\
+                 to those outcomes and written to FILE [default: leaves.txt].\n\n\
+                 The equity strategy uses the table when named equity:FILE, e.g.\n\
+                 selfplay --strategies equity:leaves.txt,equity to compare it with\n\
+                 the hand-tuned leaves, or leaves --strategy equity:leaves.txt to\n\
+                 fit again from its own games. --seed makes the whole run\n\
+                 repeatable; --time makes moves depend on speed, so prefer --nodes."
+            }
+            Some("quiz") => {
+                "Usage: scrabble quiz [--rounds N]\n\n\
+                 Find as many words as you can in a random rack."
+            }
+            Some("lexicon") => {
+                "Usage: scrabble lexicon build <IN> <OUT>\n\n\
+                 Compile a word list into a lexicon: upper-case it, drop duplicates\n\
+                 and invalid entries, and store it grouped by alphagram so anagram\n\
+                 lookups need no indexing when it is loaded with --lexicon OUT."
+            }
+            Some("svg") => {
+                "Usage: scrabble svg [--board ROWS] [--play MOVE] [--out FILE]\n\n\
+                 Draw a board as an SVG diagram with its premium squares and tile\n\
+                 values; wildcard tiles are shown in red without a value. ROWS is\n\
+                 the board notation, rows from the top separated by /, with a\n\
+                 number for a run of empty squares [default: the empty board].\n\
+                 --play outlines a play such as \"8H QUIZ\", drawing any of its\n\
+                 tiles not yet on the board. The diagram is printed unless --out\n\
+                 names a file to write it to."
+            }
+            Some("game") => {
+                "Usage: scrabble game [--strategy NAME] [--challenge RULE] [--time MS] [--record FILE]\n\n\
+                 Play a full game against the agent. Type your moves in notation:\n\
+                 8H QUIXOTE reads across from 8H, H8 QUIXOTE down, lowercase\n\
+                 letters are wildcards; -QV or exchange QV swaps tiles and pass\n\
+                 passes. Every move is checked and scored, and the game goes on\n\
+                 until a player goes out or six turns in a row score nothing.\n\n\
+                 The agent plays with the strategy NAME: equity (score plus the\n\
+                 value of the tiles kept) [default], greedy (highest score),\n\
+                 random, equity:FILE (leaves valued by a table fitted by the\n\
+                 leaves command) or sim (the best equity plays tried against the\n\
+                 replies of racks inferred from your last play; sim:uniform draws\n\
+                 them blindly), thinking for up to MS milliseconds a move\n\
+                 [default: 2000].\n\n\
+                 RULE decides what happens to phonies: void refuses them outright\n\
+                 [default]; under single, double, 5-point or 10-point they can be\n\
+                 played and challenged (type challenge) before the next move. A\n\
+                 challenged phony is withdrawn. A challenge of a valid play costs\n\
+                 nothing under single, the challenger's turn under double, and\n\
+                 gives the player challenged 5 or 10 points under N-point.\n\
+                 --seed replays a game; --record writes it to FILE for `replay`."
+            }
+            Some("tui") => {
+                "Usage: scrabble tui [--strategy NAME] [--time MS] [--record FILE]\n\n\
+                 Play a full game against the agent on a colored board in the\n\
+                 terminal. Move the cursor with the arrow keys and type letters to\n\
+                 place tiles along the direction shown (Tab turns it); a letter you\n\
+                 don't have is played with a wildcard. Enter plays the tiles,\n\
+                 Backspace takes one back and Esc clears them. Ctrl-E exchanges,\n\
+                 Ctrl-P passes and Ctrl-Q quits. The agent plays with the strategy\n\
+                 NAME, as in `game`, thinking for up to MS milliseconds a move\n\
+                 [default: 2000]; --seed replays a game.\n\
+                 With --record the game is written to FILE for `replay`."
+            }
+            Some("unseen") => {
+                "Usage: scrabble unseen [--board ROWS] [--rack RACK] [--draw TILES] [--count N]\n\n\
+                 List the tiles a player can't see: a full bag less the tiles on\n\
+                 the board ROWS (as for svg) and on their RACK, so those in the bag\n\
+                 and on the opponent's rack, who is taken to hold seven.\n\n\
+                 With --draw, give the chance of drawing all of TILES (e.g. S, QU\n\
+                 or ?, repeats meaning that many) when drawing N tiles, or when\n\
+                 drawing 1 to 7 tiles without --count. Not knowing the opponent's\n\
+                 rack, a draw is as good as one from every unseen tile."
+            }
+            Some("infer") => {
+                "Usage: scrabble infer <RECORD> [--samples N]\n\n\
+                 Replay a game record and, for the player to move, estimate what\n\
+                 the opponent kept with their last play: N leaves [default: 64]\n\
+                 are drawn from the unseen tiles and each is weighed by how close\n\
+                 the play comes to the best one that leave allowed, by equity.\n\
+                 Shows the likeliest leaves and, tile by tile, the chance the\n\
+                 opponent holds it now against the chance with nothing inferred.\n\
+                 The sim strategy draws opponent racks this way; sim:uniform\n\
+                 draws them blindly. --seed repeats an estimate."
+            }
+            Some("replay") => {
+                "Usage: scrabble replay <RECORD> [--out FILE]\n\n\
+                 Check a game record move by move and write a self-contained HTML\n\
+                 page with the board, scores and move list, stepped through with\n\
+                 buttons or the arrow keys [default: RECORD with .html].\n\n\
+                 A record is a seed line, an optional players line and one move a\n\
+                 line; anything after # annotates the move:\n\n\
+                 \x20 seed 42\n\
+                 \x20 players Ann Bob\n\
+                 \x20 8D ZAX  # the Z on a double letter\n\
+                 \x20 -UUV\n\
+                 \x20 pass"
+            }
+            Some("repl") => {
+                "Usage: scrabble repl\n\n\
+                 Start an interactive shell that loads the lexicon once and keeps a\n\
+                 full board and a rack between commands:\n\n\
+                 \x20 rack AEINST?\n\
+                 \x20 play 8H QUIZ\n\
+                 \x20 best 5\n\
+                 \x20 undo\n\n\
+                 Type help in the shell for every command. With --format json there\n\
+                 is no prompt and each command answers with one JSON object a line."
+            }
+            Some("engine") => {
+                "Usage: scrabble engine\n\n\
+                 Read engine protocol commands from stdin and answer on stdout, so a\n\
+                 GUI or script can use the agent on a full board. A session looks like:\n\n\
+                 \x20 scrabble             -> id ..., option ..., scrabbleok\n\
+                 \x20 isready              -> readyok (loads the lexicon)\n\
+                 \x20 position empty moves 8H QUIZ\n\
+                 \x20 rack AEINST?\n\
+                 \x20 go movetime 1000     -> info ... pv 9G ..., bestmove 9G ...\n\
+                 \x20 quit\n\n\
+                 Other commands: setoption name Threads|MultiPV|Lexicon value V,\n\
+                 lexicon PATH, newgame, position board ROWS [moves ...], stop, d.\n\
+                 Plays are written as on a score sheet: 8H WORD reads across from\n\
+                 row 8 column H, H8 WORD reads down; lowercase letters are wildcards."
+            }
+            Some("serve") => {
+                "Usage: scrabble serve [--address HOST:PORT]\n\n\
+                 Answer JSON requests on HTTP [default: 127.0.0.1:8080]. The lexicon\n\
+                 is loaded once and kept for every request. Parameters come from the\n\
+                 query string or a JSON object body:\n\n\
+                 \x20 GET  /health\n\
+                 \x20 GET  /check?words=QI,ZA[&play=true]\n\
+                 \x20 GET  /anagram?letters=AEINST%3F[&min=N][&exact=true]\n\
+                 \x20 POST /analyze {\"rack\": \"AEINST?\", \"board\": ROWS, \"moves\": [\"8H QUIZ\"],\n\
+                 \x20                \"top\": 10, \"time_ms\": 1000, \"nodes\": N}\n\
+                 \x20 POST /replay  {\"moves\": [\"8H QUIZ\", \"pass\"], \"players\": 2}\n\n\
+                 /check with play judges the words as one play and answers only the\n\
+                 verdict, never which word failed."
+            }
+            _ => USAGE,
+        }
+    }
+}
+
+/// Take the value following an option, or explain that it is missing.
+fn value(option: &str, next: Option<&String>) -> Result<String, String> {
+    match next {
+        Some(v) if !v.starts_with('-') || v.len() == 1 => Ok(v.clone()),
+        _ => Err(format!("{} expects a value", option)),
+    }
+}
+
+/// ## Flags
+/// Small helper that consumes the arguments of a subcommand and makes
+/// sure nothing unexpected is left over.
+struct Flags<'a> {
+    command: &'a str,
+    args: Vec<String>,
+}
+
+impl<'a> Flags<'a> {
+    fn new(command: &'a str, args: &[String]) -> Self {
+        Flags {
+            command,
+            args: args.to_vec(),
+        }
+    }
+
+    /// Remove `flag <value>` from the arguments if present.
+    fn take(&mut self, flag: &str) -> Result<Option<String>, String> {
+        match self.args.iter().position(|a| a == flag) {
+            None => Ok(None),
+            Some(i) => {
+                let v = value(flag, self.args.get(i + 1))?;
+                self.args.drain(i..i + 2);
+                Ok(Some(v))
+            }
+        }
+    }
+
+    /// Like `take_number()` but with no default.
+    fn take_optional_number<T: std::str::FromStr>(
+        &mut self,
+        flag: &str,
+    ) -> Result<Option<T>, String> {
+        match self.take(flag)? {
+            None => Ok(None),
+            Some(v) => v
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("{} expects a whole number, got \"{}\"", flag, v)),
+        }
+    }
+
+    /// Remove a flag that takes no value, returning whether it was present.
+    fn take_switch(&mut self, flag: &str) -> bool {
+        match self.args.iter().position(|a| a == flag) {
+            None => false,
+            Some(i) => {
+                self.args.remove(i);
+                true
+            }
+        }
+    }
+
+    /// Like `take()` but parses the value as a number.
+    fn take_number<T: std::str::FromStr>(&mut self, flag: &str, default: T) -> Result<T, String> {
+        match self.take(flag)? {
+            None => Ok(default),
+            Some(v) => v
+                .parse()
+                .map_err(|_| format!("{} expects a whole number, got \"{}\"", flag, v)),
+        }
+    }
+
+    /// Remove the next positional argument if there is one.
+    fn optional(&mut self) -> Option<String> {
+        let i = self.args.iter().position(|a| !a.starts_with("--"))?;
+        Some(self.args.remove(i))
+    }
+
+    /// Remove the next positional argument.
+    fn positional(&mut self, name: &str) -> Result<String, String> {
+        match self.args.iter().position(|a| !a.starts_with("--")) {
+            Some(i) => Ok(self.args.remove(i)),
+            None => Err(format!(
+                "`{}` is missing <{}>\n\n{}",
+                self.command,
+                name,
+                Cli::help(Some(self.command))
+            )),
+        }
+    }
+
+    /// Remove every remaining positional argument.
+    fn remaining(&mut self) -> Vec<String> {
+        let mut values = Vec::new();
+        while let Some(i) = self.args.iter().position(|a| !a.starts_with("--")) {
+            values.push(self.args.remove(i));
+        }
+        values
+    }
+
+    /// Like `remaining()`, but at least one argument is required.
+    fn rest(&mut self, name: &str) -> Result<Vec<String>, String> {
+        let values = self.remaining();
+        if values.is_empty() {
+            return Err(format!(
+                "`{}` needs at least one <{}>\n\n{}",
+                self.command,
+                name,
+                Cli::help(Some(self.command))
+            ));
+        }
+        Ok(values)
+    }
+
+    /// Fail if any argument was not consumed.
+    fn finish(self) -> Result<(), String> {
+        match self.args.first() {
+            None => Ok(()),
+            Some(arg) => Err(format!(
+                "unexpected argument \"{}\" for `{}`\n\n{}",
+                arg,
+                self.command,
+                Cli::help(Some(self.command))
+            )),
+        }
+    }
+}
@@ -0,0 +1,1766 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::{json, Value};
+
+use scrabble::agent::agent::Agent;
+use scrabble::agent::controller::{
+    Progress, SearchController, SearchLimits, SearchResult, StopReason,
+};
+use scrabble::agent::inference::RackInference;
+use scrabble::agent::leaves::{self, LeaveTable, DEFAULT_RIDGE};
+use scrabble::agent::rack::Rack;
+use scrabble::agent::strategy::{self, STRATEGIES};
+use scrabble::anagrams::anagrams::Anagram;
+use scrabble::board::play::Play;
+use scrabble::board::position::Position;
+use scrabble::board::svg::to_svg;
+use scrabble::engine::protocol::Engine;
+use scrabble::game::game::{Action, ChallengeRule, Game, RACK_SIZE};
+use scrabble::game::html::to_html;
+use scrabble::game::rating::{Ratings, Tournament};
+use scrabble::game::record::Record;
+use scrabble::game::selfplay::{SelfPlay, CSV_HEADER};
+use scrabble::game::tracker::TileTracker;
+use scrabble::search::search::{Pattern, Query, RackMode};
+use scrabble::server::server::Server;
+use scrabble::utils::adjudicator::{Adjudicator, Verdict};
+use scrabble::utils::lexicon::Lexicon;
+use scrabble::utils::trieguy::TrieTree;
+use scrabble::utils::weighted_group::WeightedGroups;
+use scrabble::utils::word_check::{PlayJudgement, WordCheck};
+
+use crate::cli::cli::{Cli, Command, Options, OutputFormat, Verbosity};
+use crate::cli::repl::Repl;
+use crate::cli::tui::Tui;
+
+/// Exit status when a command succeeds.
+pub const EXIT_OK: i32 = 0;
+/// Exit status when `check` finds a word that is not in the lexicon.
+pub const EXIT_INVALID: i32 = 1;
+/// Exit status for usage errors and failures such as a missing lexicon.
+pub const EXIT_ERROR: i32 = 2;
+
+/// ### run()
+/// Execute a parsed `Cli` and return the process exit status. Every
+/// command reports failures as a message for `main()` to print rather
+/// than panicking.
+pub fn run(cli: &Cli) -> Result<i32, String> {
+    let options = &cli.options;
+    let result = match &cli.command {
+        Command::Solve { rack, time, nodes } => {
+            solve(options, rack.as_deref(), limits(*time, *nodes))
+        }
+        Command::Analyze {
+            rack,
+            top,
+            time,
+            nodes,
+        } => analyze(options, rack.as_deref(), *top, limits(*time, *nodes)),
+        Command::Anagram {
+            letters,
+            min_length,
+            exact,
+        } => anagram(options, letters, *min_length, *exact),
+        Command::Search {
+            pattern,
+            anagram,
+            subanagram,
+            contains,
+            prefix,
+            suffix,
+            min_length,
+            max_length,
+            limit,
+        } => {
+            let mut query = Query::new();
+            if let Some(pattern) = pattern {
+                query.pattern = Some(Pattern::parse(pattern)?);
+            }
+            if let Some(letters) = anagram {
+                query.set_rack(letters, RackMode::Anagram)?;
+            }
+            if let Some(letters) = subanagram {
+                query.set_rack(letters, RackMode::Subanagram)?;
+            }
+            if let Some(letters) = contains {
+                query.set_contains(letters)?;
+            }
+            query.prefix = letters_only("--prefix", prefix.as_deref())?;
+            query.suffix = letters_only("--suffix", suffix.as_deref())?;
+            query.min_length = *min_length;
+            query.max_length = *max_length;
+            search(options, &query, *limit)
+        }
+        Command::Check { words, play, board } => {
+            return check(options, words, play.as_deref(), board.as_deref())
+        }
+        Command::Adjudicate { words, log } => return adjudicate(options, words, log.as_deref()),
+        Command::Play { rounds } => play(options, *rounds),
+        Command::SelfPlay {
+            games,
+            strategies: Some(strategies),
+            challenge,
+            time,
+            nodes,
+            output,
+        } => {
+            let mut harness = SelfPlay::new(
+                &strategies[0],
+                &strategies[1],
+                *games as usize,
+                options.seed.unwrap_or_else(rand::random),
+            );
+            harness.limits = limits(*time, *nodes);
+            harness.threads = options.threads;
+            harness.challenge_rule = *challenge;
+            selfplay_match(options, &harness, output.as_deref())
+        }
+        Command::SelfPlay { games, .. } => selfplay(options, *games),
+        Command::Tournament {
+            games,
+            strategies,
+            ratings,
+            time,
+            nodes,
+        } => {
+            let strategies = match strategies {
+                Some(names) => names.clone(),
+                None => STRATEGIES.iter().map(|name| name.to_string()).collect(),
+            };
+            let mut event = Tournament::new(
+                strategies,
+                *games as usize,
+                options.seed.unwrap_or_else(rand::random),
+            );
+            event.limits = limits(*time, *nodes);
+            event.threads = options.threads;
+            tournament(options, &event, ratings)
+        }
+        Command::Leaves {
+            games,
+            strategy,
+            time,
+            nodes,
+            output,
+        } => {
+            let mut harness = SelfPlay::new(
+                strategy,
+                strategy,
+                *games as usize,
+                options.seed.unwrap_or_else(rand::random),
+            );
+            harness.limits = limits(*time, *nodes);
+            harness.threads = options.threads;
+            leaves(options, &harness, output)
+        }
+        Command::Quiz { rounds } => quiz(options, *rounds),
+        Command::LexiconBuild { input, output } => lexicon_build(options, input, output),
+        Command::Serve { address } => serve(options, address),
+        Command::Replay { record, output } => replay(options, record, output.as_deref()),
+        Command::Infer { record, samples } => infer(options, record, *samples as usize),
+        Command::Unseen {
+            board,
+            rack,
+            draw,
+            count,
+        } => unseen(
+            options,
+            board.as_deref(),
+            rack.as_deref(),
+            draw.as_deref(),
+            count.map(|count| count as usize),
+        ),
+        Command::Svg {
+            board,
+            play,
+            output,
+        } => svg(
+            options,
+            board.as_deref(),
+            play.as_deref(),
+            output.as_deref(),
+        ),
+        Command::Game {
+            strategy,
+            challenge,
+            time,
+            record,
+        } => game(options, strategy, *challenge, *time, record.as_deref()),
+        Command::Tui {
+            strategy,
+            time,
+            record,
+        } => {
+            let lexicon = load_lexicon(options)?;
+            let seed = options.seed.unwrap_or_else(rand::random);
+            let strategy = strategy::by_name(strategy, think_limits(*time), options.threads, seed)?;
+            let mut tui = Tui::new(&lexicon, seed, strategy);
+            let scores = tui.run()?;
+            if let Some(file) = record {
+                let text = Record::from_game(tui.game(), ["You", "Agent"]).to_text();
+                fs::write(file, text).map_err(|e| format!("cannot write {}: {}", file, e))?;
+            }
+            match options.format {
+                OutputFormat::Json => println!(
+                    "{}",
+                    json!({ "seed": seed, "you": scores[0], "agent": scores[1] })
+                ),
+                OutputFormat::Text => println!(
+                    "Final score - you: {}, agent: {} (seed {})",
+                    scores[0], scores[1], seed
+                ),
+            }
+            Ok(())
+        }
+        Command::Repl => {
+            let lexicon = load_lexicon(options)?;
+            Repl::new(lexicon, options.threads, options.format).run(io::stdin().lock())
+        }
+        Command::Engine => {
+            Engine::new(&options.lexicon, options.threads).run(io::stdin().lock());
+            Ok(())
+        }
+        Command::Help { command } => {
+            let help = Cli::help(command.as_deref());
+            match options.format {
+                OutputFormat::Json => println!("{}", json!({ "help": help })),
+                OutputFormat::Text => println!("{}", help),
+            }
+            Ok(())
+        }
+        Command::Version => {
+            match options.format {
+                OutputFormat::Json => println!(
+                    "{}",
+                    json!({ "name": "scrabble", "version": env!("CARGO_PKG_VERSION") })
+                ),
+                OutputFormat::Text => println!("scrabble {}", env!("CARGO_PKG_VERSION")),
+            }
+            Ok(())
+        }
+    };
+    result.map(|_| EXIT_OK)
+}
+
+/// ### load_lexicon()
+/// Load the `Lexicon` named in the options, checking first that the
+/// file exists so the user gets a readable error.
+fn load_lexicon(options: &Options) -> Result<Lexicon, String> {
+    if !Path::new(&options.lexicon).is_file() {
+        return Err(format!(
+            "lexicon \"{}\" not found; pass a word list with --lexicon <PATH>",
+            options.lexicon
+        ));
+    }
+    let start = Instant::now();
+    let lexicon = Lexicon::load(&options.lexicon)?;
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!(
+            "Loaded lexicon {} in {:.2?}",
+            options.lexicon,
+            start.elapsed()
+        );
+    }
+    Ok(lexicon)
+}
+
+/// ### rng()
+/// The random number generator for this run; seeded when `--seed` is given.
+fn rng(options: &Options) -> StdRng {
+    let seed = options.seed.unwrap_or_else(rand::random);
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("Seed: {}", seed);
+    }
+    StdRng::seed_from_u64(seed)
+}
+
+/// ### rack()
+/// Use the rack given on the command line, or draw a random one.
+fn rack(options: &Options, letters: Option<&str>) -> Result<Rack, String> {
+    match (letters, options.seed) {
+        (Some(letters), _) => Ok(Rack::from_letters(letters)?),
+        (None, Some(_)) => Ok(Rack::from_rng(&mut rng(options))),
+        (None, None) => Ok(Rack::new()),
+    }
+}
+
+/// Search limits from the `--time` (milliseconds) and `--nodes` flags.
+fn limits(time: Option<u64>, nodes: Option<u64>) -> SearchLimits {
+    SearchLimits {
+        time: time.map(Duration::from_millis),
+        nodes,
+    }
+}
+
+/// A `SearchController` for `limits` that reports its progress on
+/// stderr when verbose.
+fn controller<'a>(options: &Options, limits: SearchLimits) -> SearchController<'a> {
+    let mut controller = SearchController::new(limits);
+    if options.verbosity == Verbosity::Verbose {
+        controller.on_progress(|progress: &Progress| {
+            eprintln!(
+                "[{:>8.2?}] {}/{} letters, {} nodes, {} candidates, best {}",
+                progress.elapsed,
+                progress.units_done,
+                progress.units_total,
+                progress.nodes,
+                progress.candidates,
+                progress.best.map_or("-".to_string(), |m| format!(
+                    "{} {}",
+                    m.anagram.word, m.score
+                )),
+            )
+        });
+    }
+    controller
+}
+
+/// Tell the user on stderr when a limit cut the search short.
+fn report_stop(options: &Options, result: &SearchResult) {
+    if result.stop_reason != StopReason::Complete && options.verbosity != Verbosity::Quiet {
+        eprintln!(
+            "search stopped early ({}) after {} nodes in {:.2?}",
+            result.stop_reason.as_str(),
+            result.nodes,
+            result.elapsed
+        );
+    }
+}
+
+fn solve(options: &Options, letters: Option<&str>, limits: SearchLimits) -> Result<(), String> {
+    let rack = rack(options, letters)?;
+    let lexicon = load_lexicon(options)?;
+    let mut agent = Agent::with_rack(rack);
+    agent.threads = options.threads;
+    let mut controller = controller(options, limits);
+    if options.format == OutputFormat::Text && options.verbosity != Verbosity::Quiet {
+        println!("\n\n===========SCRABBLE===========\n\n");
+        let result = agent.solve(&lexicon.trie, &mut controller)?;
+        report_stop(options, &result);
+        return Ok(());
+    }
+
+    let rack_word: String = agent.rack.letters.iter().collect();
+    let result = controller.run(&agent, &lexicon.trie);
+    report_stop(options, &result);
+    let best = result
+        .best()
+        .ok_or_else(|| format!("no move found for rack {}", rack_word))?;
+    match options.format {
+        OutputFormat::Json => {
+            agent.make_move(&best.anagram, best.starting_element)?;
+            agent.board.set_score(best.score);
+            let mut value = best.to_json();
+            value["rack"] = json!(rack_word);
+            value["leave"] = json!(agent.rack.leave(&best.anagram.word));
+            value["board"] = agent.board.to_json();
+            value["search"] = result.stats();
+            println!("{}", value);
+        }
+        OutputFormat::Text => println!(
+            "{} {} {}",
+            best.anagram.word, best.starting_element, best.score
+        ),
+    }
+    Ok(())
+}
+
+fn analyze(
+    options: &Options,
+    letters: Option<&str>,
+    top: usize,
+    limits: SearchLimits,
+) -> Result<(), String> {
+    let rack = rack(options, letters)?;
+    let lexicon = load_lexicon(options)?;
+    let mut agent = Agent::with_rack(rack);
+    agent.threads = options.threads;
+    let rack_word: String = agent.rack.letters.iter().collect();
+    let result = controller(options, limits).run(&agent, &lexicon.trie);
+    report_stop(options, &result);
+    let moves = &result.moves;
+
+    match options.format {
+        OutputFormat::Json => {
+            let moves: Vec<_> = moves
+                .iter()
+                .take(top)
+                .map(|m| {
+                    let mut value = m.to_json();
+                    value["leave"] = json!(agent.rack.leave(&m.anagram.word));
+                    value
+                })
+                .collect();
+            println!(
+                "{}",
+                json!({
+                    "rack": rack_word,
+                    "moves": moves,
+                    "search": result.stats(),
+                })
+            );
+        }
+        OutputFormat::Text => {
+            if options.verbosity != Verbosity::Quiet {
+                println!("Rack: {}\n", rack_word);
+                println!(
+                    "{:>4}  {:<8} {:>5}  {:>5}  {:>5}",
+                    "#", "Word", "Start", "Score", "Est."
+                );
+            }
+            for (i, m) in moves.iter().take(top).enumerate() {
+                println!(
+                    "{:>4}  {:<8} {:>5}  {:>5}  {:>5}",
+                    i + 1,
+                    m.anagram.word,
+                    m.starting_element,
+                    m.score,
+                    m.anagram.score
+                );
+            }
+            if options.verbosity == Verbosity::Verbose {
+                println!(
+                    "\n{} candidates, {} nodes in {:.2?}",
+                    moves.len(),
+                    result.nodes,
+                    result.elapsed
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ### anagram()
+/// Answer from the alphagram index: every word that uses all of the
+/// letters with `exact`, otherwise every word that uses some of them.
+fn anagram(options: &Options, letters: &str, min_length: usize, exact: bool) -> Result<(), String> {
+    let rack = Rack::from_letters(letters)?;
+    let rack_word: String = rack.letters.iter().collect();
+    let lexicon = load_lexicon(options)?;
+    let start = Instant::now();
+    let words = if exact {
+        lexicon.alphagrams.blank_anagrams(&rack_word)
+    } else {
+        lexicon.alphagrams.subanagrams(&rack_word, min_length)
+    };
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("Found {} words in {:.2?}", words.len(), start.elapsed());
+    }
+
+    match options.format {
+        OutputFormat::Json => println!("{}", json!({ "letters": letters, "words": words })),
+        OutputFormat::Text => {
+            if words.is_empty() && options.verbosity != Verbosity::Quiet {
+                println!("No words found in {}", letters);
+            }
+            // One line per word length, longest first
+            let mut length = 0;
+            for word in &words {
+                if word.len() != length {
+                    if length != 0 {
+                        println!();
+                    }
+                    length = word.len();
+                    if options.verbosity != Verbosity::Quiet {
+                        print!("{}: ", length);
+                    }
+                } else {
+                    print!(" ");
+                }
+                print!("{}", word);
+            }
+            if !words.is_empty() {
+                println!();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Upper-case an optional argument that may only contain letters.
+fn letters_only(flag: &str, value: Option<&str>) -> Result<String, String> {
+    let value = value.unwrap_or("").to_uppercase();
+    if !value.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(format!("{} expects letters only, got \"{}\"", flag, value));
+    }
+    Ok(value)
+}
+
+/// ### search()
+/// Run a word `Query` and print the matches, up to `limit` of them.
+fn search(options: &Options, query: &Query, limit: Option<usize>) -> Result<(), String> {
+    let lexicon = load_lexicon(options)?;
+    let start = Instant::now();
+    let (words, nodes) = query.run(&lexicon.trie);
+    let elapsed = start.elapsed();
+    let shown = &words[..limit.unwrap_or(words.len()).min(words.len())];
+
+    match options.format {
+        OutputFormat::Json => println!("{}", json!({ "count": words.len(), "words": shown })),
+        OutputFormat::Text => {
+            for word in shown {
+                println!("{}", word);
+            }
+            if options.verbosity != Verbosity::Quiet {
+                println!("\n{} words", words.len());
+            }
+            if options.verbosity == Verbosity::Verbose {
+                eprintln!("Visited {} trie nodes in {:.2?}", nodes, elapsed);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ### check()
+/// Look each word up in the lexicon, or judge `play` on `board` by every
+/// word it forms there. Returns `EXIT_INVALID` when a word is not valid
+/// so scripts can rely on the exit status alone.
+fn check(
+    options: &Options,
+    words: &[String],
+    play: Option<&str>,
+    board: Option<&str>,
+) -> Result<i32, String> {
+    let lexicon = load_lexicon(options)?;
+    let play = match play {
+        Some(play) => {
+            let position = match board {
+                Some(rows) => Position::parse(rows)?,
+                None => Position::new(),
+            };
+            // The rest of an unquoted play, as in --play 8H QUIZ
+            let notation = std::iter::once(play).chain(words.iter().map(String::as_str));
+            let play = Play::from_notation(&notation.collect::<Vec<&str>>().join(" "))?;
+            let play = position.check_placement(&play, &WeightedGroups::new())?;
+            Some((position.words_formed(&play), play))
+        }
+        None => None,
+    };
+    let words = match &play {
+        Some((formed, _)) => formed,
+        None => words,
+    };
+    let judgement = PlayJudgement::new(words, &lexicon.trie);
+    let acceptable = judgement.is_acceptable();
+
+    match options.format {
+        OutputFormat::Json => {
+            let results: Vec<_> = judgement
+                .words
+                .iter()
+                .map(|w| check_json(w, &lexicon.trie))
+                .collect();
+            if let Some((_, play)) = &play {
+                println!(
+                    "{}",
+                    json!({
+                        "play": play.notation(),
+                        "score": play.score,
+                        "words": results,
+                        "acceptable": acceptable,
+                    })
+                );
+            } else {
+                println!("{}", json!(results));
+            }
+        }
+        OutputFormat::Text => {
+            if options.verbosity != Verbosity::Quiet {
+                for check in &judgement.words {
+                    print_check(check, &lexicon.trie);
+                }
+                if let Some((_, play)) = &play {
+                    if acceptable {
+                        println!(
+                            "{} scores {}, play is acceptable",
+                            play.notation(),
+                            play.score
+                        );
+                    } else {
+                        println!(
+                            "Play is not acceptable, phony: {}",
+                            judgement.phonies().join(" ")
+                        );
+                    }
+                }
+            }
+        }
+    }
+    Ok(if acceptable { EXIT_OK } else { EXIT_INVALID })
+}
+
+/// Print one checked word with its hooks, e.g.
+/// `CARE            valid    front: S  back: DRST`
+pub(crate) fn print_check(check: &WordCheck, tree: &TrieTree) {
+    let hooks = tree.hooks(&check.word);
+    let front: String = hooks.front.iter().collect();
+    let back: String = hooks.back.iter().collect();
+    println!(
+        "{:<15} {:<8} front: {:<5} back: {}",
+        check.word,
+        if check.valid { "valid" } else { "invalid" },
+        if front.is_empty() { "-" } else { &front },
+        if back.is_empty() { "-" } else { &back }
+    );
+}
+
+/// One word checked, as `--format json` prints it.
+pub(crate) fn check_json(check: &WordCheck, tree: &TrieTree) -> Value {
+    let hooks = tree.hooks(&check.word);
+    json!({
+        "word": check.word,
+        "valid": check.valid,
+        "front_hooks": hooks.front.iter().collect::<String>(),
+        "back_hooks": hooks.back.iter().collect::<String>(),
+    })
+}
+
+/// ### adjudicate()
+/// Judge one play given on the command line, or one play per line read
+/// from standard input. Only the verdict is printed, whatever the verbosity.
+fn adjudicate(options: &Options, words: &[String], log: Option<&str>) -> Result<i32, String> {
+    let lexicon = load_lexicon(options)?;
+    let mut adjudicator = match log {
+        Some(path) => Adjudicator::with_log(&lexicon.trie, path)
+            .map_err(|e| format!("cannot open log {}: {}", path, e))?,
+        None => Adjudicator::new(&lexicon.trie),
+    };
+    let mut judge = |words: &[String]| -> Result<Verdict, String> {
+        let verdict = adjudicator
+            .judge(words)
+            .map_err(|e| format!("cannot write to the log: {}", e))?;
+        match options.format {
+            OutputFormat::Json => println!("{}", json!({ "verdict": verdict.as_str() })),
+            OutputFormat::Text => println!("Play is {}", verdict.as_str()),
+        }
+        Ok(verdict)
+    };
+
+    if !words.is_empty() {
+        return match judge(words)? {
+            Verdict::Acceptable => Ok(EXIT_OK),
+            Verdict::NotAcceptable => Ok(EXIT_INVALID),
+        };
+    }
+    if options.format == OutputFormat::Text && options.verbosity != Verbosity::Quiet {
+        println!("Enter the words formed by each play, one play per line.");
+    }
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let words: Vec<String> = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_string())
+            .collect();
+        if !words.is_empty() {
+            judge(&words)?;
+        }
+    }
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("{} plays judged", adjudicator.decisions());
+    }
+    Ok(EXIT_OK)
+}
+
+/// ### play()
+/// The human and the agent both get the same rack. The human types a word
+/// which is checked against the rack and the lexicon and scored with the
+/// agent's placement rules; then the agent shows its own best move.
+/// With `--format json` there are no prompts and every event is printed
+/// as one JSON object per line.
+fn play(options: &Options, rounds: u32) -> Result<(), String> {
+    let json = options.format == OutputFormat::Json;
+    let lexicon = load_lexicon(options)?;
+    let mut rng = rng(options);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let (mut human_total, mut agent_total) = (0, 0);
+
+    for round in 1..=rounds {
+        let mut agent = Agent::with_rack(Rack::from_rng(&mut rng));
+        agent.threads = options.threads;
+        let rack_word: String = agent.rack.letters.iter().collect();
+        if json {
+            println!(
+                "{}",
+                json!({ "event": "round", "round": round, "rack": rack_word })
+            );
+        } else {
+            println!("\nRound {} - rack: {}", round, rack_word);
+        }
+
+        // The agent thinks about its own move while the human is typing;
+        // if the input ends the search is cancelled rather than waited for
+        let mut controller = SearchController::new(SearchLimits::default());
+        let cancel = controller.cancel_handle();
+        let outcome = thread::scope(|scope| {
+            let thinking = scope.spawn(|| controller.run(&agent, &lexicon.trie));
+            let human = loop {
+                if !json {
+                    print!("Your word (lowercase for wildcards, empty to pass): ");
+                    io::stdout().flush().map_err(|e| e.to_string())?;
+                }
+                let line = match lines.next() {
+                    Some(line) => line.map_err(|e| e.to_string())?,
+                    None => {
+                        cancel.cancel();
+                        return Ok(None);
+                    }
+                };
+                let word = line.trim();
+                if word.is_empty() {
+                    break None;
+                }
+                match validate_play(word, &agent.rack, &lexicon.trie) {
+                    Ok(()) => {
+                        let placed = agent.find_best_move(&Anagram::new(word.to_string(), 0));
+                        if !json {
+                            println!(
+                                "{} starting on element {} scores {}",
+                                word, placed.starting_element, placed.score
+                            );
+                        }
+                        break Some(placed);
+                    }
+                    Err(reason) if json => println!(
+                        "{}",
+                        json!({
+                            "event": "rejected",
+                            "round": round,
+                            "word": word,
+                            "reason": reason,
+                        })
+                    ),
+                    Err(reason) => println!("{}, try again", reason),
+                }
+            };
+            let result = thinking
+                .join()
+                .map_err(|_| "the agent's search panicked".to_string())?;
+            Ok::<_, String>(Some((human, result)))
+        })?;
+        let (human, result) = match outcome {
+            Some(outcome) => outcome,
+            None => return Ok(()),
+        };
+
+        let human_score = human.as_ref().map_or(0, |m| m.score);
+        let agent_score = result.best().map_or(0, |m| m.score);
+        if json {
+            println!(
+                "{}",
+                json!({
+                    "event": "result",
+                    "round": round,
+                    "human": human.as_ref().map(|m| m.to_json()),
+                    "agent": result.best().map(|m| m.to_json()),
+                    "search": result.stats(),
+                })
+            );
+        } else {
+            match result.best() {
+                Some(best) => println!(
+                    "Agent plays {} starting on element {} for {}",
+                    best.anagram.word, best.starting_element, best.score
+                ),
+                None => println!("Agent passes"),
+            }
+        }
+        human_total += human_score;
+        agent_total += agent_score;
+    }
+
+    if json {
+        println!(
+            "{}",
+            json!({ "event": "final", "human": human_total, "agent": agent_total })
+        );
+    } else {
+        println!(
+            "\nFinal score - you: {}, agent: {}",
+            human_total, agent_total
+        );
+    }
+    Ok(())
+}
+
+/// ### validate_play()
+/// A played word must be in the lexicon and made only of tiles on the
+/// rack. Lowercase letters are played with a wildcard.
+fn validate_play(word: &str, rack: &Rack, tree: &TrieTree) -> Result<(), String> {
+    if !word.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!(
+            "\"{}\" contains something other than letters",
+            word
+        ));
+    }
+    let mut tiles = rack.letters.clone();
+    for c in word.chars() {
+        let tile = if c.is_ascii_lowercase() { '_' } else { c };
+        match tiles.iter().position(|t| *t == tile) {
+            Some(i) => {
+                tiles.remove(i);
+            }
+            None if tile == '_' => return Err("you don't have a wildcard left".to_string()),
+            None => return Err(format!("{} is not on your rack", c)),
+        }
+    }
+    if !tree.search(&word.to_uppercase()) {
+        return Err(format!("{} is not a valid word", word.to_uppercase()));
+    }
+    Ok(())
+}
+
+/// The commands understood while playing a `game`, printed by `help`.
+const GAME_HELP: &str = "\
+Moves:
+  8H QUIXOTE    Play across from 8H; H8 QUIXOTE plays down
+                (lowercase letters are wildcards)
+  -QV           Exchange tiles, also written exchange QV
+  pass          Pass the turn
+  challenge     Challenge the agent's last play
+Other commands:
+  unseen        The tiles in the bag and on the agent's rack
+  draw S [N]    The chance of drawing S, or any tiles, among N tiles
+                (among 1 to 7 tiles without N)
+  help          Show this help
+  quit          Give up the game";
+
+/// The search limits of an agent given `time` ms a move.
+fn think_limits(time: u64) -> SearchLimits {
+    SearchLimits {
+        time: Some(Duration::from_millis(time)),
+        nodes: None,
+    }
+}
+
+/// ### game()
+/// A full game against the agent: the human, who moves first, types
+/// moves in notation and the agent answers with `strategy` until the
+/// game is over or the human quits. Phonies are handled by `challenge`;
+/// the agent challenges every phony. With `record` the game is saved for
+/// `replay`.
+fn game(
+    options: &Options,
+    strategy: &str,
+    challenge: ChallengeRule,
+    time: u64,
+    record: Option<&str>,
+) -> Result<(), String> {
+    let json = options.format == OutputFormat::Json;
+    let lexicon = load_lexicon(options)?;
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let mut agent = strategy::by_name(strategy, think_limits(time), options.threads, seed)?;
+    let mut game = Game::new(seed);
+    game.challenge_rule = challenge;
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    if json {
+        println!(
+            "{}",
+            json!({
+                "event": "start",
+                "seed": seed,
+                "strategy": agent.name(),
+                "challenge": challenge.name(),
+            })
+        );
+    } else {
+        println!(
+            "New game against the {} agent (seed {}, {} challenge), type help for the commands.",
+            agent.name(),
+            seed,
+            challenge.name()
+        );
+    }
+
+    loop {
+        if game.turn == 1 {
+            // Even a play that ended the game can be challenged
+            if game.can_challenge() && agent.challenge(&game, &lexicon.trie) {
+                game.challenge(&lexicon.trie)?;
+                report_turn(json, &game);
+                continue;
+            }
+            if game.is_over() {
+                break;
+            }
+            let action = agent.choose(&game, &lexicon.trie);
+            // A move the game refuses would be a bug in the strategy; the
+            // agent passes rather than stopping the game
+            if game.apply(&action, &lexicon.trie).is_err() {
+                game.pass()?;
+            }
+            report_turn(json, &game);
+            continue;
+        }
+        if game.is_over() {
+            break;
+        }
+
+        let rack: String = game.rack().letters.iter().collect();
+        if json {
+            println!(
+                "{}",
+                json!({
+                    "event": "rack",
+                    "rack": rack,
+                    "scores": game.scores,
+                    "bag": game.bag.len(),
+                })
+            );
+        } else {
+            println!();
+            game.position.display();
+            println!(
+                "\nYou {} - agent {}   bag: {}   rack: {}",
+                game.scores[0],
+                game.scores[1],
+                game.bag.len(),
+                rack
+            );
+            print!("Your move: ");
+            io::stdout().flush().map_err(|e| e.to_string())?;
+        }
+        let line = match lines.next() {
+            Some(line) => line.map_err(|e| e.to_string())?,
+            None => break,
+        };
+        match line.trim() {
+            "" => {}
+            "quit" | "exit" => break,
+            "help" => println!("{}", GAME_HELP),
+            "unseen" => {
+                let tracker = TileTracker::from_game(&game, 0);
+                match json {
+                    true => println!(
+                        "{}",
+                        json!({ "event": "unseen", "tiles": tracker_json(&tracker) })
+                    ),
+                    false => print_tracker(&tracker),
+                }
+            }
+            line if line.starts_with("draw ") => {
+                let tracker = TileTracker::from_game(&game, 0);
+                let query = match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                    [_, tiles] => Ok((tiles, None)),
+                    [_, tiles, count] => count
+                        .parse()
+                        .map(|count| (tiles, Some(count)))
+                        .map_err(|_| format!("not a number of tiles: {}", count)),
+                    _ => Err("expected draw TILES [N]".to_string()),
+                };
+                match query
+                    .and_then(|(tiles, count)| Ok((tiles, draw_chances(&tracker, tiles, count)?)))
+                {
+                    Ok((tiles, chances)) if json => println!(
+                        "{}",
+                        json!({ "event": "draw", "draw": chances_json(tiles, &chances) })
+                    ),
+                    Ok((tiles, chances)) => print_chances(tiles, &chances),
+                    Err(reason) if json => println!(
+                        "{}",
+                        json!({ "event": "rejected", "move": line, "reason": reason })
+                    ),
+                    Err(reason) => println!("{}, try again", reason),
+                }
+            }
+            line => match Action::parse(line).and_then(|a| game.apply(&a, &lexicon.trie)) {
+                Ok(_) => report_turn(json, &game),
+                Err(reason) if json => println!(
+                    "{}",
+                    json!({ "event": "rejected", "move": line, "reason": reason.to_string() })
+                ),
+                Err(reason) => println!("{}, try again", reason),
+            },
+        }
+    }
+
+    let winner = match (game.is_over(), game.winner()) {
+        (false, _) => "abandoned",
+        (true, Some(0)) => "you",
+        (true, Some(_)) => "agent",
+        (true, None) => "tie",
+    };
+    if json {
+        println!(
+            "{}",
+            json!({
+                "event": "final",
+                "you": game.scores[0],
+                "agent": game.scores[1],
+                "tiles_left": game.rack_adjustments,
+                "winner": winner,
+                "seed": seed,
+            })
+        );
+    } else {
+        if let Some([you, agent]) = game.rack_adjustments {
+            println!("Tiles left on the racks: you {:+}, agent {:+}", you, agent);
+        }
+        let verdict = match winner {
+            "abandoned" => "Game abandoned",
+            "you" => "You win",
+            "agent" => "The agent wins",
+            _ => "It's a tie",
+        };
+        println!(
+            "{} - you: {}, agent: {} (seed {})",
+            verdict, game.scores[0], game.scores[1], seed
+        );
+    }
+    if let Some(file) = record {
+        let text = Record::from_game(&game, ["You", "Agent"]).to_text();
+        fs::write(file, text).map_err(|e| format!("cannot write {}: {}", file, e))?;
+    }
+    Ok(())
+}
+
+/// Print the turn just made in a `game`.
+fn report_turn(json: bool, game: &Game) {
+    let turn = match game.history.last() {
+        Some(turn) => turn,
+        None => return,
+    };
+    let player = ["you", "agent"][turn.player];
+    // Whether a challenge took the play before it off the board
+    let withdrawn = game.history.len() >= 2 && game.history[game.history.len() - 2].withdrawn;
+    if json {
+        let mut event = json!({
+            "event": "turn",
+            "player": player,
+            "rack": turn.rack,
+            "move": turn.action.notation(),
+            "score": turn.score,
+            "total": turn.total,
+        });
+        if turn.action == Action::Challenge {
+            event["withdrawn"] = json!(withdrawn);
+            event["scores"] = json!(game.scores);
+        }
+        println!("{}", event);
+        return;
+    }
+    let who = ["You", "Agent"][turn.player];
+    match &turn.action {
+        Action::Play(play) => println!(
+            "{}: {} for {} (total {})",
+            who,
+            play.notation(),
+            turn.score,
+            turn.total
+        ),
+        Action::Exchange(tiles) => println!("{}: exchanged {} tiles", who, tiles.len()),
+        Action::Pass => println!("{}: passed", who),
+        Action::Challenge if withdrawn => {
+            println!("{}: challenged, the play is withdrawn", who)
+        }
+        Action::Challenge => println!(
+            "{}: challenged, the play stands (you {} - agent {})",
+            who, game.scores[0], game.scores[1]
+        ),
+    }
+}
+
+/// ### selfplay_match()
+/// Play a self-play match and report how each strategy did, writing
+/// every game to `output` if given.
+fn selfplay_match(
+    options: &Options,
+    harness: &SelfPlay,
+    output: Option<&str>,
+) -> Result<(), String> {
+    let lexicon = load_lexicon(options)?;
+    let start = Instant::now();
+    let results = harness.run(&lexicon.trie)?;
+    let elapsed = start.elapsed();
+    let names = &harness.strategies;
+    if options.verbosity == Verbosity::Verbose {
+        for result in &results {
+            eprintln!(
+                "game {} (seed {}): {} {} - {} {}",
+                result.game, result.seed, names[0], result.scores[0], names[1], result.scores[1]
+            );
+        }
+    }
+
+    if let Some(file) = output {
+        let text = match file.ends_with(".csv") {
+            true => {
+                let mut text = format!("{}\n", CSV_HEADER);
+                for result in &results {
+                    text.push_str(&result.csv_row(names));
+                    text.push('\n');
+                }
+                text
+            }
+            false => json!({
+                "seed": harness.seed,
+                "strategies": names,
+                "summary": harness.summarize(&results).map(|s| s.to_json()),
+                "games": results.iter().map(|r| r.to_json(names)).collect::<Vec<_>>(),
+            })
+            .to_string(),
+        };
+        fs::write(file, text).map_err(|e| format!("cannot write {}: {}", file, e))?;
+    }
+
+    let summary = harness.summarize(&results);
+    match options.format {
+        OutputFormat::Json => println!(
+            "{}",
+            json!({
+                "games": results.len(),
+                "seed": harness.seed,
+                "elapsed_ms": elapsed.as_millis() as u64,
+                "strategies": summary.iter().map(|s| s.to_json()).collect::<Vec<_>>(),
+            })
+        ),
+        OutputFormat::Text => {
+            println!(
+                "{} games, seed {}, in {:.1?}\n",
+                results.len(),
+                harness.seed,
+                elapsed
+            );
+            let width = names
+                .iter()
+                .map(|name| name.len())
+                .max()
+                .unwrap_or(0)
+                .max(10);
+            println!(
+                "{:<width$} {:>6} {:>6} {:>9} {:>8} {:>8} {:>9} {:>9}",
+                "Strategy", "Wins", "Ties", "Win rate", "Score", "Spread", "Bingos/g", "ms/move"
+            );
+            for s in &summary {
+                println!(
+                    "{:<width$} {:>6} {:>6} {:>8.1}% {:>8.1} {:>+8.1} {:>9.2} {:>9.1}",
+                    s.name,
+                    s.wins,
+                    s.ties,
+                    s.win_rate() * 100.0,
+                    s.average_score,
+                    s.average_spread,
+                    s.bingos_per_game,
+                    s.ms_per_move
+                );
+            }
+            if let Some(file) = output {
+                println!("\nWrote {}", file);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ### tournament()
+/// Play a round robin, rate it and save the ratings.
+fn tournament(options: &Options, event: &Tournament, file: &str) -> Result<(), String> {
+    if event.strategies.len() < 2 {
+        return Err("a tournament needs at least two strategies".to_string());
+    }
+    for name in &event.strategies {
+        strategy::by_name(name, event.limits, 1, event.seed)?;
+    }
+    let mut ratings = Ratings::load(file)?;
+    let lexicon = load_lexicon(options)?;
+    let start = Instant::now();
+    let pairings = event.run(&lexicon.trie)?;
+    let before = ratings.clone();
+    ratings.update(&pairings);
+    ratings.save(file)?;
+
+    match options.format {
+        OutputFormat::Json => println!(
+            "{}",
+            json!({
+                "seed": event.seed,
+                "elapsed_ms": start.elapsed().as_millis() as u64,
+                "pairings": pairings.iter().map(|p| p.to_json()).collect::<Vec<_>>(),
+                "ratings": ratings.ranked().iter().map(|(name, rating)| json!({
+                    "strategy": name,
+                    "rating": rating.rating,
+                    "deviation": rating.deviation,
+                    "games": rating.games,
+                    "change": rating.rating - before.get(name).rating,
+                })).collect::<Vec<_>>(),
+                "file": file,
+            })
+        ),
+        OutputFormat::Text => {
+            println!(
+                "Round robin of {} games a pairing, seed {}, in {:.1?}\n",
+                event.games,
+                event.seed,
+                start.elapsed()
+            );
+            for pairing in &pairings {
+                let [first, second] = &pairing.strategies;
+                let wins = pairing.wins();
+                println!("  {:<10} {:>4} - {:<4} {}", first, wins[0], wins[1], second);
+            }
+            println!(
+                "\n{:>4}  {:<10} {:>7} {:>9} {:>7} {:>7}",
+                "#", "Strategy", "Rating", "Deviation", "Change", "Games"
+            );
+            for (rank, (name, rating)) in ratings.ranked().iter().enumerate() {
+                println!(
+                    "{:>4}  {:<10} {:>7.0} {:>9.0} {:>+7.0} {:>7}",
+                    rank + 1,
+                    name,
+                    rating.rating,
+                    rating.deviation,
+                    rating.rating - before.get(name).rating,
+                    rating.games
+                );
+            }
+            if options.verbosity != Verbosity::Quiet {
+                println!("\nSaved to {}", file);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ### leaves()
+/// Play the self-play games, fit a leave table to them and save it.
+fn leaves(options: &Options, harness: &SelfPlay, file: &str) -> Result<(), String> {
+    let lexicon = load_lexicon(options)?;
+    let start = Instant::now();
+    let results = harness.run(&lexicon.trie)?;
+    let mut samples = Vec::new();
+    for result in &results {
+        samples.extend(leaves::samples(&result.record, &lexicon.trie)?);
+    }
+    if samples.is_empty() {
+        return Err("the games kept no leaves to learn from".to_string());
+    }
+    let table = LeaveTable::fit(&samples, DEFAULT_RIDGE);
+    table.save(file)?;
+
+    let singles = table.singles();
+    match options.format {
+        OutputFormat::Json => println!(
+            "{}",
+            json!({
+                "games": results.len(),
+                "seed": harness.seed,
+                "strategy": harness.strategies[0],
+                "samples": samples.len(),
+                "elapsed_ms": start.elapsed().as_millis() as u64,
+                "singles": singles.iter().map(|(tile, value)| json!({
+                    "tile": tile.to_string(),
+                    "value": value,
+                })).collect::<Vec<_>>(),
+                "output": file,
+            })
+        ),
+        OutputFormat::Text => {
+            println!(
+                "{} games of {}, seed {}, {} leaves in {:.1?}\n",
+                results.len(),
+                harness.strategies[0],
+                harness.seed,
+                samples.len(),
+                start.elapsed()
+            );
+            for row in singles.chunks(9) {
+                let line = row
+                    .iter()
+                    .map(|(tile, value)| format!("{} {:+5.1}", tile, value))
+                    .collect::<Vec<String>>();
+                println!("  {}", line.join("   "));
+            }
+            if options.verbosity != Verbosity::Quiet {
+                println!("\nWrote {}", file);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ### selfplay()
+/// Solve `games` random racks and summarise how the agent did.
+fn selfplay(options: &Options, games: u32) -> Result<(), String> {
+    let lexicon = load_lexicon(options)?;
+    let mut rng = rng(options);
+    let mut scores: Vec<u32> = Vec::new();
+    let mut bingos = 0;
+    let start = Instant::now();
+
+    for game in 1..=games {
+        let mut agent = Agent::with_rack(Rack::from_rng(&mut rng));
+        agent.threads = options.threads;
+        let rack_word: String = agent.rack.letters.iter().collect();
+        let best = agent.analyze(&lexicon.trie).into_iter().next();
+        let score = best.as_ref().map_or(0, |m| m.score);
+        if best.as_ref().map_or(0, |m| m.anagram.word.len()) == 7 {
+            bingos += 1;
+        }
+        if options.verbosity == Verbosity::Verbose {
+            let word = best.map_or("-".to_string(), |m| m.anagram.word);
+            eprintln!("game {}: {} -> {} {}", game, rack_word, word, score);
+        }
+        scores.push(score);
+    }
+
+    let elapsed = start.elapsed();
+    let total: u32 = scores.iter().sum();
+    let average = total as f64 / games.max(1) as f64;
+    let per_move = elapsed.as_secs_f64() * 1000.0 / games.max(1) as f64;
+    match options.format {
+        OutputFormat::Json => println!(
+            "{}",
+            json!({
+                "games": games,
+                "average_score": average,
+                "best_score": scores.iter().max(),
+                "worst_score": scores.iter().min(),
+                "bingos": bingos,
+                "ms_per_move": per_move,
+            })
+        ),
+        OutputFormat::Text => {
+            println!("Games:         {}", games);
+            println!("Average score: {:.2}", average);
+            println!("Best score:    {}", scores.iter().max().unwrap_or(&0));
+            println!("Worst score:   {}", scores.iter().min().unwrap_or(&0));
+            println!("Bingos:        {}", bingos);
+            println!("Time per move: {:.1} ms", per_move);
+        }
+    }
+    Ok(())
+}
+
+/// ### quiz()
+/// Show a random rack and let the user type the words they can find.
+/// When they enter an empty line, show how they did and what they missed.
+/// With `--format json` every event is one JSON object per line.
+fn quiz(options: &Options, rounds: u32) -> Result<(), String> {
+    let json = options.format == OutputFormat::Json;
+    let lexicon = load_lexicon(options)?;
+    let mut rng = rng(options);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    for round in 1..=rounds {
+        let rack = Rack::from_rng(&mut rng);
+        let rack_word: String = rack.letters.iter().collect();
+        let answers: BTreeSet<String> = lexicon
+            .alphagrams
+            .subanagrams(&rack_word, 3)
+            .into_iter()
+            .collect();
+        if json {
+            println!(
+                "{}",
+                json!({
+                    "event": "round",
+                    "round": round,
+                    "rack": rack_word,
+                    "answers": answers.len(),
+                })
+            );
+        } else {
+            println!(
+                "\nRound {} - rack: {} ({} words of 3+ letters). Empty line to finish.",
+                round,
+                rack_word,
+                answers.len()
+            );
+        }
+
+        let mut found: BTreeSet<String> = BTreeSet::new();
+        loop {
+            if !json {
+                print!("> ");
+                io::stdout().flush().map_err(|e| e.to_string())?;
+            }
+            let guess = match lines.next() {
+                Some(line) => line.map_err(|e| e.to_string())?.trim().to_uppercase(),
+                None => String::new(),
+            };
+            if guess.is_empty() {
+                break;
+            }
+            let verdict = if !answers.contains(&guess) {
+                "no"
+            } else if found.insert(guess.clone()) {
+                "yes"
+            } else {
+                "already found"
+            };
+            match (json, verdict) {
+                (true, _) => println!(
+                    "{}",
+                    json!({
+                        "event": "guess",
+                        "round": round,
+                        "word": guess,
+                        "result": verdict,
+                        "found": found.len(),
+                    })
+                ),
+                (false, "yes") => println!("yes ({}/{})", found.len(), answers.len()),
+                (false, _) => println!("{}", verdict),
+            }
+        }
+
+        let missed: Vec<&str> = answers.difference(&found).map(|w| w.as_str()).collect();
+        if json {
+            println!(
+                "{}",
+                json!({
+                    "event": "summary",
+                    "round": round,
+                    "found": found,
+                    "missed": missed,
+                })
+            );
+            continue;
+        }
+        println!("You found {} of {}", found.len(), answers.len());
+        if !missed.is_empty() {
+            println!("Missed: {}", missed.join(" "));
+        }
+    }
+    Ok(())
+}
+
+/// ### lexicon_build()
+/// Compile a word list into a lexicon: words are upper-cased, duplicates
+/// dropped and the alphagram index written alongside them so loading it
+/// later needs no indexing. Blank lines and lines starting with `#` are
+/// skipped; anything else that is not made of letters is rejected and
+/// counted.
+fn lexicon_build(options: &Options, input: &str, output: &str) -> Result<(), String> {
+    let file = File::open(input).map_err(|e| format!("cannot open {}: {}", input, e))?;
+    let mut words: BTreeSet<String> = BTreeSet::new();
+    let mut rejected = 0;
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("cannot read {}: {}", input, e))?;
+        let word = line.trim();
+        if word.is_empty() || word.starts_with('#') {
+            continue;
+        }
+        if !word.chars().all(|c| c.is_ascii_alphabetic()) {
+            rejected += 1;
+            if options.verbosity == Verbosity::Verbose {
+                eprintln!("{}:{}: skipping \"{}\"", input, number + 1, word);
+            }
+            continue;
+        }
+        words.insert(word.to_uppercase());
+    }
+
+    let count = words.len();
+    Lexicon::from_words(words)
+        .write_compiled(output)
+        .map_err(|e| format!("cannot write {}: {}", output, e))?;
+
+    match options.format {
+        OutputFormat::Json => println!(
+            "{}",
+            json!({ "output": output, "words": count, "rejected": rejected })
+        ),
+        OutputFormat::Text => {
+            if options.verbosity != Verbosity::Quiet {
+                println!(
+                    "Wrote {} words to {} ({} rejected)",
+                    count, output, rejected
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ### unseen()
+/// The tiles unseen from one side of a board, and the chances of a draw.
+fn unseen(
+    options: &Options,
+    board: Option<&str>,
+    rack: Option<&str>,
+    draw: Option<&str>,
+    count: Option<usize>,
+) -> Result<(), String> {
+    let position = match board {
+        Some(rows) => Position::parse(rows)?,
+        None => Position::new(),
+    };
+    let rack = match rack {
+        Some(letters) => Rack::from_letters(letters)?.letters,
+        None => Vec::new(),
+    };
+    let tracker = TileTracker::new(&position, &rack);
+    let chances = draw
+        .map(|tiles| draw_chances(&tracker, tiles, count))
+        .transpose()?;
+    match options.format {
+        OutputFormat::Json => {
+            let mut value = tracker_json(&tracker);
+            if let (Some(tiles), Some(chances)) = (draw, &chances) {
+                value["draw"] = chances_json(tiles, chances);
+            }
+            println!("{}", value);
+        }
+        OutputFormat::Text => {
+            print_tracker(&tracker);
+            if let (Some(tiles), Some(chances)) = (draw, &chances) {
+                println!();
+                print_chances(tiles, chances);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The chance of drawing all of `tiles` among `count` tiles, or among 1 to
+/// 7 tiles without a count, as (tiles drawn, chance).
+fn draw_chances(
+    tracker: &TileTracker,
+    tiles: &str,
+    count: Option<usize>,
+) -> Result<Vec<(usize, f64)>, String> {
+    let counts = match count {
+        Some(count) => vec![count],
+        None => (1..=RACK_SIZE).collect(),
+    };
+    let mut chances = Vec::new();
+    for drawn in counts {
+        chances.push((drawn, tracker.probability(tiles, drawn)?));
+    }
+    Ok(chances)
+}
+
+fn tracker_json(tracker: &TileTracker) -> Value {
+    let counts: serde_json::Map<String, Value> = tracker
+        .counts()
+        .into_iter()
+        .map(|(tile, count)| (tile.to_string(), json!(count)))
+        .collect();
+    json!({
+        "unseen": tracker.unseen.iter().collect::<String>(),
+        "total": tracker.unseen.len(),
+        "in_bag": tracker.in_bag,
+        "vowels": tracker.vowels(),
+        "wildcards": tracker.count('_'),
+        "counts": counts,
+    })
+}
+
+fn chances_json(tiles: &str, chances: &[(usize, f64)]) -> Value {
+    json!({
+        "tiles": tiles,
+        "chances": chances.iter().map(|(drawn, p)| json!({
+            "drawn": drawn,
+            "probability": p,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn print_tracker(tracker: &TileTracker) {
+    let wildcards = tracker.count('_');
+    println!(
+        "{} unseen, {} in the bag: {} vowels, {} consonants, {} wildcards",
+        tracker.unseen.len(),
+        tracker.in_bag,
+        tracker.vowels(),
+        tracker.unseen.len() - tracker.vowels() - wildcards,
+        wildcards
+    );
+    let counts = tracker
+        .counts()
+        .into_iter()
+        .map(|(tile, count)| format!("{} {:<2}", if tile == '_' { '?' } else { tile }, count))
+        .collect::<Vec<String>>();
+    for row in counts.chunks(9) {
+        println!("  {}", row.join("  "));
+    }
+}
+
+fn print_chances(tiles: &str, chances: &[(usize, f64)]) {
+    println!("Chance of drawing {}:", tiles.to_uppercase());
+    for (drawn, p) in chances {
+        let noun = if *drawn == 1 { "tile" } else { "tiles" };
+        println!("  {:>2} {:<6} {:>6.1}%", drawn, noun, p * 100.0);
+    }
+}
+
+/// ### infer()
+/// Estimate the opponent's leave at the end of a game record.
+fn infer(options: &Options, record: &str, samples: usize) -> Result<(), String> {
+    let text = fs::read_to_string(record).map_err(|e| format!("cannot read {}: {}", record, e))?;
+    let parsed = Record::parse(&text).map_err(|e| format!("{}: {}", record, e))?;
+    let lexicon = load_lexicon(options)?;
+    let game = parsed
+        .replay(&lexicon.trie)
+        .map_err(|e| format!("{}: {}", record, e))?;
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let inference = RackInference::from_game(&game, &lexicon.trie, samples, seed)?;
+    let opponent = &parsed.players[1 - game.turn];
+
+    let mut tiles = inference.pool.clone();
+    tiles.dedup();
+    // Tiles by how much the play changed the chance of holding them
+    let mut odds = tiles
+        .iter()
+        .map(|&tile| {
+            let (now, prior) = (
+                inference.rack_probability(tile),
+                inference.prior_probability(tile),
+            );
+            (tile, now, prior)
+        })
+        .collect::<Vec<(char, f64, f64)>>();
+    odds.sort_by(|a, b| (b.1 - b.2).abs().total_cmp(&(a.1 - a.2).abs()));
+    let leaves = inference.leaves.iter().take(10);
+
+    match options.format {
+        OutputFormat::Json => println!(
+            "{}",
+            json!({
+                "opponent": opponent,
+                "seed": seed,
+                "unseen": inference.pool.iter().collect::<String>(),
+                "rack_size": inference.rack_size,
+                "leaves": leaves.map(|(leave, p)| json!({
+                    "leave": leave,
+                    "probability": p,
+                })).collect::<Vec<_>>(),
+                "tiles": odds.iter().map(|(tile, now, prior)| json!({
+                    "tile": tile.to_string(),
+                    "probability": now,
+                    "prior": prior,
+                })).collect::<Vec<_>>(),
+            })
+        ),
+        OutputFormat::Text => {
+            println!(
+                "{} holds {} of the {} tiles unseen by their opponent: {}",
+                opponent,
+                inference.rack_size,
+                inference.pool.len(),
+                inference.pool.iter().collect::<String>()
+            );
+            if inference.leaves.len() == 1 && inference.leaves[0].0.is_empty() {
+                println!("Their last move says nothing about what they kept.");
+            } else {
+                println!("\nLikeliest leaves:");
+                for (leave, p) in leaves {
+                    println!("  {:<8} {:>5.1}%", leave, p * 100.0);
+                }
+            }
+            println!("\n{:>6} {:>8} {:>8}", "Tile", "Holds", "Blindly");
+            for (tile, now, prior) in &odds {
+                println!("{:>6} {:>7.1}% {:>7.1}%", tile, now * 100.0, prior * 100.0);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ### replay()
+/// Replay a game record against the lexicon, so a mistyped move is caught,
+/// and write it out as an HTML replay page.
+fn replay(options: &Options, record: &str, output: Option<&str>) -> Result<(), String> {
+    let text = fs::read_to_string(record).map_err(|e| format!("cannot read {}: {}", record, e))?;
+    let parsed = Record::parse(&text).map_err(|e| format!("{}: {}", record, e))?;
+    let lexicon = load_lexicon(options)?;
+    let game = parsed
+        .replay(&lexicon.trie)
+        .map_err(|e| format!("{}: {}", record, e))?;
+    let notes: Vec<Option<String>> = parsed.turns.iter().map(|turn| turn.note.clone()).collect();
+    let output = match output {
+        Some(file) => file.to_string(),
+        None => Path::new(record)
+            .with_extension("html")
+            .to_string_lossy()
+            .into_owned(),
+    };
+    fs::write(&output, to_html(&game, &parsed.players, &notes))
+        .map_err(|e| format!("cannot write {}: {}", output, e))?;
+
+    match options.format {
+        OutputFormat::Json => println!(
+            "{}",
+            json!({
+                "output": output,
+                "moves": game.history.len(),
+                "scores": game.scores,
+                "over": game.is_over(),
+            })
+        ),
+        OutputFormat::Text => {
+            if options.verbosity != Verbosity::Quiet {
+                println!(
+                    "Wrote {} ({} moves, {} {} - {} {})",
+                    output,
+                    game.history.len(),
+                    parsed.players[0],
+                    game.scores[0],
+                    parsed.players[1],
+                    game.scores[1]
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ### svg()
+/// Draw the board given in notation, or the empty one, as an SVG diagram
+/// with `play` highlighted. No lexicon is needed: the play is only drawn,
+/// not checked.
+fn svg(
+    options: &Options,
+    board: Option<&str>,
+    play: Option<&str>,
+    output: Option<&str>,
+) -> Result<(), String> {
+    let position = match board {
+        Some(rows) => Position::parse(rows)?,
+        None => Position::new(),
+    };
+    let play = play.map(Play::from_notation).transpose()?;
+    let diagram = to_svg(&position, play.as_ref());
+    match output {
+        Some(file) => {
+            fs::write(file, &diagram).map_err(|e| format!("cannot write {}: {}", file, e))?;
+            match options.format {
+                OutputFormat::Json => println!("{}", json!({ "output": file })),
+                OutputFormat::Text => {
+                    if options.verbosity != Verbosity::Quiet {
+                        println!("Wrote {}", file);
+                    }
+                }
+            }
+        }
+        None => match options.format {
+            OutputFormat::Json => println!("{}", json!({ "svg": diagram })),
+            OutputFormat::Text => print!("{}", diagram),
+        },
+    }
+    Ok(())
+}
+
+/// ### serve()
+/// Load the lexicon once and answer HTTP requests until stopped.
+fn serve(options: &Options, address: &str) -> Result<(), String> {
+    let lexicon = load_lexicon(options)?;
+    let server = Server::new(lexicon, options.threads);
+    if options.verbosity != Verbosity::Quiet {
+        eprintln!("Listening on http://{}", address);
+    }
+    server.serve(address)
+}
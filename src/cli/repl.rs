@@ -0,0 +1,380 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+use scrabble::agent::controller::{SearchController, SearchLimits};
+use scrabble::agent::rack::Rack;
+use scrabble::board::play::Play;
+use scrabble::board::position::Position;
+use scrabble::board::svg::to_svg;
+use scrabble::game::game::take_tiles;
+use scrabble::utils::lexicon::Lexicon;
+use scrabble::utils::weighted_group::WeightedGroups;
+use scrabble::utils::word_check::WordCheck;
+
+use crate::cli::cli::OutputFormat;
+use crate::cli::commands::{check_json, print_check};
+
+/// How many plays `best` lists unless told otherwise.
+const DEFAULT_BEST: usize = 10;
+
+/// The commands understood by the shell, printed by `help`.
+pub const REPL_HELP: &str = "\
+Commands:
+  rack [LETTERS|-]      Show or set the rack (? or _ for wildcards), - for none
+  board [ROWS|empty]    Show the board, or set it from its notation
+  play SQUARE WORD      Play a word from the rack, e.g. play 8H QUIZ or
+                        play H8 quiz; with no rack set, any tiles will do
+  undo                  Take back the last play or board change, and the
+                        tiles it took from the rack
+  best [N]              List the N best plays for the rack [default: 10]
+  anagram LETTERS       Every word using all of the letters
+  check WORD...         Look words up in the lexicon, with their hooks
+  hooks WORD            The letters that extend a word at either end
+  svg FILE [SQUARE WORD]
+                        Draw the board as an SVG diagram, highlighting a play
+  save FILE             Write the board and rack to FILE
+  load FILE             Read a board and rack written by save
+  help                  Show this help
+  quit                  Leave the shell";
+
+/// # Repl
+/// An interactive shell for analysis sessions. The lexicon is loaded once
+/// when the shell starts, so every query after that is answered at once.
+/// The shell holds a `Position` and a rack; a play takes its tiles from
+/// the rack, and every play or board change can be taken back with `undo`.
+/// With `--format json` there is no prompt and every command answers with
+/// one JSON object on a line, `{"error": ...}` when it fails.
+/// Functions:
+/// - `new()`, `run()`
+pub struct Repl {
+    lexicon: Lexicon,
+    threads: usize,
+    json: bool,
+    values: WeightedGroups,
+    position: Position,
+    rack: Option<Rack>,
+    /// The board and rack before each play or board change
+    history: Vec<(Position, Option<Rack>)>,
+}
+
+impl Repl {
+    pub fn new(lexicon: Lexicon, threads: usize, format: OutputFormat) -> Repl {
+        Repl {
+            lexicon,
+            threads,
+            json: format == OutputFormat::Json,
+            values: WeightedGroups::new(),
+            position: Position::new(),
+            rack: None,
+            history: Vec::new(),
+        }
+    }
+
+    /// ### run()
+    /// Prompt for commands on `input` until `quit` or the end of input.
+    /// A failed command prints why and leaves the session as it was.
+    pub fn run<R: BufRead>(&mut self, input: R) -> Result<(), String> {
+        let mut lines = input.lines();
+        loop {
+            if !self.json {
+                print!("scrabble> ");
+                io::stdout().flush().map_err(|e| e.to_string())?;
+            }
+            let line = match lines.next() {
+                Some(line) => line.map_err(|e| e.to_string())?,
+                None => {
+                    if !self.json {
+                        println!();
+                    }
+                    return Ok(());
+                }
+            };
+            match line.trim() {
+                "quit" | "exit" => return Ok(()),
+                "" => {}
+                line => match self.handle(line) {
+                    Ok(result) if self.json => println!("{}", result),
+                    Ok(_) => {}
+                    Err(message) if self.json => println!("{}", json!({ "error": message })),
+                    Err(message) => println!("error: {}", message),
+                },
+            }
+        }
+    }
+
+    /// ### handle()
+    /// Carry out one command line, printing its text unless the shell
+    /// speaks JSON. Returns the result as JSON.
+    fn handle(&mut self, line: &str) -> Result<Value, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (*command, args),
+            None => return Ok(Value::Null),
+        };
+        let result = match (command, args) {
+            ("help", _) => {
+                self.text(|| println!("{}", REPL_HELP));
+                json!({ "help": REPL_HELP })
+            }
+            ("rack", []) => {
+                self.text(|| match &self.rack {
+                    Some(rack) => println!("Rack: {}", rack.letters.iter().collect::<String>()),
+                    None => println!("No rack, set one with rack LETTERS"),
+                });
+                json!({ "rack": self.rack_letters() })
+            }
+            ("rack", letters) => {
+                self.rack = parse_rack(&letters.concat())?;
+                json!({ "rack": self.rack_letters() })
+            }
+            ("board", []) => {
+                self.text(|| {
+                    self.position.display();
+                    println!("Board: {}", self.position.notation());
+                });
+                json!({ "board": self.position.notation() })
+            }
+            ("board", [rows]) => {
+                self.set_position(parse_board(rows)?);
+                json!({ "board": self.position.notation() })
+            }
+            ("play", [square, word]) => self.play(square, word)?,
+            ("undo", []) => match self.history.pop() {
+                Some((position, rack)) => {
+                    self.position = position;
+                    self.rack = rack;
+                    self.text(|| {
+                        println!("Board: {}", self.position.notation());
+                        if let Some(rack) = &self.rack {
+                            println!("Rack: {}", rack.letters.iter().collect::<String>());
+                        }
+                    });
+                    json!({ "board": self.position.notation(), "rack": self.rack_letters() })
+                }
+                None => return Err("nothing to undo".to_string()),
+            },
+            ("best", []) => self.best(DEFAULT_BEST)?,
+            ("best", [count]) => self.best(
+                count
+                    .parse()
+                    .map_err(|_| format!("best expects a number, got \"{}\"", count))?,
+            )?,
+            ("anagram", [letters]) => {
+                let rack = Rack::from_letters(letters)?;
+                let rack_word: String = rack.letters.iter().collect();
+                let words = self.lexicon.alphagrams.blank_anagrams(&rack_word);
+                self.text(|| {
+                    if words.is_empty() {
+                        println!("No words");
+                    } else {
+                        println!("{}", words.join(" "));
+                    }
+                });
+                json!({ "letters": letters, "words": words })
+            }
+            ("check", words) | ("hooks", words @ [_]) if !words.is_empty() => {
+                let checks: Vec<WordCheck> = words
+                    .iter()
+                    .map(|word| WordCheck::new(word, &self.lexicon.trie))
+                    .collect();
+                self.text(|| {
+                    for check in &checks {
+                        print_check(check, &self.lexicon.trie);
+                    }
+                });
+                let results: Vec<Value> = checks
+                    .iter()
+                    .map(|check| check_json(check, &self.lexicon.trie))
+                    .collect();
+                json!({ "words": results })
+            }
+            ("svg", [file]) => self.svg(file, None)?,
+            ("svg", [file, square, word]) => self.svg(file, Some(Play::parse(square, word)?))?,
+            ("save", [file]) => self.save(file)?,
+            ("load", [file]) => self.load(file)?,
+            _ => {
+                return Err(format!(
+                    "can't make sense of \"{}\", type help for the commands",
+                    line
+                ))
+            }
+        };
+        Ok(result)
+    }
+
+    /// Print a command's text, unless the shell speaks JSON.
+    fn text(&self, print: impl FnOnce()) {
+        if !self.json {
+            print();
+        }
+    }
+
+    /// The letters on the rack, if one is set.
+    fn rack_letters(&self) -> Option<String> {
+        self.rack.as_ref().map(|rack| rack.letters.iter().collect())
+    }
+
+    /// Replace the board, remembering the old one for `undo`.
+    fn set_position(&mut self, position: Position) {
+        let previous = std::mem::replace(&mut self.position, position);
+        self.history.push((previous, self.rack.clone()));
+    }
+
+    /// `play SQUARE WORD`: check and score the play, take its tiles from
+    /// the rack, if one is set, then make it.
+    fn play(&mut self, square: &str, word: &str) -> Result<Value, String> {
+        let play = Play::parse(square, word)?;
+        let play = self
+            .position
+            .check(&play, &self.lexicon.trie, &self.values)?;
+        let left = match &self.rack {
+            Some(rack) => Some(take_tiles(&rack.letters, &play.tiles)?),
+            None => None,
+        };
+        let mut position = self.position.clone();
+        position.place(&play);
+        self.set_position(position);
+        if let (Some(rack), Some(left)) = (&mut self.rack, left) {
+            rack.letters = left;
+        }
+        let leave = self.rack_letters();
+        self.text(|| match &leave {
+            Some(leave) => println!(
+                "{} scores {}, leaving {}",
+                play.notation(),
+                play.score,
+                if leave.is_empty() { "nothing" } else { leave }
+            ),
+            None => println!("{} scores {}", play.notation(), play.score),
+        });
+        Ok(json!({
+            "play": play.notation(),
+            "tiles": play.tiles,
+            "score": play.score,
+            "rack": leave,
+            "board": self.position.notation(),
+        }))
+    }
+
+    /// `best [N]`: the `count` best plays for the rack on this board.
+    fn best(&self, count: usize) -> Result<Value, String> {
+        let rack = self
+            .rack
+            .as_ref()
+            .ok_or_else(|| "no rack, set one with rack LETTERS".to_string())?;
+        let result = SearchController::new(SearchLimits::default()).run_position(
+            &self.position,
+            rack,
+            &self.lexicon.trie,
+            self.threads,
+        );
+        self.text(|| {
+            if result.moves.is_empty() {
+                println!("No plays, pass or exchange");
+                return;
+            }
+            println!("{:>4}  {:<20} {:>5}  Leave", "#", "Play", "Score");
+            for (i, play) in result.moves.iter().take(count).enumerate() {
+                println!(
+                    "{:>4}  {:<20} {:>5}  {}",
+                    i + 1,
+                    play.notation(),
+                    play.score,
+                    rack.leave(&play.tiles)
+                );
+            }
+            println!(
+                "\n{} plays, {} nodes in {:.2?}",
+                result.moves.len(),
+                result.nodes,
+                result.elapsed
+            );
+        });
+        let plays: Vec<Value> = result
+            .moves
+            .iter()
+            .take(count)
+            .map(|play| {
+                json!({
+                    "play": play.notation(),
+                    "score": play.score,
+                    "leave": rack.leave(&play.tiles),
+                })
+            })
+            .collect();
+        Ok(json!({
+            "plays": plays,
+            "total": result.moves.len(),
+            "nodes": result.nodes,
+        }))
+    }
+
+    /// `svg FILE [SQUARE WORD]`: draw the board, with the play outlined.
+    fn svg(&self, file: &str, play: Option<Play>) -> Result<Value, String> {
+        fs::write(file, to_svg(&self.position, play.as_ref()))
+            .map_err(|e| format!("cannot write {}: {}", file, e))?;
+        self.text(|| println!("Wrote {}", file));
+        Ok(json!({ "output": file }))
+    }
+
+    /// `save FILE`: write the session as the `board` and `rack` commands
+    /// that set it up again, `rack -` when there are no tiles.
+    fn save(&self, file: &str) -> Result<Value, String> {
+        let rack = self.rack_letters().filter(|letters| !letters.is_empty());
+        let text = format!(
+            "board {}\nrack {}\n",
+            self.position.notation(),
+            rack.as_deref().unwrap_or("-")
+        );
+        fs::write(file, text).map_err(|e| format!("cannot write {}: {}", file, e))?;
+        self.text(|| println!("Saved to {}", file));
+        Ok(json!({ "saved": file }))
+    }
+
+    /// `load FILE`: read the `board` and `rack` lines of a saved session.
+    /// Nothing changes unless every line is good; `undo` takes the whole
+    /// load back.
+    fn load(&mut self, file: &str) -> Result<Value, String> {
+        let text = fs::read_to_string(file).map_err(|e| format!("cannot read {}: {}", file, e))?;
+        let mut position = self.position.clone();
+        let mut rack = self.rack.clone();
+        for (number, line) in text.lines().enumerate() {
+            let failed = |e: String| format!("{}:{}: {}", file, number + 1, e);
+            match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                [] => {}
+                [first, ..] if first.starts_with('#') => {}
+                ["board", rows] => position = parse_board(rows).map_err(failed)?,
+                ["rack", ref letters @ ..] => {
+                    rack = parse_rack(&letters.concat()).map_err(failed)?
+                }
+                _ => return Err(failed("expected board or rack".to_string())),
+            }
+        }
+        self.set_position(position);
+        self.rack = rack;
+        self.text(|| println!("Loaded {}", file));
+        Ok(json!({
+            "loaded": file,
+            "board": self.position.notation(),
+            "rack": self.rack_letters(),
+        }))
+    }
+}
+
+/// The board `rows` stand for, `empty` for an empty one.
+fn parse_board(rows: &str) -> Result<Position, String> {
+    match rows {
+        "empty" => Ok(Position::new()),
+        rows => Ok(Position::parse(rows)?),
+    }
+}
+
+/// The rack `letters` stand for, `-` for none.
+fn parse_rack(letters: &str) -> Result<Option<Rack>, String> {
+    match letters {
+        "-" => Ok(None),
+        letters => Ok(Some(Rack::from_letters(letters)?)),
+    }
+}
@@ -0,0 +1,481 @@
+use std::io::{self, Write};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{
+    Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use scrabble::agent::strategy::Strategy;
+use scrabble::board::play::Direction;
+use scrabble::board::position::{Premium, CENTER, SIZE};
+use scrabble::game::game::{Action, Game};
+use scrabble::utils::lexicon::Lexicon;
+use scrabble::utils::weighted_group::WeightedGroups;
+
+/// The column where the side panel starts, right of the board.
+const PANEL: u16 = 52;
+
+/// The keys, shown under the board.
+const KEYS: &str = "arrows move   letters place   Tab direction   \
+                    Backspace take back   Enter play   Esc clear   \
+                    Ctrl-E exchange   Ctrl-P pass   Ctrl-Q quit";
+
+/// # Tui
+/// A full game against the agent in the terminal. The board is drawn with
+/// its coordinates and colored premium squares, the agent's last move is
+/// highlighted, and the side panel shows both scores, the human's rack,
+/// the bag and the tiles the human has not seen yet.
+///
+/// Tiles are placed from the keyboard: move the cursor with the arrow
+/// keys, type letters to put rack tiles down along the current direction
+/// and press Enter to play them. When a letter is not on the rack a
+/// wildcard is used for it.
+/// Functions:
+/// - `new()`, `game()`, `run()`
+pub struct Tui<'a> {
+    game: Game,
+    lexicon: &'a Lexicon,
+    strategy: Box<dyn Strategy>,
+    values: WeightedGroups,
+    /// The human is always player 0 and moves first
+    cursor: (usize, usize),
+    direction: Direction,
+    /// Tiles put on the board but not yet played
+    placed: Vec<(usize, usize, char)>,
+    /// Tiles marked for an exchange while in exchange mode
+    exchange: Option<String>,
+    message: String,
+}
+
+/// Puts the terminal back the way it was, however the game ends.
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Screen> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl<'a> Tui<'a> {
+    /// A new game shuffled by `seed`, the agent choosing its moves with
+    /// `strategy`.
+    pub fn new(lexicon: &'a Lexicon, seed: u64, strategy: Box<dyn Strategy>) -> Tui<'a> {
+        Tui {
+            game: Game::new(seed),
+            lexicon,
+            strategy,
+            values: WeightedGroups::new(),
+            cursor: (CENTER, CENTER),
+            direction: Direction::Across,
+            placed: Vec::new(),
+            exchange: None,
+            message: "Your move.".to_string(),
+        }
+    }
+
+    /// The game being played, or played out once `run()` returns.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// ### run()
+    /// Play until the game ends and a key is pressed, or the human quits.
+    /// Returns the final scores, the human's first.
+    pub fn run(&mut self) -> Result<[i32; 2], String> {
+        let _screen =
+            Screen::enter().map_err(|e| format!("cannot start the terminal UI: {}", e))?;
+        let mut out = io::stdout();
+        loop {
+            self.draw(&mut out).map_err(|e| e.to_string())?;
+            if self.game.is_over() {
+                wait_for_key().map_err(|e| e.to_string())?;
+                return Ok(self.game.scores);
+            }
+            if self.game.turn == 1 {
+                self.agent_move();
+                continue;
+            }
+            let key = match event::read().map_err(|e| e.to_string())? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                _ => continue,
+            };
+            if !self.handle(key) {
+                return Ok(self.game.scores);
+            }
+        }
+    }
+
+    /// Act on one key press; `false` when the human quits.
+    fn handle(&mut self, key: KeyEvent) -> bool {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Char('c') if control => return false,
+            KeyCode::Char('p') if control => {
+                self.clear();
+                let outcome = self.game.pass().map(|_| "You passed.".to_string());
+                self.finish_turn(outcome);
+            }
+            KeyCode::Char('e') if control => {
+                self.clear();
+                self.exchange = Some(String::new());
+                self.message = "Type the tiles to exchange, then Enter.".to_string();
+            }
+            KeyCode::Up => self.cursor.0 = self.cursor.0.saturating_sub(1),
+            KeyCode::Down => self.cursor.0 = (self.cursor.0 + 1).min(SIZE - 1),
+            KeyCode::Left => self.cursor.1 = self.cursor.1.saturating_sub(1),
+            KeyCode::Right => self.cursor.1 = (self.cursor.1 + 1).min(SIZE - 1),
+            KeyCode::Tab => self.direction = self.direction.other(),
+            KeyCode::Esc => {
+                self.clear();
+                self.message = "Your move.".to_string();
+            }
+            KeyCode::Backspace => match &mut self.exchange {
+                Some(tiles) => {
+                    tiles.pop();
+                }
+                None => {
+                    if let Some((row, col, _)) = self.placed.pop() {
+                        self.cursor = (row, col);
+                    }
+                }
+            },
+            KeyCode::Enter => self.submit(),
+            KeyCode::Char(c) if c.is_ascii_alphabetic() || c == '?' => self.type_tile(c),
+            _ => {}
+        }
+        true
+    }
+
+    /// A letter typed on the board, or marked for an exchange.
+    fn type_tile(&mut self, c: char) {
+        let letter = c.to_ascii_uppercase();
+        let tile = if letter == '?' { '_' } else { letter };
+        let left = self.rack_left();
+        if let Some(tiles) = &mut self.exchange {
+            if left.contains(&tile) {
+                tiles.push(tile);
+            } else {
+                self.message = format!("{} is not on your rack", tile);
+            }
+            return;
+        }
+        if letter == '?' {
+            self.message = "Type the letter the wildcard stands for.".to_string();
+            return;
+        }
+        // Skip over tiles already on the board
+        while self.occupied(self.cursor) && self.step().is_some() {}
+        if self.occupied(self.cursor) {
+            return;
+        }
+        let placed = if left.contains(&letter) {
+            letter
+        } else if left.contains(&'_') {
+            letter.to_ascii_lowercase()
+        } else {
+            self.message = format!("{} is not on your rack", letter);
+            return;
+        };
+        self.placed.push((self.cursor.0, self.cursor.1, placed));
+        if self.step().is_some() {
+            while self
+                .game
+                .position
+                .get(self.cursor.0, self.cursor.1)
+                .is_some()
+                && self.step().is_some()
+            {}
+        }
+    }
+
+    /// Play the placed tiles, or make the marked exchange.
+    fn submit(&mut self) {
+        if let Some(tiles) = self.exchange.take() {
+            let outcome = self
+                .game
+                .exchange(&tiles)
+                .map(|_| format!("You exchanged {} tiles.", tiles.len()));
+            if outcome.is_err() {
+                self.exchange = Some(tiles);
+            }
+            self.finish_turn(outcome);
+            return;
+        }
+        let outcome = self
+            .game
+            .position
+            .play_from_tiles(&self.placed)
+            .and_then(|play| self.game.play(&play, &self.lexicon.trie))
+            .map(|play| format!("You played {} for {}.", play.notation(), play.score));
+        if outcome.is_ok() {
+            self.placed.clear();
+        }
+        self.finish_turn(outcome);
+    }
+
+    fn finish_turn<E: ToString>(&mut self, outcome: Result<String, E>) {
+        self.message = match outcome {
+            Ok(message) => message,
+            Err(e) => e.to_string(),
+        };
+        if self.game.is_over() {
+            self.message = self.result();
+        }
+    }
+
+    /// The agent makes the move its strategy picks. Should the game
+    /// refuse it, the agent passes instead.
+    fn agent_move(&mut self) {
+        let action = self.strategy.choose(&self.game, &self.lexicon.trie);
+        let reply = match self.game.apply(&action, &self.lexicon.trie) {
+            Ok(Action::Play(play)) => {
+                format!("Agent played {} for {}.", play.notation(), play.score)
+            }
+            Ok(Action::Exchange(tiles)) => format!("Agent exchanged {} tiles.", tiles.len()),
+            Ok(Action::Pass) => "Agent passed.".to_string(),
+            Ok(Action::Challenge) => "Agent challenged.".to_string(),
+            Err(e) => {
+                let _ = self.game.pass();
+                format!("Agent passed ({}).", e)
+            }
+        };
+        self.finish_turn(Ok::<_, String>(format!("{} Your move.", reply)));
+    }
+
+    /// The final result line.
+    fn result(&self) -> String {
+        let [you, agent] = self.game.scores;
+        let verdict = match self.game.winner() {
+            Some(0) => "You win",
+            Some(_) => "The agent wins",
+            None => "It's a tie",
+        };
+        format!("{} {}-{}. Press any key to leave.", verdict, you, agent)
+    }
+
+    fn clear(&mut self) {
+        self.placed.clear();
+        self.exchange = None;
+    }
+
+    /// The human's tiles not yet put on the board.
+    fn rack_left(&self) -> Vec<char> {
+        let placed: String = self.placed.iter().map(|(_, _, letter)| *letter).collect();
+        let mut left: Vec<char> = self.game.racks[0].leave(&placed).chars().collect();
+        if let Some(tiles) = &self.exchange {
+            for tile in tiles.chars() {
+                if let Some(i) = left.iter().position(|t| *t == tile) {
+                    left.remove(i);
+                }
+            }
+        }
+        left
+    }
+
+    fn occupied(&self, (row, col): (usize, usize)) -> bool {
+        self.game.position.get(row, col).is_some()
+            || self.placed.iter().any(|(r, c, _)| (*r, *c) == (row, col))
+    }
+
+    /// Move the cursor one square along the direction, if there is room.
+    fn step(&mut self) -> Option<()> {
+        let (row, col) = self.cursor;
+        self.cursor = match self.direction {
+            Direction::Across if col + 1 < SIZE => (row, col + 1),
+            Direction::Down if row + 1 < SIZE => (row + 1, col),
+            _ => return None,
+        };
+        Some(())
+    }
+
+    /// ### draw()
+    /// Redraw the whole screen: the board on the left, the panel on the
+    /// right and the keys and message underneath.
+    fn draw<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+        queue!(
+            out,
+            SetAttribute(Attribute::Bold),
+            Print("SCRABBLE  you vs the agent"),
+            SetAttribute(Attribute::Reset)
+        )?;
+
+        queue!(out, MoveTo(0, 2), Print("   "))?;
+        for col in 0..SIZE {
+            queue!(out, Print(format!(" {} ", (b'A' + col as u8) as char)))?;
+        }
+        let last: Vec<(usize, usize)> = match self.game.last_play() {
+            Some(play) => (0..play.word.len())
+                .map(|i| match play.direction {
+                    Direction::Across => (play.row, play.col + i),
+                    Direction::Down => (play.row + i, play.col),
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        for row in 0..SIZE {
+            queue!(
+                out,
+                MoveTo(0, 3 + row as u16),
+                Print(format!("{:>2} ", row + 1))
+            )?;
+            for col in 0..SIZE {
+                self.draw_square(out, row, col, last.contains(&(row, col)))?;
+            }
+        }
+        self.draw_panel(out)?;
+
+        let bottom = 4 + SIZE as u16;
+        queue!(out, MoveTo(0, bottom), Print(&self.message))?;
+        queue!(
+            out,
+            MoveTo(0, bottom + 2),
+            SetForegroundColor(Color::DarkGrey),
+            Print(KEYS),
+            ResetColor
+        )?;
+        out.flush()
+    }
+
+    /// One square, three characters wide.
+    fn draw_square<W: Write>(
+        &self,
+        out: &mut W,
+        row: usize,
+        col: usize,
+        last: bool,
+    ) -> io::Result<()> {
+        let pending = self
+            .placed
+            .iter()
+            .find(|(r, c, _)| (*r, *c) == (row, col))
+            .map(|(_, _, letter)| *letter);
+        let (text, foreground, background) = match (self.game.position.get(row, col), pending) {
+            (Some(tile), _) if last => (tile_text(tile), Color::Black, Color::Green),
+            (Some(tile), _) => (tile_text(tile), Color::Black, Color::DarkYellow),
+            (None, Some(tile)) => (tile_text(tile), Color::Black, Color::White),
+            (None, None) => match Premium::at(row, col) {
+                _ if (row, col) == (CENTER, CENTER) => {
+                    (" * ".to_string(), Color::White, Color::Magenta)
+                }
+                Premium::TripleWord => ("3W ".to_string(), Color::White, Color::DarkRed),
+                Premium::DoubleWord => ("2W ".to_string(), Color::White, Color::Magenta),
+                Premium::TripleLetter => ("3L ".to_string(), Color::White, Color::DarkBlue),
+                Premium::DoubleLetter => ("2L ".to_string(), Color::Black, Color::Cyan),
+                Premium::Plain => (" . ".to_string(), Color::DarkGrey, Color::Reset),
+            },
+        };
+        if (row, col) == self.cursor && self.game.turn == 0 {
+            queue!(out, SetAttribute(Attribute::Reverse))?;
+        }
+        queue!(
+            out,
+            SetForegroundColor(foreground),
+            SetBackgroundColor(background),
+            Print(text),
+            ResetColor,
+            SetAttribute(Attribute::Reset)
+        )
+    }
+
+    /// Scores, rack, bag, last move and unseen tiles.
+    fn draw_panel<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut y = 2;
+        let mut line = |out: &mut W, text: String| -> io::Result<()> {
+            queue!(out, MoveTo(PANEL, y), Print(text))?;
+            y += 1;
+            Ok(())
+        };
+        for (player, name) in [(0, "You"), (1, "Agent")] {
+            let marker = if self.game.turn == player && !self.game.is_over() {
+                ">"
+            } else {
+                " "
+            };
+            line(
+                out,
+                format!("{} {:<6} {:>4}", marker, name, self.game.scores[player]),
+            )?;
+        }
+        line(out, String::new())?;
+
+        let rack: Vec<String> = self
+            .rack_left()
+            .iter()
+            .map(|tile| format!("{}{}", tile, self.values.get_score(*tile)))
+            .collect();
+        line(out, format!("Rack:  {}", rack.join(" ")))?;
+        if let Some(tiles) = &self.exchange {
+            line(out, format!("Exchange: {}", tiles))?;
+        } else {
+            let arrow = match self.direction {
+                Direction::Across => "across ->",
+                Direction::Down => "down v",
+            };
+            line(out, format!("Direction: {}", arrow))?;
+        }
+        line(out, format!("Bag:   {} tiles", self.game.bag.len()))?;
+        let last = match self.game.history.last() {
+            Some(turn) => format!(
+                "Last:  {} {} {}",
+                if turn.player == 0 { "you" } else { "agent" },
+                turn.action.notation(),
+                match turn.action {
+                    Action::Play(_) => turn.score.to_string(),
+                    _ => String::new(),
+                }
+            ),
+            None => "Last:  -".to_string(),
+        };
+        line(out, last)?;
+        line(out, String::new())?;
+
+        let unseen = self.game.unseen(0);
+        line(out, format!("Unseen: {} tiles", unseen.len()))?;
+        let mut text = String::new();
+        for (i, tile) in unseen.iter().enumerate() {
+            if i > 0 && unseen[i - 1] != *tile {
+                text.push(' ');
+            }
+            text.push(*tile);
+        }
+        // Wrap the unseen tiles to the width of the panel
+        let mut row = String::new();
+        for group in text.split(' ') {
+            if row.len() + group.len() + 1 > 40 {
+                line(out, row.trim_end().to_string())?;
+                row.clear();
+            }
+            row.push_str(group);
+            row.push(' ');
+        }
+        line(out, row.trim_end().to_string())
+    }
+}
+
+/// A tile as shown on a square: wildcards in lowercase.
+fn tile_text(tile: char) -> String {
+    format!(" {} ", tile)
+}
+
+fn wait_for_key() -> io::Result<()> {
+    loop {
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Release {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use std::io::BufRead;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::agent::controller::{
+    CancelHandle, Progress, SearchController, SearchLimits, StopReason,
+};
+use crate::agent::rack::Rack;
+use crate::board::play::Play;
+use crate::board::position::Position;
+use crate::utils::lexicon::Lexicon;
+use crate::utils::weighted_group::WeightedGroups;
+
+/// How many of the best plays `go` reports unless `MultiPV` is set.
+const DEFAULT_MULTIPV: usize = 5;
+
+/// # Engine
+/// A line-based protocol on stdin/stdout that lets a GUI or another tool
+/// use the agent as a backend, in the spirit of UCI for chess engines.
+/// The front-end sets up the lexicon, the position and the rack, then
+/// sends `go`; the engine searches in the background, streams `info`
+/// lines and finishes with `bestmove`. While it searches, `stop` ends the
+/// search early with the best play found so far.
+///
+/// | Command | Reply |
+/// | --- | --- |
+/// | `scrabble` | `id` and `option` lines, then `scrabbleok` |
+/// | `isready` | `readyok` once the lexicon is loaded |
+/// | `setoption name <Threads\|MultiPV\|Lexicon> value <V>` | |
+/// | `lexicon <PATH>` | loads the word list |
+/// | `newgame` | clears the board and the rack |
+/// | `position empty\|board <ROWS> [moves <SQUARE WORD \| pass>...]` | |
+/// | `rack <LETTERS>` | |
+/// | `go [movetime MS] [nodes N]` | `info` lines, then `bestmove <SQUARE WORD>` or `bestmove pass` |
+/// | `stop` | ends the search |
+/// | `d` | prints the board and rack |
+/// | `quit` | |
+///
+/// Problems are reported as `info string error: ...` and never end the
+/// session.
+/// Functions:
+/// - `new()`, `run()`
+pub struct Engine {
+    lexicon_path: String,
+    lexicon: Option<Arc<Lexicon>>,
+    position: Position,
+    rack: Option<Rack>,
+    threads: usize,
+    multipv: usize,
+    search: Option<Search>,
+}
+
+/// A `go` running in the background.
+struct Search {
+    handle: JoinHandle<()>,
+    cancel: CancelHandle,
+}
+
+impl Engine {
+    pub fn new(lexicon_path: &str, threads: usize) -> Engine {
+        Engine {
+            lexicon_path: lexicon_path.to_string(),
+            lexicon: None,
+            position: Position::new(),
+            rack: None,
+            threads,
+            multipv: DEFAULT_MULTIPV,
+            search: None,
+        }
+    }
+
+    /// ### run()
+    /// Answer commands from `input` until `quit` or the end of input.
+    /// `quit` stops a running search; at the end of input it is allowed
+    /// to finish, so a script can pipe in a whole session.
+    pub fn run<R: BufRead>(&mut self, input: R) {
+        for line in input.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let (command, args) = match words.split_first() {
+                Some((command, args)) => (*command, args),
+                None => continue,
+            };
+            if command == "quit" {
+                self.stop();
+                return;
+            }
+            if let Err(message) = self.handle(command, args) {
+                println!("info string error: {}", message);
+            }
+        }
+        self.wait();
+    }
+
+    /// ### handle()
+    /// Carry out one command. Everything except `stop` and `isready`
+    /// first waits for a running search to finish.
+    fn handle(&mut self, command: &str, args: &[&str]) -> Result<(), String> {
+        match command {
+            "stop" => {
+                self.stop();
+                return Ok(());
+            }
+            "isready" => {
+                self.lexicon()?;
+                println!("readyok");
+                return Ok(());
+            }
+            _ => self.wait(),
+        }
+        match command {
+            "scrabble" => {
+                println!("id name scrabble {}", env!("CARGO_PKG_VERSION"));
+                println!(
+                    "option name Threads type spin default {} min 1 max 512",
+                    self.threads
+                );
+                println!(
+                    "option name MultiPV type spin default {} min 1 max 500",
+                    self.multipv
+                );
+                println!(
+                    "option name Lexicon type string default {}",
+                    self.lexicon_path
+                );
+                println!("scrabbleok");
+            }
+            "setoption" => self.set_option(args)?,
+            "lexicon" => {
+                self.lexicon_path = args.join(" ");
+                self.load_lexicon()?;
+            }
+            "newgame" => {
+                self.position = Position::new();
+                self.rack = None;
+            }
+            "position" => self.set_position(args)?,
+            "rack" => {
+                self.rack = Some(Rack::from_letters(&args.concat())?);
+            }
+            "go" => self.go(args)?,
+            "d" => {
+                self.position.display();
+                let rack: String = match &self.rack {
+                    Some(rack) => rack.letters.iter().collect(),
+                    None => "-".to_string(),
+                };
+                println!("Rack: {}", rack);
+                println!("Board: {}", self.position.notation());
+            }
+            _ => return Err(format!("unknown command \"{}\"", command)),
+        }
+        Ok(())
+    }
+
+    /// `setoption name <NAME> value <VALUE>`
+    fn set_option(&mut self, args: &[&str]) -> Result<(), String> {
+        let (name, value) = match args {
+            ["name", name, "value", value @ ..] if !value.is_empty() => (*name, value.join(" ")),
+            _ => return Err("expected setoption name <NAME> value <VALUE>".to_string()),
+        };
+        let number = || {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| {
+                    format!("{} expects a whole number above 0, got \"{}\"", name, value)
+                })
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "threads" => self.threads = number()?,
+            "multipv" => self.multipv = number()?,
+            "lexicon" => {
+                self.lexicon_path = value.clone();
+                self.load_lexicon()?;
+            }
+            _ => return Err(format!("unknown option \"{}\"", name)),
+        }
+        Ok(())
+    }
+
+    /// `position empty|board <ROWS> [moves <SQUARE WORD | pass>...]`
+    /// Every move is checked against the position it is played on.
+    fn set_position(&mut self, args: &[&str]) -> Result<(), String> {
+        let (mut position, rest) = match args {
+            ["empty", rest @ ..] => (Position::new(), rest),
+            ["board", rows, rest @ ..] => (Position::parse(rows)?, rest),
+            _ => return Err("expected position empty or position board <ROWS>".to_string()),
+        };
+        let moves = match rest {
+            [] => &[][..],
+            ["moves", moves @ ..] => moves,
+            _ => return Err(format!("expected moves, got \"{}\"", rest.join(" "))),
+        };
+        let values = WeightedGroups::new();
+        let mut moves = moves.iter();
+        while let Some(square) = moves.next() {
+            if *square == "pass" {
+                continue;
+            }
+            let word = moves
+                .next()
+                .ok_or_else(|| format!("move {} has no word", square))?;
+            let play = Play::parse(square, word)?;
+            let lexicon = self.lexicon()?;
+            position.apply(&play, &lexicon.trie, &values)?;
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    /// `go [movetime MS] [nodes N]`
+    /// Start searching in the background. Each finished line of the board
+    /// is reported as `info ... pv <best play so far>`; at the end the
+    /// `MultiPV` best plays are listed as `info multipv`, then `bestmove`.
+    fn go(&mut self, args: &[&str]) -> Result<(), String> {
+        let mut limits = SearchLimits::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = |name: &str, value: Option<&&str>| {
+                value
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .ok_or_else(|| format!("{} expects a whole number", name))
+            };
+            match *arg {
+                "movetime" => limits.time = Some(Duration::from_millis(value(arg, args.next())?)),
+                "nodes" => limits.nodes = Some(value(arg, args.next())?),
+                "infinite" => {}
+                _ => return Err(format!("unknown go parameter \"{}\"", arg)),
+            }
+        }
+        let rack = self
+            .rack
+            .clone()
+            .ok_or_else(|| "no rack, send rack <LETTERS> first".to_string())?;
+        let lexicon = self.lexicon()?;
+        let position = self.position.clone();
+        let (threads, multipv) = (self.threads, self.multipv);
+
+        let mut controller = SearchController::new(limits);
+        controller.on_progress(|progress: &Progress<Play>| {
+            if let Some(best) = progress.best {
+                println!(
+                    "info time {} nodes {} lines {}/{} candidates {} score {} pv {}",
+                    progress.elapsed.as_millis(),
+                    progress.nodes,
+                    progress.units_done,
+                    progress.units_total,
+                    progress.candidates,
+                    best.score,
+                    best.notation()
+                );
+            }
+        });
+        let cancel = controller.cancel_handle();
+        let handle = thread::spawn(move || {
+            let result = controller.run_position(&position, &rack, &lexicon.trie, threads);
+            for (i, play) in result.moves.iter().take(multipv).enumerate() {
+                println!(
+                    "info multipv {} score {} pv {}",
+                    i + 1,
+                    play.score,
+                    play.notation()
+                );
+            }
+            if result.stop_reason != StopReason::Complete {
+                println!(
+                    "info string search stopped: {}",
+                    result.stop_reason.as_str()
+                );
+            }
+            match result.best() {
+                Some(best) => println!("bestmove {}", best.notation()),
+                None => println!("bestmove pass"),
+            }
+        });
+        self.search = Some(Search { handle, cancel });
+        Ok(())
+    }
+
+    /// The loaded lexicon, loading it first if no command has yet.
+    fn lexicon(&mut self) -> Result<Arc<Lexicon>, String> {
+        match &self.lexicon {
+            Some(lexicon) => Ok(Arc::clone(lexicon)),
+            None => self.load_lexicon(),
+        }
+    }
+
+    fn load_lexicon(&mut self) -> Result<Arc<Lexicon>, String> {
+        let lexicon = Arc::new(Lexicon::load(&self.lexicon_path)?);
+        self.lexicon = Some(Arc::clone(&lexicon));
+        println!("info string lexicon {} loaded", self.lexicon_path);
+        Ok(lexicon)
+    }
+
+    /// Cancel the running search, if any, and wait for its `bestmove`.
+    fn stop(&mut self) {
+        if let Some(search) = &self.search {
+            search.cancel.cancel();
+        }
+        self.wait();
+    }
+
+    /// Wait for the running search, if any, to finish.
+    fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            let _ = search.handle.join();
+        }
+    }
+}
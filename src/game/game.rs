@@ -0,0 +1,624 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::agent::bag::Bag;
+use crate::agent::rack::Rack;
+use crate::board::play::Play;
+use crate::board::position::Position;
+use crate::game::tracker::TileTracker;
+use crate::utils::error::Error;
+use crate::utils::trieguy::TrieTree;
+use crate::utils::weighted_group::WeightedGroups;
+
+/// How many tiles a player holds after drawing.
+pub const RACK_SIZE: usize = 7;
+
+/// The game ends after this many scoreless turns in a row.
+pub const MAX_SCORELESS_TURNS: usize = 6;
+
+/// ## ChallengeRule
+/// What happens to a phony, a play forming a word that is not in the
+/// lexicon, and to a challenge that finds none.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChallengeRule {
+    /// Phonies are refused as soon as they are played
+    Void,
+    /// A challenged phony is withdrawn and scores nothing; a challenge of
+    /// a valid play costs nothing
+    Single,
+    /// As `Single`, but a challenge of a valid play costs the challenger
+    /// their turn
+    Double,
+    /// As `Single`, but a challenge of a valid play earns the player
+    /// challenged this many points
+    Points(i32),
+}
+
+impl ChallengeRule {
+    /// ### parse()
+    /// Read a rule by name: `void`, `single`, `double`, or `5-point` or
+    /// `10-point` for the points given for a failed challenge.
+    pub fn parse(name: &str) -> Result<ChallengeRule, Error> {
+        match name.to_lowercase().as_str() {
+            "void" => Ok(ChallengeRule::Void),
+            "single" => Ok(ChallengeRule::Single),
+            "double" => Ok(ChallengeRule::Double),
+            "5-point" => Ok(ChallengeRule::Points(5)),
+            "10-point" => Ok(ChallengeRule::Points(10)),
+            _ => Err(Error::Parse(format!(
+                "unknown challenge rule \"{}\", expected void, single, double, 5-point or 10-point",
+                name
+            ))),
+        }
+    }
+
+    /// The name `parse()` reads back.
+    pub fn name(self) -> String {
+        match self {
+            ChallengeRule::Void => "void".to_string(),
+            ChallengeRule::Single => "single".to_string(),
+            ChallengeRule::Double => "double".to_string(),
+            ChallengeRule::Points(points) => format!("{}-point", points),
+        }
+    }
+}
+
+/// ## Action
+/// What a player did on their turn.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    Play(Play),
+    /// The tiles put back in the bag
+    Exchange(String),
+    Pass,
+    /// A challenge of the play just made by the opponent
+    Challenge,
+}
+
+impl Action {
+    /// ### parse()
+    /// Read a move as written by `notation()`; `exchange QV` is accepted
+    /// for `-QV` too.
+    pub fn parse(text: &str) -> Result<Action, Error> {
+        let text = text.trim();
+        match text {
+            "pass" => return Ok(Action::Pass),
+            "challenge" => return Ok(Action::Challenge),
+            _ => {}
+        }
+        match text
+            .strip_prefix('-')
+            .or_else(|| text.strip_prefix("exchange "))
+        {
+            Some(tiles) => Ok(Action::Exchange(tiles.trim().to_string())),
+            None => Ok(Action::Play(Play::from_notation(text)?)),
+        }
+    }
+
+    /// The action as written on a score sheet: `8H QUIZ`, `-QV`, `pass`
+    /// or `challenge`.
+    pub fn notation(&self) -> String {
+        match self {
+            Action::Play(play) => play.notation(),
+            Action::Exchange(tiles) => format!("-{}", tiles),
+            Action::Pass => "pass".to_string(),
+            Action::Challenge => "challenge".to_string(),
+        }
+    }
+}
+
+/// ## Turn
+/// One line of the score sheet: who moved, the rack they held, what they
+/// did, what it scored and their total afterwards. A play challenged off
+/// the board stays on the sheet, `withdrawn` and scoring nothing.
+#[derive(Clone, Debug)]
+pub struct Turn {
+    pub player: usize,
+    pub rack: String,
+    pub action: Action,
+    pub score: i32,
+    pub total: i32,
+    pub withdrawn: bool,
+}
+
+/// The last play, kept until the opponent moves so a challenge can take
+/// it back.
+#[derive(Clone, Debug)]
+struct Pending {
+    player: usize,
+    position: Position,
+    rack: Vec<char>,
+    drawn: Vec<char>,
+    scoreless: usize,
+}
+
+/// # Game
+/// A two player game on the full board: the `Position`, the `Bag`, each
+/// player's rack and score, and the history of every turn. Tiles are drawn
+/// from a seeded bag, so a game can be replayed from its seed and moves.
+///
+/// The game ends when a player goes out with the bag empty, or after
+/// `MAX_SCORELESS_TURNS` scoreless turns in a row. Going out earns the
+/// value of the opponent's tiles and costs the opponent as much;
+/// otherwise each player loses the value of their own tiles.
+///
+/// Phonies are handled by the game's `ChallengeRule`, `Void` unless set
+/// otherwise before the first move. Under the other rules a phony can be
+/// played, and the opponent may `challenge()` it before making their own
+/// move, even one that ended the game.
+/// Functions:
+/// - `new()`, `rack()`, `play()`, `apply()`, `exchange()`, `pass()`,
+///   `challenge()`, `can_challenge()`, `pending_phonies()`, `is_over()`, `winner()`,
+///   `last_play()`, `unseen()`
+pub struct Game {
+    pub position: Position,
+    pub bag: Bag,
+    pub racks: [Rack; 2],
+    pub scores: [i32; 2],
+    /// The player to move, `0` or `1`
+    pub turn: usize,
+    pub history: Vec<Turn>,
+    /// The points each player gained or lost for the tiles left on the
+    /// racks, once the game is over
+    pub rack_adjustments: Option<[i32; 2]>,
+    pub seed: u64,
+    pub challenge_rule: ChallengeRule,
+    scoreless: usize,
+    pending: Option<Pending>,
+    rng: StdRng,
+    values: WeightedGroups,
+}
+
+impl Game {
+    /// Start a game, drawing both racks from a bag shuffled by `seed`.
+    pub fn new(seed: u64) -> Game {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut bag = Bag::new();
+        let first = Rack::from_bag(&mut bag, &mut rng);
+        let second = Rack::from_bag(&mut bag, &mut rng);
+        Game {
+            position: Position::new(),
+            bag,
+            racks: [first, second],
+            scores: [0, 0],
+            turn: 0,
+            history: Vec::new(),
+            rack_adjustments: None,
+            seed,
+            challenge_rule: ChallengeRule::Void,
+            scoreless: 0,
+            pending: None,
+            rng,
+            values: WeightedGroups::new(),
+        }
+    }
+
+    /// The rack of the player to move.
+    pub fn rack(&self) -> &Rack {
+        &self.racks[self.turn]
+    }
+
+    /// ### play()
+    /// Make `play` for the player to move: it must fit on the board and be
+    /// made of tiles on their rack, and under `ChallengeRule::Void` its
+    /// words must be in the lexicon. The tiles are replaced from the bag.
+    /// Returns the play with its tiles and score filled in.
+    pub fn play(&mut self, play: &Play, tree: &TrieTree) -> Result<Play, Error> {
+        self.check_not_over()?;
+        let checked = match self.challenge_rule {
+            ChallengeRule::Void => self.position.check(play, tree, &self.values)?,
+            _ => self.position.check_placement(play, &self.values)?,
+        };
+        let left = take_tiles(&self.rack().letters, &checked.tiles)?;
+        let before = self.position.clone();
+        self.position.place(&checked);
+
+        let player = self.turn;
+        let held = self.racks[player].letters.clone();
+        self.racks[player].letters = left;
+        let drawn = self.refill(player);
+        let scoreless = std::mem::replace(&mut self.scoreless, 0);
+        self.record(
+            held.iter().collect(),
+            Action::Play(checked.clone()),
+            checked.score as i32,
+        );
+        if self.challenge_rule != ChallengeRule::Void {
+            self.pending = Some(Pending {
+                player,
+                position: before,
+                rack: held,
+                drawn,
+                scoreless,
+            });
+        }
+
+        if self.racks[player].letters.is_empty() {
+            self.finish(Some(player));
+        }
+        Ok(checked)
+    }
+
+    /// ### apply()
+    /// Make `action` for the player to move, however it was chosen.
+    /// Returns it as made, with a play's tiles and score filled in.
+    pub fn apply(&mut self, action: &Action, tree: &TrieTree) -> Result<Action, Error> {
+        match action {
+            Action::Play(play) => Ok(Action::Play(self.play(play, tree)?)),
+            Action::Exchange(tiles) => {
+                self.exchange(tiles)?;
+                Ok(action.clone())
+            }
+            Action::Pass => {
+                self.pass()?;
+                Ok(Action::Pass)
+            }
+            Action::Challenge => {
+                self.challenge(tree)?;
+                Ok(Action::Challenge)
+            }
+        }
+    }
+
+    /// ### exchange()
+    /// Put `tiles` back in the bag and draw as many new ones. Only allowed
+    /// while the bag still holds a full rack.
+    pub fn exchange(&mut self, tiles: &str) -> Result<(), Error> {
+        self.check_not_over()?;
+        if self.bag.len() < RACK_SIZE {
+            return Err(Error::IllegalMove(format!(
+                "can't exchange with {} tiles in the bag",
+                self.bag.len()
+            )));
+        }
+        let tiles = tiles.to_uppercase().replace('?', "_");
+        if tiles.is_empty() {
+            return Err(Error::IllegalMove("exchange at least one tile".to_string()));
+        }
+        let left = take_tiles(&self.rack().letters, &tiles)?;
+        self.pending = None;
+
+        let player = self.turn;
+        let held = self.racks[player].letters.iter().collect();
+        self.racks[player].letters = left;
+        // Draw before returning the tiles, so the same ones can't come back
+        self.refill(player);
+        let returned: Vec<char> = tiles.chars().collect();
+        self.bag.put_back(&returned);
+        self.scoreless += 1;
+        self.record(held, Action::Exchange(tiles), 0);
+        self.end_if_scoreless();
+        Ok(())
+    }
+
+    /// ### pass()
+    /// Give up the turn.
+    pub fn pass(&mut self) -> Result<(), Error> {
+        self.check_not_over()?;
+        self.pending = None;
+        let held = self.rack().letters.iter().collect();
+        self.scoreless += 1;
+        self.record(held, Action::Pass, 0);
+        self.end_if_scoreless();
+        Ok(())
+    }
+
+    /// Whether the player to move may challenge the play just made.
+    pub fn can_challenge(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// ### pending_phonies()
+    /// The words not in the lexicon formed by the play that may still be
+    /// challenged; empty when it is valid or there is none.
+    pub fn pending_phonies(&self, tree: &TrieTree) -> Vec<String> {
+        match (&self.pending, self.history.last()) {
+            (
+                Some(pending),
+                Some(Turn {
+                    action: Action::Play(play),
+                    ..
+                }),
+            ) => pending.position.phonies(play, tree),
+            _ => Vec::new(),
+        }
+    }
+
+    /// ### challenge()
+    /// The player to move challenges the play just made. A phony is
+    /// withdrawn: its tiles go back to the rack they came from, the tiles
+    /// drawn for it go back to the bag, it scores nothing and counts as
+    /// a scoreless turn; the challenger then moves. A valid play stands,
+    /// and the challenger pays as the `ChallengeRule` says. Returns
+    /// whether the play was withdrawn.
+    pub fn challenge(&mut self, tree: &TrieTree) -> Result<bool, Error> {
+        let pending = self
+            .pending
+            .take()
+            .ok_or_else(|| Error::IllegalMove("there is no play to challenge".to_string()))?;
+        let challenger = 1 - pending.player;
+        let play = match self.history.last() {
+            Some(Turn {
+                action: Action::Play(play),
+                ..
+            }) => play.clone(),
+            _ => {
+                return Err(Error::IllegalMove(
+                    "there is no play to challenge".to_string(),
+                ))
+            }
+        };
+        let phony = !pending.position.phonies(&play, tree).is_empty();
+
+        if phony {
+            // Take back the end of the game too, if the phony went out
+            if let Some(adjustments) = self.rack_adjustments.take() {
+                self.scores[0] -= adjustments[0];
+                self.scores[1] -= adjustments[1];
+            }
+            self.position = pending.position;
+            self.bag.put_back(&pending.drawn);
+            self.racks[pending.player].letters = pending.rack;
+            self.adjust_last_play(pending.player, None);
+            self.scoreless = pending.scoreless + 1;
+        } else if let ChallengeRule::Points(points) = self.challenge_rule {
+            self.adjust_last_play(pending.player, Some(points));
+        }
+
+        let held = self.racks[challenger].letters.iter().collect();
+        self.history.push(Turn {
+            player: challenger,
+            rack: held,
+            action: Action::Challenge,
+            score: 0,
+            total: self.scores[challenger],
+            withdrawn: false,
+        });
+        if !phony && self.challenge_rule == ChallengeRule::Double && !self.is_over() {
+            // The challenger loses their turn
+            self.scoreless += 1;
+            self.turn = pending.player;
+        }
+        if !self.is_over() {
+            self.end_if_scoreless();
+        }
+        Ok(phony)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.rack_adjustments.is_some()
+    }
+
+    /// ### winner()
+    /// The player with the higher score once the game is over, or `None`
+    /// for a tie or a game still going.
+    pub fn winner(&self) -> Option<usize> {
+        if !self.is_over() || self.scores[0] == self.scores[1] {
+            return None;
+        }
+        Some(if self.scores[0] > self.scores[1] {
+            0
+        } else {
+            1
+        })
+    }
+
+    /// The most recent play on the board, skipping passes, exchanges and
+    /// withdrawn phonies.
+    pub fn last_play(&self) -> Option<&Play> {
+        self.history
+            .iter()
+            .rev()
+            .find_map(|turn| match &turn.action {
+                Action::Play(play) if !turn.withdrawn => Some(play),
+                _ => None,
+            })
+    }
+
+    /// ### unseen()
+    /// The tiles `player` can't see: everything in a full bag that is not
+    /// on the board or on their own rack, so the bag and the opponent's
+    /// rack together. Sorted A-Z with wildcards last; see `TileTracker`.
+    pub fn unseen(&self, player: usize) -> Vec<char> {
+        TileTracker::from_game(self, player).unseen
+    }
+
+    fn check_not_over(&self) -> Result<(), Error> {
+        if self.is_over() {
+            return Err(Error::IllegalMove("the game is over".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Top `player`'s rack back up to `RACK_SIZE` tiles, returning the
+    /// tiles drawn.
+    fn refill(&mut self, player: usize) -> Vec<char> {
+        let missing = RACK_SIZE.saturating_sub(self.racks[player].letters.len());
+        let drawn = self.bag.draw(missing, &mut self.rng);
+        self.racks[player].letters.extend(drawn.iter().cloned());
+        drawn
+    }
+
+    /// Score the turn, add it to the history and pass the move on.
+    fn record(&mut self, rack: String, action: Action, score: i32) {
+        let player = self.turn;
+        self.scores[player] += score;
+        self.history.push(Turn {
+            player,
+            rack,
+            action,
+            score,
+            total: self.scores[player],
+            withdrawn: false,
+        });
+        self.turn = 1 - player;
+    }
+
+    /// Rescore the last play of `player` after a challenge: `None` for a
+    /// withdrawn phony, or the points it earns for standing.
+    fn adjust_last_play(&mut self, player: usize, bonus: Option<i32>) {
+        if let Some(turn) = self.history.last_mut() {
+            let change = bonus.unwrap_or(-turn.score);
+            self.scores[player] += change;
+            turn.score += change;
+            turn.total = self.scores[player];
+            turn.withdrawn = bonus.is_none();
+        }
+    }
+
+    fn end_if_scoreless(&mut self) {
+        if self.scoreless >= MAX_SCORELESS_TURNS {
+            self.finish(None);
+        }
+    }
+
+    /// Settle the tiles left on the racks; `out` is the player who used
+    /// all their tiles, if any.
+    fn finish(&mut self, out: Option<usize>) {
+        let value = |rack: &Rack| -> i32 {
+            rack.letters
+                .iter()
+                .map(|tile| self.values.get_score(*tile) as i32)
+                .sum()
+        };
+        let left = [value(&self.racks[0]), value(&self.racks[1])];
+        let adjustments = match out {
+            Some(0) => [left[1], -left[1]],
+            Some(_) => [-left[0], left[0]],
+            None => [-left[0], -left[1]],
+        };
+        self.scores[0] += adjustments[0];
+        self.scores[1] += adjustments[1];
+        self.rack_adjustments = Some(adjustments);
+    }
+}
+
+/// The letters left after taking `tiles` from `letters`, where a lowercase
+/// tile (or `_`) takes a wildcard.
+pub fn take_tiles(letters: &[char], tiles: &str) -> Result<Vec<char>, Error> {
+    let mut left = letters.to_vec();
+    for tile in tiles.chars() {
+        let wanted = if tile.is_ascii_lowercase() { '_' } else { tile };
+        match left.iter().position(|l| *l == wanted) {
+            Some(i) => {
+                left.remove(i);
+            }
+            None if wanted == '_' => {
+                return Err(Error::IllegalMove(
+                    "no wildcard left on the rack".to_string(),
+                ))
+            }
+            None => return Err(Error::IllegalMove(format!("{} is not on the rack", wanted))),
+        }
+    }
+    Ok(left)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::lexicon::Lexicon;
+
+    fn lexicon() -> Lexicon {
+        Lexicon::from_words(["AT", "CAT", "TA"].iter().map(|word| word.to_string()))
+    }
+
+    /// A game under `rule` where the first player holds `CATXEIO`.
+    fn game(rule: ChallengeRule) -> Game {
+        let mut game = Game::new(7);
+        game.challenge_rule = rule;
+        game.racks[0].letters = "CATXEIO".chars().collect();
+        game
+    }
+
+    fn play(notation: &str) -> Play {
+        Play::from_notation(notation).unwrap()
+    }
+
+    #[test]
+    fn void_refuses_phonies_outright() {
+        let lexicon = lexicon();
+        let mut game = game(ChallengeRule::Void);
+        assert!(game.play(&play("8H TXC"), &lexicon.trie).is_err());
+        assert!(game.history.is_empty());
+        assert_eq!(game.turn, 0);
+        assert!(!game.can_challenge());
+    }
+
+    #[test]
+    fn a_challenged_phony_is_withdrawn() {
+        let lexicon = lexicon();
+        let mut game = game(ChallengeRule::Single);
+        let bag = game.bag.len();
+        game.play(&play("8H TXC"), &lexicon.trie).unwrap();
+        assert_eq!(game.pending_phonies(&lexicon.trie), vec!["TXC"]);
+        assert!(game.challenge(&lexicon.trie).unwrap());
+        assert!(game.position.is_empty());
+        assert_eq!(game.racks[0].letters.iter().collect::<String>(), "CATXEIO");
+        assert_eq!(game.bag.len(), bag);
+        assert_eq!(game.scores, [0, 0]);
+        assert!(game.history[0].withdrawn);
+        assert!(game.last_play().is_none());
+        // The challenger moves next
+        assert_eq!(game.turn, 1);
+    }
+
+    #[test]
+    fn single_challenges_of_valid_plays_cost_nothing() {
+        let lexicon = lexicon();
+        let mut game = game(ChallengeRule::Single);
+        let score = game.play(&play("8H CAT"), &lexicon.trie).unwrap().score as i32;
+        assert!(!game.challenge(&lexicon.trie).unwrap());
+        assert_eq!(game.scores, [score, 0]);
+        assert_eq!(game.turn, 1);
+        assert!(!game.can_challenge());
+    }
+
+    #[test]
+    fn double_challenges_of_valid_plays_lose_the_turn() {
+        let lexicon = lexicon();
+        let mut game = game(ChallengeRule::Double);
+        let score = game.play(&play("8H CAT"), &lexicon.trie).unwrap().score as i32;
+        assert!(!game.challenge(&lexicon.trie).unwrap());
+        assert_eq!(game.scores, [score, 0]);
+        assert_eq!(game.turn, 0);
+    }
+
+    #[test]
+    fn point_challenges_of_valid_plays_pay_the_player() {
+        let lexicon = lexicon();
+        let mut game = game(ChallengeRule::Points(5));
+        let score = game.play(&play("8H CAT"), &lexicon.trie).unwrap().score as i32;
+        assert!(!game.challenge(&lexicon.trie).unwrap());
+        assert_eq!(game.scores, [score + 5, 0]);
+        assert_eq!(game.history[0].score, score + 5);
+        assert_eq!(game.turn, 1);
+    }
+
+    #[test]
+    fn only_the_last_play_can_be_challenged() {
+        let lexicon = lexicon();
+        let mut game = game(ChallengeRule::Single);
+        assert!(game.challenge(&lexicon.trie).is_err());
+        game.play(&play("8H TXC"), &lexicon.trie).unwrap();
+        game.pass().unwrap();
+        assert!(!game.can_challenge());
+        assert!(game.challenge(&lexicon.trie).is_err());
+    }
+
+    #[test]
+    fn rules_parse_and_print() {
+        for name in ["void", "single", "double", "5-point", "10-point"] {
+            assert_eq!(ChallengeRule::parse(name).unwrap().name(), name);
+        }
+        for name in [
+            "triple", "-5-point", "0-point", "+7-point", "7-point", "point",
+        ] {
+            assert!(
+                matches!(ChallengeRule::parse(name), Err(Error::Parse(_))),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use serde_json::{json, Value};
+
+use crate::board::play::Play;
+use crate::board::position::Position;
+use crate::board::svg::to_svg;
+use crate::game::game::{Action, Game};
+
+/// The page around the replay data: the board on the left, the scores, the
+/// current move and the move list on the right, with buttons and the arrow
+/// keys to step through the game. `TITLE` and `STEPS` are filled in.
+const PAGE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>TITLE</title>
+<style>
+body { font-family: Helvetica, Arial, sans-serif; margin: 24px; color: #222; }
+main { display: flex; gap: 32px; align-items: flex-start; }
+#side { min-width: 300px; }
+#scores { font-size: 20px; margin-bottom: 12px; }
+#scores span { display: inline-block; min-width: 140px; }
+#move { font-weight: bold; min-height: 1.4em; }
+#note { font-style: italic; min-height: 1.4em; margin: 6px 0 12px; }
+button { font-size: 16px; padding: 4px 12px; }
+ol { max-height: 420px; overflow-y: auto; padding-left: 32px; }
+li { cursor: pointer; padding: 1px 4px; }
+li.current { background: #ffe2b3; }
+li .note { color: #777; }
+</style>
+</head>
+<body>
+<h1>TITLE</h1>
+<main>
+<div id="board"></div>
+<div id="side">
+<div id="scores"></div>
+<div id="move"></div>
+<div id="note"></div>
+<div>
+<button id="first" title="Start">&#x23EE;</button>
+<button id="back" title="Back (left arrow)">&#x25C0;</button>
+<button id="forward" title="Forward (right arrow)">&#x25B6;</button>
+<button id="last" title="End">&#x23ED;</button>
+</div>
+<ol id="moves"></ol>
+</div>
+</main>
+<script>
+const steps = STEPS;
+let current = 0;
+const list = document.getElementById("moves");
+steps.slice(1).forEach((step, i) => {
+  const item = document.createElement("li");
+  item.textContent = step.move + " ";
+  if (step.note) {
+    const note = document.createElement("span");
+    note.className = "note";
+    note.textContent = "# " + step.note;
+    item.appendChild(note);
+  }
+  item.onclick = () => show(i + 1);
+  list.appendChild(item);
+});
+function show(index) {
+  current = Math.max(0, Math.min(steps.length - 1, index));
+  const step = steps[current];
+  document.getElementById("board").innerHTML = step.svg;
+  const scores = document.getElementById("scores");
+  scores.innerHTML = "";
+  step.scores.forEach((score, player) => {
+    const line = document.createElement("span");
+    line.textContent = (step.turn === player ? "▸ " : "  ") + step.players[player] + " " + score;
+    scores.appendChild(line);
+  });
+  document.getElementById("move").textContent = step.move;
+  document.getElementById("note").textContent = step.note || "";
+  Array.from(list.children).forEach((item, i) => item.classList.toggle("current", i + 1 === current));
+}
+document.getElementById("first").onclick = () => show(0);
+document.getElementById("back").onclick = () => show(current - 1);
+document.getElementById("forward").onclick = () => show(current + 1);
+document.getElementById("last").onclick = () => show(steps.length - 1);
+document.addEventListener("keydown", event => {
+  if (event.key === "ArrowLeft") show(current - 1);
+  if (event.key === "ArrowRight") show(current + 1);
+  if (event.key === "Home") show(0);
+  if (event.key === "End") show(steps.length - 1);
+});
+show(0);
+</script>
+</body>
+</html>
+"##;
+
+/// ### to_html()
+/// A self-contained HTML page replaying `game`: the board after every
+/// move, drawn by `to_svg()` with the move outlined, the running scores
+/// and the move list, stepped through with buttons or the arrow keys. It
+/// needs nothing but a browser. `notes` holds an optional annotation per
+/// move, shown beside it.
+pub fn to_html(game: &Game, players: &[String; 2], notes: &[Option<String>]) -> String {
+    let mut steps = vec![step(
+        &Position::new(),
+        None,
+        players,
+        [0, 0],
+        Some(0),
+        "Start".to_string(),
+        None,
+    )];
+
+    // Replay the history onto an empty board. A phony is shown on the
+    // board until the challenge that withdraws it
+    let mut position = Position::new();
+    let mut scores = [0, 0];
+    for (i, turn) in game.history.iter().enumerate() {
+        let mut shown = position.clone();
+        let play = match &turn.action {
+            Action::Play(play) => {
+                shown.place(play);
+                if !turn.withdrawn {
+                    position.place(play);
+                }
+                Some(play)
+            }
+            _ => None,
+        };
+        scores[turn.player] = turn.total;
+        // A challenge rescores the play before it
+        if turn.action == Action::Challenge && i > 0 {
+            let challenged = &game.history[i - 1];
+            scores[challenged.player] = challenged.total;
+        }
+        let text = match &turn.action {
+            Action::Challenge => {
+                let outcome = match i > 0 && game.history[i - 1].withdrawn {
+                    true => "the play is withdrawn",
+                    false => "the play stands",
+                };
+                format!(
+                    "{}. {}: challenge, {}",
+                    i + 1,
+                    players[turn.player],
+                    outcome
+                )
+            }
+            Action::Play(_) if turn.withdrawn => format!(
+                "{}. {}: {} withdrawn (rack {})",
+                i + 1,
+                players[turn.player],
+                turn.action.notation(),
+                turn.rack
+            ),
+            _ => format!(
+                "{}. {}: {} {:+} (rack {})",
+                i + 1,
+                players[turn.player],
+                turn.action.notation(),
+                turn.score,
+                turn.rack
+            ),
+        };
+        let note = notes.get(i).cloned().flatten();
+        let next = match game.history.get(i + 1) {
+            Some(next) => next.player,
+            None => game.turn,
+        };
+        steps.push(step(&shown, play, players, scores, Some(next), text, note));
+    }
+
+    if let Some(adjustments) = game.rack_adjustments {
+        let text = format!(
+            "Game over: tiles left {:+} / {:+}, final {} - {}",
+            adjustments[0], adjustments[1], game.scores[0], game.scores[1]
+        );
+        steps.push(step(
+            &position,
+            None,
+            players,
+            game.scores,
+            None,
+            text,
+            None,
+        ));
+    }
+
+    let title = escape(&format!("{} vs {}", players[0], players[1]));
+    // Keep a "</script>" inside the data from ending the script early
+    let data = Value::Array(steps).to_string().replace("</", "<\\/");
+    match PAGE.split_once("STEPS") {
+        Some((head, tail)) => format!("{}{}{}", head.replace("TITLE", &title), data, tail),
+        None => PAGE.to_string(),
+    }
+}
+
+/// One position of the replay as the page's script reads it.
+fn step(
+    position: &Position,
+    play: Option<&Play>,
+    players: &[String; 2],
+    scores: [i32; 2],
+    turn: Option<usize>,
+    text: String,
+    note: Option<String>,
+) -> Value {
+    json!({
+        "svg": to_svg(position, play),
+        "players": players,
+        "scores": scores,
+        "turn": turn,
+        "move": text,
+        "note": note,
+    })
+}
+
+/// Text made safe to put in the page's markup.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,344 @@
+use std::collections::BTreeMap;
+use std::f64::consts::{LN_10, PI};
+use std::fs;
+use std::io;
+
+use serde_json::{json, Value};
+
+use crate::agent::controller::SearchLimits;
+use crate::game::selfplay::{GameResult, SelfPlay};
+use crate::utils::error::Error;
+use crate::utils::trieguy::TrieTree;
+
+/// First line of a ratings file written by `Ratings::save()`. Every
+/// following line is a strategy, its rating, its rating deviation and the
+/// games it has played, e.g. `equity 1623.4 61.2 120`.
+pub const RATINGS_HEADER: &str = "#scrabble-ratings glicko";
+
+/// Where every strategy starts.
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// The deviation of a strategy that has never played: nothing is known.
+pub const INITIAL_DEVIATION: f64 = 350.0;
+
+/// How much a deviation grows between tournaments, since a strategy may
+/// have been changed since it was last rated.
+pub const DEVIATION_GROWTH: f64 = 30.0;
+
+/// Glicko's scale factor, ln(10) / 400.
+const Q: f64 = LN_10 / 400.0;
+
+/// ## Rating
+/// A Glicko rating: the strength estimate and how uncertain it is. About
+/// 95% of the time the true strength lies within two deviations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub games: usize,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            rating: INITIAL_RATING,
+            deviation: INITIAL_DEVIATION,
+            games: 0,
+        }
+    }
+}
+
+impl Rating {
+    /// The expected score, between 0 and 1, against `opponent`.
+    pub fn expected(&self, opponent: &Rating) -> f64 {
+        1.0 / (1.0 + 10f64.powf(-g(opponent.deviation) * (self.rating - opponent.rating) / 400.0))
+    }
+
+    /// The rating at the start of the next tournament: the deviation grows
+    /// by `DEVIATION_GROWTH`, but never past knowing nothing.
+    pub fn aged(&self) -> Rating {
+        Rating {
+            deviation: (self.deviation.powi(2) + DEVIATION_GROWTH.powi(2))
+                .sqrt()
+                .min(INITIAL_DEVIATION),
+            ..*self
+        }
+    }
+
+    /// ### rated()
+    /// The rating after one period of `games`, each the opponent's rating
+    /// and the score against them, as in Glickman's paper. The deviation is
+    /// taken as it stands at the start of the period; see `aged()`.
+    pub fn rated(&self, games: &[(Rating, f64)]) -> Rating {
+        let mut variance = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in games {
+            let weight = g(opponent.deviation);
+            let expected = self.expected(opponent);
+            variance += weight * weight * expected * (1.0 - expected);
+            improvement += weight * (score - expected);
+        }
+        let d2 = 1.0 / (Q * Q * variance);
+        let precision = 1.0 / self.deviation.powi(2) + 1.0 / d2;
+        Rating {
+            rating: self.rating + Q / precision * improvement,
+            deviation: (1.0 / precision).sqrt(),
+            games: self.games + games.len(),
+        }
+    }
+}
+
+/// Glicko's weight for an opponent whose deviation is `deviation`: the
+/// less certain their rating, the less a result against them counts.
+fn g(deviation: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * Q * Q * deviation * deviation / (PI * PI)).sqrt()
+}
+
+/// # Ratings
+/// The ratings of every strategy that has played a `Tournament`, kept in
+/// a file between runs so changes to a strategy show up as a rise or fall
+/// over time. Each tournament is one Glicko rating period.
+/// Functions:
+/// - `load()`, `save()`, `get()`, `update()`, `ranked()`
+#[derive(Clone, Debug, Default)]
+pub struct Ratings {
+    pub ratings: BTreeMap<String, Rating>,
+}
+
+impl Ratings {
+    /// ### load()
+    /// Read the ratings written by `save()`; a file that doesn't exist
+    /// yet holds no ratings.
+    pub fn load(filename: &str) -> Result<Ratings, Error> {
+        let text = match fs::read_to_string(filename) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Ratings::default()),
+            Err(e) => return Err(Error::in_file("read", filename, e)),
+        };
+        let mut ratings = BTreeMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let failed = || {
+                Error::Parse(format!(
+                    "{}:{}: expected NAME RATING DEVIATION GAMES",
+                    filename,
+                    number + 1
+                ))
+            };
+            match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                [name, rating, deviation, games] => {
+                    let rating = Rating {
+                        rating: rating.parse().map_err(|_| failed())?,
+                        deviation: deviation.parse().map_err(|_| failed())?,
+                        games: games.parse().map_err(|_| failed())?,
+                    };
+                    ratings.insert(name.to_string(), rating);
+                }
+                _ => return Err(failed()),
+            }
+        }
+        Ok(Ratings { ratings })
+    }
+
+    /// ### save()
+    /// Write the ratings for `load()`.
+    pub fn save(&self, filename: &str) -> Result<(), Error> {
+        let mut text = format!("{}\n", RATINGS_HEADER);
+        for (name, rating) in &self.ratings {
+            text.push_str(&format!(
+                "{} {:.1} {:.1} {}\n",
+                name, rating.rating, rating.deviation, rating.games
+            ));
+        }
+        fs::write(filename, text).map_err(|e| Error::in_file("write", filename, e))?;
+        Ok(())
+    }
+
+    /// The rating of `name`, or a new one if it has never played.
+    pub fn get(&self, name: &str) -> Rating {
+        self.ratings.get(name).cloned().unwrap_or_default()
+    }
+
+    /// ### update()
+    /// Rate one tournament: every strategy's rating moves by its results
+    /// against the others' ratings from before the tournament. A win
+    /// counts 1, a tie a half.
+    pub fn update(&mut self, pairings: &[Pairing]) {
+        let before = self.clone();
+        // Each strategy's games: the opponent and the result
+        let mut games: BTreeMap<&str, Vec<(&str, f64)>> = BTreeMap::new();
+        for pairing in pairings {
+            let [first, second] = &pairing.strategies;
+            for result in &pairing.results {
+                let score = match result.winner() {
+                    Some(0) => 1.0,
+                    Some(_) => 0.0,
+                    None => 0.5,
+                };
+                games.entry(first).or_default().push((second, score));
+                games.entry(second).or_default().push((first, 1.0 - score));
+            }
+        }
+
+        for (name, played) in games {
+            let played = played
+                .into_iter()
+                .map(|(opponent, score)| (before.get(opponent), score))
+                .collect::<Vec<(Rating, f64)>>();
+            let rating = before.get(name).aged().rated(&played);
+            self.ratings.insert(name.to_string(), rating);
+        }
+    }
+
+    /// The strategies from highest rated down.
+    pub fn ranked(&self) -> Vec<(&String, &Rating)> {
+        let mut ranked: Vec<(&String, &Rating)> = self.ratings.iter().collect();
+        ranked.sort_by(|a, b| b.1.rating.total_cmp(&a.1.rating));
+        ranked
+    }
+}
+
+/// ## Pairing
+/// The games two strategies played against each other in a tournament.
+#[derive(Clone, Debug)]
+pub struct Pairing {
+    pub strategies: [String; 2],
+    pub results: Vec<GameResult>,
+}
+
+impl Pairing {
+    /// Wins of each strategy; ties are neither.
+    pub fn wins(&self) -> [usize; 2] {
+        [0, 1].map(|s| {
+            self.results
+                .iter()
+                .filter(|r| r.winner() == Some(s))
+                .count()
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "strategies": self.strategies,
+            "games": self.results.len(),
+            "wins": self.wins(),
+        })
+    }
+}
+
+/// # Tournament
+/// A round robin: every strategy plays a `SelfPlay` match of `games`
+/// games against every other. Each pairing uses the same seeds, so every
+/// strategy faces the same racks.
+/// Functions:
+/// - `new()`, `run()`
+pub struct Tournament {
+    pub strategies: Vec<String>,
+    pub games: usize,
+    pub seed: u64,
+    pub limits: SearchLimits,
+    pub threads: usize,
+}
+
+impl Tournament {
+    pub fn new(strategies: Vec<String>, games: usize, seed: u64) -> Tournament {
+        Tournament {
+            strategies,
+            games,
+            seed,
+            limits: SearchLimits::default(),
+            threads: 1,
+        }
+    }
+
+    /// ### run()
+    /// Play every pairing, in the order the strategies were given.
+    pub fn run(&self, tree: &TrieTree) -> Result<Vec<Pairing>, Error> {
+        let mut pairings = Vec::new();
+        for (i, first) in self.strategies.iter().enumerate() {
+            for second in &self.strategies[i + 1..] {
+                let mut harness = SelfPlay::new(first, second, self.games, self.seed);
+                harness.limits = self.limits;
+                harness.threads = self.threads;
+                pairings.push(Pairing {
+                    strategies: [first.clone(), second.clone()],
+                    results: harness.run(tree)?,
+                });
+            }
+        }
+        Ok(pairings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::game::Game;
+    use crate::game::record::Record;
+    use std::time::Duration;
+
+    fn rating(rating: f64, deviation: f64) -> Rating {
+        Rating {
+            rating,
+            deviation,
+            games: 0,
+        }
+    }
+
+    /// A game the strategies scored `scores` in.
+    fn result(scores: [i32; 2]) -> GameResult {
+        GameResult {
+            game: 0,
+            seed: 0,
+            first: 0,
+            scores,
+            bingos: [0; 2],
+            moves: [0; 2],
+            time: [Duration::ZERO; 2],
+            record: Record::from_game(&Game::new(0), ["a", "b"]),
+        }
+    }
+
+    #[test]
+    fn glickmans_example() {
+        // Example from Glickman, "The Glicko system": a win against the
+        // first opponent and losses to the other two
+        let player = rating(1500.0, 200.0);
+        let games = [
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ];
+        let rated = player.rated(&games);
+        assert!((rated.rating - 1464.1).abs() < 0.1, "{}", rated.rating);
+        assert!((rated.deviation - 151.4).abs() < 0.1, "{}", rated.deviation);
+        assert_eq!(rated.games, 3);
+    }
+
+    #[test]
+    fn deviation_grows_between_tournaments_up_to_the_initial_one() {
+        let aged = rating(1600.0, 40.0).aged();
+        assert_eq!(aged.rating, 1600.0);
+        assert!((aged.deviation - 50.0).abs() < 1e-9);
+        assert_eq!(rating(1600.0, 349.0).aged().deviation, INITIAL_DEVIATION);
+        assert_eq!(Rating::default().aged(), Rating::default());
+    }
+
+    #[test]
+    fn update_rates_both_sides_of_a_pairing() {
+        let mut ratings = Ratings::default();
+        ratings.update(&[Pairing {
+            strategies: ["equity".to_string(), "greedy".to_string()],
+            results: vec![result([400, 350]), result([380, 380])],
+        }]);
+        let (winner, loser) = (ratings.get("equity"), ratings.get("greedy"));
+        assert!(winner.rating > INITIAL_RATING);
+        assert!((winner.rating - INITIAL_RATING - (INITIAL_RATING - loser.rating)).abs() < 1e-9);
+        assert!(winner.deviation < INITIAL_DEVIATION);
+        assert_eq!((winner.games, loser.games), (2, 2));
+        assert_eq!(ratings.ranked()[0].0, "equity");
+    }
+}
@@ -0,0 +1,144 @@
+use crate::game::game::{Action, ChallengeRule, Game};
+use crate::utils::error::Error;
+use crate::utils::trieguy::TrieTree;
+
+/// ## RecordedTurn
+/// One move of a record with the annotation written after it, if any.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedTurn {
+    pub action: Action,
+    pub note: Option<String>,
+}
+
+/// # Record
+/// A game written down so it can be replayed: the seed of the bag, the
+/// players' names and every move in order. Since the bag is seeded, the
+/// racks and draws follow from the moves and need not be stored.
+///
+/// As text, a record is a `seed` line, optional `players` and `rule` (the
+/// `ChallengeRule`) lines and one move per line, `8H QUIZ`, `-QV` for an
+/// exchange, `pass` or `challenge`. Anything after a `#` is an annotation
+/// of that move:
+///
+/// ```text
+/// seed 42
+/// players Ann Bob
+/// 8D ZAX  # the Z on a double letter
+/// -UUV
+/// ```
+/// Functions:
+/// - `from_game()`, `parse()`, `to_text()`, `replay()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub seed: u64,
+    pub players: [String; 2],
+    pub challenge_rule: ChallengeRule,
+    pub turns: Vec<RecordedTurn>,
+}
+
+impl Record {
+    /// ### from_game()
+    /// The record of `game` so far, without annotations.
+    pub fn from_game(game: &Game, players: [&str; 2]) -> Record {
+        Record {
+            seed: game.seed,
+            players: players.map(|name| name.to_string()),
+            challenge_rule: game.challenge_rule,
+            turns: game
+                .history
+                .iter()
+                .map(|turn| RecordedTurn {
+                    action: turn.action.clone(),
+                    note: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// ### parse()
+    /// Read a record written by `to_text()` or by hand. Blank lines and
+    /// lines that are only a `#` comment are skipped.
+    pub fn parse(text: &str) -> Result<Record, Error> {
+        let mut seed = None;
+        let mut players = ["Player 1".to_string(), "Player 2".to_string()];
+        let mut challenge_rule = ChallengeRule::Void;
+        let mut turns = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let failed =
+                |message: String| Error::Parse(format!("line {}: {}", number + 1, message));
+            let (line, note) = match line.split_once('#') {
+                Some((line, note)) => (line.trim(), Some(note.trim().to_string())),
+                None => (line.trim(), None),
+            };
+            if line.is_empty() {
+                continue;
+            }
+            match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                ["seed", value] => {
+                    seed = Some(value.parse().map_err(|_| {
+                        failed(format!(
+                            "the seed must be a whole number, got \"{}\"",
+                            value
+                        ))
+                    })?)
+                }
+                ["players", first, second] => {
+                    players = [first.to_string(), second.to_string()];
+                }
+                ["rule", name] => {
+                    challenge_rule =
+                        ChallengeRule::parse(name).map_err(|e| failed(e.to_string()))?
+                }
+                _ => turns.push(RecordedTurn {
+                    action: Action::parse(line).map_err(|e| failed(e.to_string()))?,
+                    note: note.filter(|note| !note.is_empty()),
+                }),
+            }
+        }
+        let seed = seed.ok_or_else(|| Error::Parse("the record has no seed line".to_string()))?;
+        Ok(Record {
+            seed,
+            players,
+            challenge_rule,
+            turns,
+        })
+    }
+
+    /// ### to_text()
+    /// The record in the format read by `parse()`.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "seed {}\nplayers {} {}\n",
+            self.seed, self.players[0], self.players[1]
+        );
+        if self.challenge_rule != ChallengeRule::Void {
+            text.push_str(&format!("rule {}\n", self.challenge_rule.name()));
+        }
+        for turn in &self.turns {
+            match &turn.note {
+                Some(note) => text.push_str(&format!("{}  # {}\n", turn.action.notation(), note)),
+                None => text.push_str(&format!("{}\n", turn.action.notation())),
+            }
+        }
+        text
+    }
+
+    /// ### replay()
+    /// Play the game again from its seed, checking every move. Fails on
+    /// the first move that is not legal, naming it.
+    pub fn replay(&self, tree: &TrieTree) -> Result<Game, Error> {
+        let mut game = Game::new(self.seed);
+        game.challenge_rule = self.challenge_rule;
+        for (i, turn) in self.turns.iter().enumerate() {
+            game.apply(&turn.action, tree).map_err(|e| {
+                Error::IllegalMove(format!(
+                    "move {} ({}): {}",
+                    i + 1,
+                    turn.action.notation(),
+                    e
+                ))
+            })?;
+        }
+        Ok(game)
+    }
+}
@@ -0,0 +1,349 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::agent::controller::SearchLimits;
+use crate::agent::strategy::{self, Strategy};
+use crate::game::game::{Action, ChallengeRule, Game};
+use crate::game::record::Record;
+use crate::utils::error::Error;
+use crate::utils::trieguy::TrieTree;
+
+/// The first line of a CSV of `GameResult::csv_row()` lines; `a` and `b`
+/// are the two strategies in the order they were given.
+pub const CSV_HEADER: &str = "game,seed,first,strategy_a,strategy_b,score_a,score_b,winner,\
+bingos_a,bingos_b,moves_a,moves_b,ms_a,ms_b";
+
+/// ## GameResult
+/// How one self-play game went, with each array holding the strategy in
+/// `SelfPlay::strategies` order, whichever of them moved first.
+#[derive(Clone, Debug)]
+pub struct GameResult {
+    pub game: usize,
+    pub seed: u64,
+    /// Which strategy moved first, `0` or `1`
+    pub first: usize,
+    pub scores: [i32; 2],
+    pub bingos: [usize; 2],
+    pub moves: [usize; 2],
+    /// Time spent choosing moves
+    pub time: [Duration; 2],
+    /// The game itself, players named by strategy in seat order
+    pub record: Record,
+}
+
+impl GameResult {
+    /// The strategy with the higher score, or `None` for a tie.
+    pub fn winner(&self) -> Option<usize> {
+        match self.scores[0].cmp(&self.scores[1]) {
+            std::cmp::Ordering::Greater => Some(0),
+            std::cmp::Ordering::Less => Some(1),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    /// ### csv_row()
+    /// The game as a line under `CSV_HEADER`, strategies named by `names`.
+    pub fn csv_row(&self, names: &[String; 2]) -> String {
+        let winner = self.winner().map_or("tie", |w| ["a", "b"][w]);
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{:.1},{:.1}",
+            self.game,
+            self.seed,
+            names[self.first],
+            names[0],
+            names[1],
+            self.scores[0],
+            self.scores[1],
+            winner,
+            self.bingos[0],
+            self.bingos[1],
+            self.moves[0],
+            self.moves[1],
+            self.time[0].as_secs_f64() * 1000.0,
+            self.time[1].as_secs_f64() * 1000.0
+        )
+    }
+
+    /// ### to_json()
+    /// The game with every array in strategy order, `first` and `winner`
+    /// being indexes into `strategies`.
+    pub fn to_json(&self, names: &[String; 2]) -> Value {
+        json!({
+            "game": self.game,
+            "seed": self.seed,
+            "strategies": names,
+            "first": self.first,
+            "scores": self.scores,
+            "winner": self.winner(),
+            "bingos": self.bingos,
+            "moves": self.moves,
+            "ms": [
+                self.time[0].as_secs_f64() * 1000.0,
+                self.time[1].as_secs_f64() * 1000.0,
+            ],
+        })
+    }
+}
+
+/// ## Summary
+/// The totals of one strategy over a self-play match.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    pub name: String,
+    pub games: usize,
+    pub wins: usize,
+    pub ties: usize,
+    pub average_score: f64,
+    pub average_spread: f64,
+    pub bingos_per_game: f64,
+    pub ms_per_move: f64,
+}
+
+impl Summary {
+    /// Wins plus half the ties, as a fraction of the games.
+    pub fn win_rate(&self) -> f64 {
+        (self.wins as f64 + self.ties as f64 / 2.0) / self.games.max(1) as f64
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "strategy": self.name,
+            "games": self.games,
+            "wins": self.wins,
+            "ties": self.ties,
+            "win_rate": self.win_rate(),
+            "average_score": self.average_score,
+            "average_spread": self.average_spread,
+            "bingos_per_game": self.bingos_per_game,
+            "ms_per_move": self.ms_per_move,
+        })
+    }
+}
+
+/// # SelfPlay
+/// Plays two strategies against each other for many full games without
+/// anyone watching. Game `i` is shuffled by `seed + i` and the strategies
+/// take turns moving first, so a match is repeatable from its seed and
+/// neither side gets the first move more often. Games run in parallel on
+/// `threads` threads, each strategy searching on one.
+/// Functions:
+/// - `new()`, `run()`, `summarize()`
+pub struct SelfPlay {
+    pub strategies: [String; 2],
+    pub games: usize,
+    pub seed: u64,
+    pub limits: SearchLimits,
+    pub threads: usize,
+    pub challenge_rule: ChallengeRule,
+}
+
+impl SelfPlay {
+    /// A match of `games` games between the strategies named `first` and
+    /// `second`, unlimited searches and one thread.
+    pub fn new(first: &str, second: &str, games: usize, seed: u64) -> SelfPlay {
+        SelfPlay {
+            strategies: [first.to_string(), second.to_string()],
+            games,
+            seed,
+            limits: SearchLimits::default(),
+            threads: 1,
+            challenge_rule: ChallengeRule::Void,
+        }
+    }
+
+    /// ### run()
+    /// Play every game and return the results in game order. Fails before
+    /// playing if a strategy name is unknown.
+    pub fn run(&self, tree: &TrieTree) -> Result<Vec<GameResult>, Error> {
+        for name in &self.strategies {
+            strategy::by_name(name, self.limits, 1, self.seed)?;
+        }
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(self.games));
+        thread::scope(|scope| {
+            for _ in 0..self.threads.clamp(1, self.games.max(1)) {
+                scope.spawn(|| loop {
+                    let game = next.fetch_add(1, Ordering::Relaxed);
+                    if game >= self.games {
+                        break;
+                    }
+                    let result = self.play(game, tree);
+                    if let Ok(mut results) = results.lock() {
+                        results.push(result);
+                    }
+                });
+            }
+        });
+        let mut results = results
+            .into_inner()
+            .map_err(|_| Error::IllegalMove("a self-play game panicked".to_string()))?
+            .into_iter()
+            .collect::<Result<Vec<GameResult>, Error>>()?;
+        results.sort_by_key(|result| result.game);
+        Ok(results)
+    }
+
+    /// Play game number `game` to the end.
+    fn play(&self, game: usize, tree: &TrieTree) -> Result<GameResult, Error> {
+        let seed = self.seed.wrapping_add(game as u64);
+        let first = game % 2;
+        // Seat 0 moves first; `seats[s]` is the strategy sitting there
+        let seats = [first, 1 - first];
+        let mut players = seats
+            .iter()
+            .map(|&s| strategy::by_name(&self.strategies[s], self.limits, 1, seed))
+            .collect::<Result<Vec<Box<dyn Strategy>>, Error>>()?;
+        let mut state = Game::new(seed);
+        state.challenge_rule = self.challenge_rule;
+        let mut time = [Duration::ZERO; 2];
+        let mut moves = [0; 2];
+
+        loop {
+            let seat = state.turn;
+            if state.can_challenge() && players[seat].challenge(&state, tree) {
+                let _ = state.challenge(tree);
+                continue;
+            }
+            if state.is_over() {
+                break;
+            }
+            let start = Instant::now();
+            let action = players[seat].choose(&state, tree);
+            time[seats[seat]] += start.elapsed();
+            moves[seats[seat]] += 1;
+            if state.apply(&action, tree).is_err() && state.pass().is_err() {
+                break;
+            }
+        }
+
+        let mut bingos = [0; 2];
+        for turn in &state.history {
+            if let Action::Play(play) = &turn.action {
+                if play.tiles.chars().count() == 7 && !turn.withdrawn {
+                    bingos[seats[turn.player]] += 1;
+                }
+            }
+        }
+        let names = seats.map(|s| self.strategies[s].as_str());
+        let record = Record::from_game(&state, names);
+        let mut scores = [0; 2];
+        for (seat, &s) in seats.iter().enumerate() {
+            scores[s] = state.scores[seat];
+        }
+        Ok(GameResult {
+            game: game + 1,
+            seed,
+            first,
+            scores,
+            bingos,
+            moves,
+            time,
+            record,
+        })
+    }
+
+    /// ### summarize()
+    /// Each strategy's totals over `results`.
+    pub fn summarize(&self, results: &[GameResult]) -> [Summary; 2] {
+        [0, 1].map(|s| {
+            let games = results.len().max(1) as f64;
+            let moves: usize = results.iter().map(|r| r.moves[s]).sum();
+            let time: Duration = results.iter().map(|r| r.time[s]).sum();
+            Summary {
+                name: self.strategies[s].clone(),
+                games: results.len(),
+                wins: results.iter().filter(|r| r.winner() == Some(s)).count(),
+                ties: results.iter().filter(|r| r.winner().is_none()).count(),
+                average_score: results.iter().map(|r| r.scores[s] as f64).sum::<f64>() / games,
+                average_spread: results
+                    .iter()
+                    .map(|r| (r.scores[s] - r.scores[1 - s]) as f64)
+                    .sum::<f64>()
+                    / games,
+                bingos_per_game: results.iter().map(|r| r.bingos[s] as f64).sum::<f64>() / games,
+                ms_per_move: time.as_secs_f64() * 1000.0 / moves.max(1) as f64,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Game `game` of a match, moved first by strategy `first`, where the
+    /// strategies scored `scores` and spent `ms` milliseconds on each of
+    /// their ten moves.
+    fn result(
+        game: usize,
+        first: usize,
+        scores: [i32; 2],
+        bingos: [usize; 2],
+        ms: u64,
+    ) -> GameResult {
+        GameResult {
+            game,
+            seed: game as u64,
+            first,
+            scores,
+            bingos,
+            moves: [10; 2],
+            time: [Duration::from_millis(10 * ms); 2],
+            record: Record::from_game(&Game::new(0), ["a", "b"]),
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn summaries_count_wins_ties_and_averages() {
+        let selfplay = SelfPlay::new("equity", "greedy", 4, 1);
+        let results = [
+            result(1, 0, [400, 300], [1, 0], 2),
+            result(2, 1, [350, 350], [0, 0], 2),
+            result(3, 0, [300, 420], [0, 2], 2),
+            result(4, 1, [410, 330], [1, 0], 2),
+        ];
+        let [a, b] = selfplay.summarize(&results);
+        assert_eq!((a.name.as_str(), b.name.as_str()), ("equity", "greedy"));
+        assert_eq!((a.games, a.wins, a.ties), (4, 2, 1));
+        assert_eq!((b.games, b.wins, b.ties), (4, 1, 1));
+        assert_close(a.win_rate(), 2.5 / 4.0);
+        assert_close(b.win_rate(), 1.5 / 4.0);
+        assert_close(a.average_score, 1460.0 / 4.0);
+        assert_close(a.average_spread, 60.0 / 4.0);
+        assert_close(b.average_spread, -60.0 / 4.0);
+        assert_close(b.bingos_per_game, 0.5);
+        assert_close(a.ms_per_move, 2.0);
+    }
+
+    #[test]
+    fn no_games_is_no_wins() {
+        let [a, _] = SelfPlay::new("equity", "greedy", 0, 1).summarize(&[]);
+        assert_eq!(a.games, 0);
+        assert_close(a.win_rate(), 0.0);
+    }
+
+    #[test]
+    fn csv_rows_fill_every_column() {
+        let names = ["equity".to_string(), "greedy".to_string()];
+        let columns = CSV_HEADER.split(',').count();
+        let won = result(3, 1, [300, 420], [0, 2], 2).csv_row(&names);
+        let tied = result(2, 0, [350, 350], [0, 0], 2).csv_row(&names);
+        assert_eq!(won.split(',').count(), columns);
+        assert_eq!(tied.split(',').count(), columns);
+        assert!(won.starts_with("3,3,greedy,equity,greedy,300,420,b,0,2,"));
+        assert!(tied.contains(",tie,"));
+    }
+}
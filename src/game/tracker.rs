@@ -0,0 +1,233 @@
+use crate::agent::bag::Bag;
+use crate::board::position::{Position, SIZE};
+use crate::game::game::{Game, RACK_SIZE};
+use crate::utils::error::Error;
+
+/// # TileTracker
+/// What's left, from one player's side of the board: every tile not on the
+/// board or on their own rack is unseen, somewhere in the bag or on the
+/// opponent's rack. Not knowing which, the player draws from the bag as if
+/// from all the unseen tiles, so the chance of a draw follows from the
+/// unseen counts alone.
+/// Functions:
+/// - `new()`, `from_game()`, `count()`, `counts()`, `vowels()`,
+///   `probability()`, `expected()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileTracker {
+    /// The unseen tiles, wildcards (`_`) last
+    pub unseen: Vec<char>,
+    /// How many of them are in the bag
+    pub in_bag: usize,
+}
+
+impl TileTracker {
+    /// ### new()
+    /// The tiles unseen by the player holding `rack` with `position` on the
+    /// board. The opponent is taken to hold a full rack, if that many tiles
+    /// are unseen, and the rest to be in the bag.
+    pub fn new(position: &Position, rack: &[char]) -> TileTracker {
+        let mut unseen = Bag::new().tiles().to_vec();
+        let on_board = (0..SIZE)
+            .flat_map(|row| (0..SIZE).map(move |col| (row, col)))
+            .filter_map(|(row, col)| position.get(row, col));
+        for tile in on_board.chain(rack.iter().cloned()) {
+            let tile = if tile.is_ascii_lowercase() { '_' } else { tile };
+            if let Some(i) = unseen.iter().position(|t| *t == tile) {
+                unseen.swap_remove(i);
+            }
+        }
+        unseen.sort_by_key(|tile| (*tile == '_', *tile));
+        let in_bag = unseen.len().saturating_sub(RACK_SIZE);
+        TileTracker { unseen, in_bag }
+    }
+
+    /// ### from_game()
+    /// The tiles unseen by `player` in `game`, with the bag as it is.
+    pub fn from_game(game: &Game, player: usize) -> TileTracker {
+        TileTracker {
+            in_bag: game.bag.len(),
+            ..TileTracker::new(&game.position, &game.racks[player].letters)
+        }
+    }
+
+    /// How many `tile`s are unseen; `?` or `_` asks for wildcards.
+    pub fn count(&self, tile: char) -> usize {
+        let tile = tile_of(tile).unwrap_or(tile);
+        self.unseen.iter().filter(|t| **t == tile).count()
+    }
+
+    /// Each unseen tile with how many are left, `A` to `Z` then wildcards.
+    pub fn counts(&self) -> Vec<(char, usize)> {
+        let mut counts: Vec<(char, usize)> = Vec::new();
+        for &tile in &self.unseen {
+            match counts.last_mut() {
+                Some((last, count)) if *last == tile => *count += 1,
+                _ => counts.push((tile, 1)),
+            }
+        }
+        counts
+    }
+
+    /// How many unseen tiles are vowels; the rest are consonants or
+    /// wildcards.
+    pub fn vowels(&self) -> usize {
+        self.unseen.iter().filter(|t| "AEIOU".contains(**t)).count()
+    }
+
+    /// ### probability()
+    /// The chance that drawing `drawn` tiles brings all of `tiles`, e.g. `S`
+    /// or `QU`, repeats meaning that many. No more tiles are drawn than the
+    /// bag holds.
+    pub fn probability(&self, tiles: &str, drawn: usize) -> Result<f64, Error> {
+        let wanted = parse_tiles(tiles)?;
+        let drawn = drawn.min(self.in_bag);
+        // Each tile wanted: how many are needed and how many are unseen
+        let mut needs: Vec<(usize, usize)> = Vec::new();
+        let mut distinct = wanted.clone();
+        distinct.dedup();
+        for tile in distinct {
+            let need = wanted.iter().filter(|t| **t == tile).count();
+            needs.push((need, self.count(tile)));
+        }
+        let total = self.unseen.len();
+        let others = total - needs.iter().map(|(_, have)| have).sum::<usize>();
+        Ok(ways(&needs, others, drawn) / binomial(total, drawn))
+    }
+
+    /// ### expected()
+    /// How many `tile`s drawing `drawn` tiles brings on average.
+    pub fn expected(&self, tile: char, drawn: usize) -> f64 {
+        match self.unseen.len() {
+            0 => 0.0,
+            total => (drawn.min(self.in_bag) * self.count(tile)) as f64 / total as f64,
+        }
+    }
+}
+
+/// The draws of `drawn` tiles that hold at least `need` of each wanted
+/// tile, of which `have` are unseen, and any of the `others`.
+fn ways(needs: &[(usize, usize)], others: usize, drawn: usize) -> f64 {
+    match needs.split_first() {
+        None => binomial(others, drawn),
+        Some((&(need, have), rest)) => (need..=have.min(drawn))
+            .map(|k| binomial(have, k) * ways(rest, others, drawn - k))
+            .fold(0.0, |sum, ways| sum + ways),
+    }
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    (0..k.min(n - k)).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+/// The tile `c` stands for: a letter in either case, or `?` or `_` for a
+/// wildcard.
+fn tile_of(c: char) -> Option<char> {
+    match c {
+        '?' | '_' => Some('_'),
+        c if c.is_ascii_alphabetic() => Some(c.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+/// `tiles` as sorted tiles, failing on anything that isn't one.
+fn parse_tiles(tiles: &str) -> Result<Vec<char>, Error> {
+    let mut parsed = tiles
+        .chars()
+        .map(|c| {
+            tile_of(c)
+                .ok_or_else(|| Error::Parse(format!("'{}' is not a tile in \"{}\"", c, tiles)))
+        })
+        .collect::<Result<Vec<char>, Error>>()?;
+    parsed.sort_unstable();
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(unseen: &str, in_bag: usize) -> TileTracker {
+        TileTracker {
+            unseen: unseen.chars().collect(),
+            in_bag,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn a_single_tile_left_is_certain_or_impossible() {
+        let last = tracker("Q", 1);
+        assert_close(last.probability("Q", 1).unwrap(), 1.0);
+        assert_close(last.probability("Z", 1).unwrap(), 0.0);
+        assert_close(last.probability("QQ", 1).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn one_of_n_distinct_tiles() {
+        let tiles = tracker("ABCDE", 5);
+        assert_close(tiles.probability("A", 1).unwrap(), 1.0 / 5.0);
+        assert_close(tiles.probability("A", 2).unwrap(), 2.0 / 5.0);
+        // One pair of the ten two-tile draws
+        assert_close(tiles.probability("AB", 2).unwrap(), 1.0 / 10.0);
+        assert_close(tiles.probability("", 2).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn repeats_need_that_many() {
+        // Both E's in two draws from EEX: one draw of three
+        let tiles = tracker("EEX", 3);
+        assert_close(tiles.probability("EE", 2).unwrap(), 1.0 / 3.0);
+        assert_close(tiles.probability("E", 2).unwrap(), 1.0);
+        assert_close(tiles.probability("e", 1).unwrap(), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn no_more_is_drawn_than_the_bag_holds() {
+        let tiles = tracker("ABCD", 1);
+        assert_close(tiles.probability("A", 7).unwrap(), 1.0 / 4.0);
+        assert_close(tiles.expected('A', 7), 1.0 / 4.0);
+    }
+
+    #[test]
+    fn wildcards_and_bad_tiles() {
+        let tiles = tracker("AB_", 3);
+        assert_close(tiles.probability("?", 1).unwrap(), 1.0 / 3.0);
+        assert_close(tiles.probability("_", 1).unwrap(), 1.0 / 3.0);
+        assert!(tiles.probability("A1", 1).is_err());
+    }
+
+    #[test]
+    fn an_empty_board_leaves_a_full_set_unseen() {
+        let tracker = TileTracker::new(&Position::new(), &[]);
+        assert_eq!((tracker.unseen.len(), tracker.in_bag), (100, 93));
+        assert_eq!((tracker.count('Q'), tracker.count('U')), (1, 4));
+        assert_eq!(tracker.count('?'), 2);
+        assert_eq!(tracker.counts().last(), Some(&('_', 2)));
+        assert_close(tracker.probability("Q", 7).unwrap(), 7.0 / 100.0);
+    }
+
+    #[test]
+    fn the_board_and_rack_are_seen() {
+        // QAT on row 8, the A a wildcard
+        let empty = ["15"; 7].join("/");
+        let position = Position::parse(&format!("{}/7QaT5/{}", empty, empty)).unwrap();
+        let tracker = TileTracker::new(&position, &['S', '_']);
+        assert_eq!(tracker.unseen.len(), 95);
+        assert_eq!(
+            (tracker.count('Q'), tracker.count('S'), tracker.count('_')),
+            (0, 3, 0)
+        );
+        assert_close(tracker.probability("Q", 7).unwrap(), 0.0);
+    }
+}
@@ -0,0 +1,104 @@
+//! # scrabble
+//! A Scrabble solver as a library: the lexicon and its trie, racks and
+//! the tile bag, the 15x15 board with scoring, move generation and the
+//! search that picks the best play. The `scrabble` binary is a thin
+//! command line wrapper over this crate.
+//!
+//! ```no_run
+//! use scrabble::{Lexicon, MoveGenerator, Position, Rack};
+//!
+//! let lexicon = Lexicon::load("dictionary.txt").unwrap();
+//! let position = Position::new();
+//! let rack = Rack::from_letters("QUIZ_ER").unwrap();
+//! let plays = MoveGenerator::new(&position, &rack, &lexicon.trie).generate();
+//! println!("{}", plays[0].notation());
+//! ```
+
+#[allow(clippy::module_inception)]
+pub mod agent {
+    pub mod agent;
+    pub mod bag;
+    pub mod controller;
+    pub mod inference;
+    pub mod leaves;
+    pub mod movegen;
+    pub mod rack;
+    pub mod scrabble_move;
+    pub mod strategy;
+}
+
+#[allow(clippy::module_inception)]
+pub mod anagrams {
+    pub mod alphagram;
+    pub mod anagrams;
+}
+
+#[allow(clippy::module_inception)]
+pub mod board {
+    pub mod board;
+    pub mod play;
+    pub mod position;
+    pub mod svg;
+}
+
+pub mod engine {
+    pub mod protocol;
+}
+
+#[allow(clippy::module_inception)]
+pub mod game {
+    pub mod game;
+    pub mod html;
+    pub mod rating;
+    pub mod record;
+    pub mod selfplay;
+    pub mod tracker;
+}
+
+#[allow(clippy::module_inception)]
+pub mod server {
+    pub mod server;
+}
+
+#[allow(clippy::module_inception)]
+pub mod search {
+    pub mod search;
+}
+
+pub mod utils {
+    pub mod adjudicator;
+    pub mod error;
+    pub mod lexicon;
+    pub mod trieguy;
+    pub mod weighted_group;
+    pub mod word_check;
+}
+
+pub use agent::agent::Agent;
+pub use agent::bag::Bag;
+pub use agent::controller::{
+    sort_moves, CancelHandle, Candidate, Progress, SearchController, SearchLimits, SearchResult,
+    StopReason,
+};
+pub use agent::inference::RackInference;
+pub use agent::leaves::LeaveTable;
+pub use agent::movegen::MoveGenerator;
+pub use agent::rack::Rack;
+pub use agent::scrabble_move::ScrabbleMove;
+pub use agent::strategy::Strategy;
+pub use anagrams::alphagram::AlphagramIndex;
+pub use anagrams::anagrams::{Anagram, Anagrams};
+pub use board::board::Board;
+pub use board::play::{Direction, Play};
+pub use board::position::{Position, Premium};
+pub use engine::protocol::Engine;
+pub use game::game::{Action, Game, Turn};
+pub use game::rating::{Ratings, Tournament};
+pub use game::record::{Record, RecordedTurn};
+pub use game::selfplay::SelfPlay;
+pub use game::tracker::TileTracker;
+pub use server::server::Server;
+pub use utils::error::Error;
+pub use utils::lexicon::Lexicon;
+pub use utils::trieguy::TrieTree;
+pub use utils::weighted_group::WeightedGroups;
@@ -1,26 +1,31 @@
-mod agent {
-    pub(crate) mod agent;
-    pub(crate) mod rack;
-    pub(crate) mod scrabble_move;
+#[allow(clippy::module_inception)]
+mod cli {
+    pub(crate) mod cli;
+    pub(crate) mod commands;
+    pub(crate) mod repl;
+    pub(crate) mod tui;
 }
 
-mod anagrams {
-    pub(crate) mod anagrams;
-}
-
-mod board {
-    pub(crate) mod board;
-}
-
-mod utils {
-    pub(crate) mod trieguy;
-    pub(crate) mod weighted_group;
-}
+use std::process;
 
-use crate::agent::agent::Agent;
+use crate::cli::cli::Cli;
+use crate::cli::commands;
 
 fn main() {
-    let mut agent = Agent::new();
-    println!("\n\n===========SCRABBLE===========\n\n");
-    agent.solve();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cli = match Cli::parse(&args) {
+        Ok(cli) => cli,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            eprintln!("\nRun `scrabble help` for usage.");
+            process::exit(commands::EXIT_ERROR);
+        }
+    };
+    match commands::run(&cli) {
+        Ok(status) => process::exit(status),
+        Err(message) => {
+            eprintln!("error: {}", message);
+            process::exit(commands::EXIT_ERROR);
+        }
+    }
 }
@@ -0,0 +1,513 @@
+use crate::utils::error::Error;
+use crate::utils::trieguy::{TrieNode, TrieTree};
+
+/// Every letter A-Z, as a bit mask.
+const ALL_LETTERS: u32 = (1 << 26) - 1;
+
+fn bit(letter: char) -> u32 {
+    1 << (letter as u8 - b'A')
+}
+
+fn index(letter: char) -> usize {
+    (letter as u8 - b'A') as usize
+}
+
+/// ## Token
+/// One element of a compiled `Pattern`: either exactly one letter out of
+/// a set, or any number (including zero) of letters out of a set.
+/// Sets are bit masks with bit 0 for `A`.
+#[derive(Clone, Copy, Debug)]
+enum Token {
+    One(u32),
+    Many(u32),
+}
+
+/// # Pattern
+/// A word pattern such as `?A?E*` or `[^AEIOU]*ING`:
+/// - `A`-`Z` match that letter (case-insensitive)
+/// - `?` or `.` match any single letter
+/// - `*` matches zero or more letters
+/// - `[ABC]` matches one letter from the class, `[A-F]` a range and
+///   `[^ABC]` any letter not in the class
+///
+/// A pattern is run as a small NFA whose state is the set of tokens
+/// matched so far, so it can be advanced one letter at a time while
+/// walking the `TrieTree`.
+pub struct Pattern {
+    tokens: Vec<Token>,
+}
+
+impl Pattern {
+    /// ### parse()
+    /// Compile a pattern, reporting the position of any syntax error.
+    pub fn parse(pattern: &str) -> Result<Pattern, Error> {
+        let chars: Vec<char> = pattern.to_uppercase().chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '?' | '.' => tokens.push(Token::One(ALL_LETTERS)),
+                '*' => tokens.push(Token::Many(ALL_LETTERS)),
+                '[' => {
+                    let close = chars[i..].iter().position(|c| *c == ']').ok_or_else(|| {
+                        Error::Parse(format!("unclosed '[' at position {} in {}", i + 1, pattern))
+                    })?;
+                    tokens.push(Token::One(Pattern::parse_class(
+                        &chars[i + 1..i + close],
+                        pattern,
+                    )?));
+                    i += close;
+                }
+                c if c.is_ascii_uppercase() => tokens.push(Token::One(bit(c))),
+                c => {
+                    return Err(Error::Parse(format!(
+                        "unexpected '{}' at position {} in {}",
+                        c,
+                        i + 1,
+                        pattern
+                    )))
+                }
+            }
+            i += 1;
+        }
+        // The state set is a u64 with one bit per token plus the final state
+        if tokens.len() > 63 {
+            return Err(Error::Parse(format!("pattern {} is too long", pattern)));
+        }
+        Ok(Pattern { tokens })
+    }
+
+    /// ### parse_class()
+    /// The letters between `[` and `]`, with ranges and negation.
+    fn parse_class(class: &[char], pattern: &str) -> Result<u32, Error> {
+        let (negated, class) = match class.first() {
+            Some('^') => (true, &class[1..]),
+            _ => (false, class),
+        };
+        let mut mask = 0;
+        let mut i = 0;
+        while i < class.len() {
+            let c = class[i];
+            if !c.is_ascii_uppercase() {
+                return Err(Error::Parse(format!(
+                    "unexpected '{}' in character class of {}",
+                    c, pattern
+                )));
+            }
+            if class.get(i + 1) == Some(&'-') && i + 2 < class.len() {
+                let end = class[i + 2];
+                if !end.is_ascii_uppercase() || end < c {
+                    return Err(Error::Parse(format!(
+                        "bad range {}-{} in {}",
+                        c, end, pattern
+                    )));
+                }
+                for letter in c..=end {
+                    mask |= bit(letter);
+                }
+                i += 3;
+            } else {
+                mask |= bit(c);
+                i += 1;
+            }
+        }
+        if mask == 0 {
+            return Err(Error::Parse(format!(
+                "empty character class in {}",
+                pattern
+            )));
+        }
+        Ok(if negated { ALL_LETTERS & !mask } else { mask })
+    }
+
+    /// The start state, before any letter is read.
+    fn start(&self) -> u64 {
+        self.closure(1)
+    }
+
+    /// Add every state reachable by letting a `*` match nothing.
+    fn closure(&self, mut states: u64) -> u64 {
+        for (i, token) in self.tokens.iter().enumerate() {
+            if states & (1 << i) != 0 {
+                if let Token::Many(_) = token {
+                    states |= 1 << (i + 1);
+                }
+            }
+        }
+        states
+    }
+
+    /// The states reached after reading `letter`; 0 means no match is possible.
+    fn step(&self, states: u64, letter: char) -> u64 {
+        let mut next = 0;
+        for (i, token) in self.tokens.iter().enumerate() {
+            if states & (1 << i) == 0 {
+                continue;
+            }
+            match token {
+                Token::One(mask) if mask & bit(letter) != 0 => next |= 1 << (i + 1),
+                Token::Many(mask) if mask & bit(letter) != 0 => next |= 1 << i,
+                _ => {}
+            }
+        }
+        self.closure(next)
+    }
+
+    fn accepts(&self, states: u64) -> bool {
+        states & (1 << self.tokens.len()) != 0
+    }
+}
+
+/// ## RackMode
+/// How the letters given to a `Query` must be used.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RackMode {
+    /// Every letter must be used
+    Anagram,
+    /// Any subset of the letters may be used
+    Subanagram,
+}
+
+/// # Query
+/// A word search made of any combination of constraints, checked while
+/// walking the `TrieTree` so a branch is abandoned as soon as no word below
+/// it can match. The prefix, pattern and rack prune letter by letter. The
+/// `contains` letters and the suffix prune once the rack or the maximum
+/// length leaves too few letters to supply them, and a rack must hold the
+/// missing `contains` letters; with neither they are checked on whole
+/// words.
+/// Functions:
+/// - `set_rack()`, `set_contains()`, `run()`
+pub struct Query {
+    pub pattern: Option<Pattern>,
+    pub prefix: String,
+    pub suffix: String,
+    pub min_length: usize,
+    pub max_length: Option<usize>,
+    rack: Option<(RackMode, [usize; 26], usize)>,
+    contains: [usize; 26],
+}
+
+/// The running state of a search, kept separate from the `Query` so the
+/// recursion only needs one mutable borrow.
+struct Walk {
+    path: String,
+    /// How many of each letter `path` holds
+    have: [usize; 26],
+    rack: [usize; 26],
+    blanks: usize,
+    found: Vec<String>,
+    nodes: usize,
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Query::new()
+    }
+}
+
+impl Query {
+    pub fn new() -> Query {
+        Query {
+            pattern: None,
+            prefix: String::new(),
+            suffix: String::new(),
+            min_length: 1,
+            max_length: None,
+            rack: None,
+            contains: [0; 26],
+        }
+    }
+
+    /// ### set_rack()
+    /// Restrict words to the given letters, where `?` or `_` is a blank.
+    pub fn set_rack(&mut self, letters: &str, mode: RackMode) -> Result<(), Error> {
+        let mut counts = [0; 26];
+        let mut blanks = 0;
+        for c in letters.to_uppercase().chars() {
+            match c {
+                '?' | '_' => blanks += 1,
+                c if c.is_ascii_uppercase() => counts[index(c)] += 1,
+                c => {
+                    return Err(Error::InvalidRack(format!(
+                        "'{}' is not a letter or blank in {}",
+                        c, letters
+                    )))
+                }
+            }
+        }
+        self.rack = Some((mode, counts, blanks));
+        Ok(())
+    }
+
+    /// ### set_contains()
+    /// Only match words that contain all of these letters (with repeats).
+    pub fn set_contains(&mut self, letters: &str) -> Result<(), Error> {
+        let mut counts = [0; 26];
+        for c in letters.to_uppercase().chars() {
+            if !c.is_ascii_uppercase() {
+                return Err(Error::Parse(format!(
+                    "'{}' is not a letter in {}",
+                    c, letters
+                )));
+            }
+            counts[index(c)] += 1;
+        }
+        self.contains = counts;
+        Ok(())
+    }
+
+    /// ### run()
+    /// Walk the tree and return the matching words, shortest first and
+    /// alphabetical within a length, along with the number of trie nodes
+    /// visited.
+    pub fn run(&self, tree: &TrieTree) -> (Vec<String>, usize) {
+        let (rack, blanks) = match self.rack {
+            Some((_, counts, blanks)) => (counts, blanks),
+            None => ([0; 26], 0),
+        };
+        let mut walk = Walk {
+            path: String::new(),
+            have: [0; 26],
+            rack,
+            blanks,
+            found: Vec::new(),
+            nodes: 0,
+        };
+        let states = self.pattern.as_ref().map_or(0, |p| p.start());
+        self.walk(&tree.root, states, &mut walk);
+        walk.found
+            .sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+        (walk.found, walk.nodes)
+    }
+
+    fn walk(&self, node: &TrieNode, states: u64, walk: &mut Walk) {
+        walk.nodes += 1;
+        let depth = walk.path.len();
+        if node.is_end_of_word && self.matches(states, walk) {
+            walk.found.push(walk.path.clone());
+        }
+        if self.max_length.is_some_and(|max| depth >= max) {
+            return;
+        }
+        if let Some((_, rack, blanks)) = &self.rack {
+            if depth >= rack.iter().sum::<usize>() + blanks {
+                return;
+            }
+        }
+        if !self.can_finish(walk) {
+            return;
+        }
+        let prefix = self.prefix.chars().nth(depth);
+
+        for (&letter, child) in &node.children {
+            // A word list may hold entries such as "DON'T" or lowercase
+            // words; no pattern or rack can spell them
+            if !letter.is_ascii_uppercase() {
+                continue;
+            }
+            if prefix.is_some_and(|p| p != letter) {
+                continue;
+            }
+            let next = match &self.pattern {
+                Some(pattern) => {
+                    let next = pattern.step(states, letter);
+                    if next == 0 {
+                        continue;
+                    }
+                    next
+                }
+                None => 0,
+            };
+            // Spend a natural tile before a blank, so each word is found once
+            let mut used_blank = false;
+            if self.rack.is_some() {
+                if walk.rack[index(letter)] > 0 {
+                    walk.rack[index(letter)] -= 1;
+                } else if walk.blanks > 0 {
+                    walk.blanks -= 1;
+                    used_blank = true;
+                } else {
+                    continue;
+                }
+            }
+            walk.path.push(letter);
+            walk.have[index(letter)] += 1;
+            self.walk(child, next, walk);
+            walk.have[index(letter)] -= 1;
+            walk.path.pop();
+            if self.rack.is_some() {
+                if used_blank {
+                    walk.blanks += 1;
+                } else {
+                    walk.rack[index(letter)] += 1;
+                }
+            }
+        }
+    }
+
+    /// Whether the letters still to come can supply the `contains` letters
+    /// missing from the path and finish the suffix: a rack must hold the
+    /// missing letters, blanks making up the rest, and a rack or maximum
+    /// length must leave room for them.
+    fn can_finish(&self, walk: &Walk) -> bool {
+        let missing = |i: usize| self.contains[i].saturating_sub(walk.have[i]);
+        if self.rack.is_some() {
+            let short: usize = (0..26)
+                .map(|i| missing(i).saturating_sub(walk.rack[i]))
+                .sum();
+            if short > walk.blanks {
+                return false;
+            }
+        }
+        let rack_size = self
+            .rack
+            .map(|(_, rack, blanks)| rack.iter().sum::<usize>() + blanks);
+        let Some(room) = [self.max_length, rack_size].into_iter().flatten().min() else {
+            return true;
+        };
+        let left = room.saturating_sub(walk.path.len());
+        // The suffix may already be begun by the end of the path
+        let begun = (0..=self.suffix.len().min(walk.path.len()))
+            .rev()
+            .find(|&k| {
+                self.suffix
+                    .get(..k)
+                    .is_some_and(|start| walk.path.ends_with(start))
+            })
+            .unwrap_or(0);
+        let needed = (0..26).map(missing).sum::<usize>();
+        needed.max(self.suffix.len() - begun) <= left
+    }
+
+    /// The checks that can only be made once a whole word has been read.
+    fn matches(&self, states: u64, walk: &Walk) -> bool {
+        let word = &walk.path;
+        if word.len() < self.min_length || word.len() < self.prefix.len() {
+            return false;
+        }
+        if !word.ends_with(&self.suffix) {
+            return false;
+        }
+        if let Some(pattern) = &self.pattern {
+            if !pattern.accepts(states) {
+                return false;
+            }
+        }
+        if let Some((RackMode::Anagram, _, _)) = self.rack {
+            if walk.blanks > 0 || walk.rack.iter().any(|&c| c > 0) {
+                return false;
+            }
+        }
+        walk.have
+            .iter()
+            .zip(self.contains.iter())
+            .all(|(have, need)| have >= need)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::lexicon::Lexicon;
+
+    fn lexicon(words: &[&str]) -> Lexicon {
+        Lexicon::from_words(words.iter().map(|word| word.to_string()))
+    }
+
+    fn run(query: &Query, lexicon: &Lexicon) -> Vec<String> {
+        query.run(&lexicon.trie).0
+    }
+
+    #[test]
+    fn pattern_skips_entries_that_are_not_letters() {
+        let lexicon = lexicon(&["cat", "DON'T", "CAT", "BAT"]);
+        let mut query = Query::new();
+        query.pattern = Some(Pattern::parse("?A?").unwrap());
+        assert_eq!(run(&query, &lexicon), vec!["BAT", "CAT"]);
+    }
+
+    #[test]
+    fn rack_skips_entries_that_are_not_letters() {
+        let lexicon = lexicon(&["cat", "DON'T", "CAT", "ACT"]);
+        let mut query = Query::new();
+        query.set_rack("TAC", RackMode::Anagram).unwrap();
+        assert_eq!(run(&query, &lexicon), vec!["ACT", "CAT"]);
+    }
+
+    #[test]
+    fn pattern_classes_and_stars() {
+        let lexicon = lexicon(&["SING", "STING", "RING", "ING", "SANG"]);
+        let mut query = Query::new();
+        query.pattern = Some(Pattern::parse("[^AEIOU]*ING").unwrap());
+        assert_eq!(run(&query, &lexicon), vec!["RING", "SING", "STING"]);
+        query.pattern = Some(Pattern::parse("S[A-F]NG").unwrap());
+        assert_eq!(run(&query, &lexicon), vec!["SANG"]);
+    }
+
+    #[test]
+    fn subanagrams_use_blanks_once() {
+        let lexicon = lexicon(&["AT", "TA", "CAT", "CATS", "TACT"]);
+        let mut query = Query::new();
+        query.set_rack("CA?", RackMode::Subanagram).unwrap();
+        assert_eq!(run(&query, &lexicon), vec!["AT", "TA", "CAT"]);
+    }
+
+    #[test]
+    fn long_racks_and_contains_sets_do_not_overflow() {
+        let lexicon = lexicon(&["AA", "AAA", "BAA"]);
+        let mut query = Query::new();
+        query
+            .set_rack(&"A".repeat(300), RackMode::Subanagram)
+            .unwrap();
+        assert_eq!(run(&query, &lexicon), vec!["AA", "AAA"]);
+        let mut query = Query::new();
+        query.set_contains(&"A".repeat(300)).unwrap();
+        assert!(run(&query, &lexicon).is_empty());
+    }
+
+    #[test]
+    fn contains_and_suffix_prune_when_the_length_is_bounded() {
+        let lexicon = lexicon(&["QUIZ", "QUIZZES", "ZAX", "JINX", "SING", "RING", "RINGS"]);
+        let all = Query::new().run(&lexicon.trie).1;
+
+        // Two letters can't hold three wanted ones: only the root is seen
+        let mut query = Query::new();
+        query.set_contains("QIZ").unwrap();
+        query.max_length = Some(2);
+        assert_eq!(query.run(&lexicon.trie), (vec![], 1));
+
+        // A rack without the wanted letter and no blank can't supply it
+        let mut query = Query::new();
+        query.set_contains("Q").unwrap();
+        query.set_rack("SINGR", RackMode::Subanagram).unwrap();
+        assert_eq!(query.run(&lexicon.trie), (vec![], 1));
+
+        let mut query = Query::new();
+        query.set_contains("Z").unwrap();
+        query.max_length = Some(4);
+        let (words, nodes) = query.run(&lexicon.trie);
+        assert_eq!(words, vec!["ZAX", "QUIZ"]);
+        assert!(nodes < all);
+
+        let mut query = Query::new();
+        query.suffix = "ING".to_string();
+        query.max_length = Some(4);
+        let (words, nodes) = query.run(&lexicon.trie);
+        assert_eq!(words, vec!["RING", "SING"]);
+        assert!(nodes < all);
+
+        // Unbounded, the same words come out from a full walk
+        query.max_length = None;
+        assert_eq!(
+            query.run(&lexicon.trie),
+            (vec!["RING".to_string(), "SING".to_string()], all)
+        );
+    }
+
+    #[test]
+    fn bad_patterns_are_errors() {
+        assert!(Pattern::parse("[AB").is_err());
+        assert!(Pattern::parse("A1").is_err());
+    }
+}
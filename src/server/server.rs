@@ -0,0 +1,413 @@
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::agent::controller::{SearchController, SearchLimits};
+use crate::agent::rack::Rack;
+use crate::board::play::Play;
+use crate::board::position::Position;
+use crate::utils::adjudicator::Adjudicator;
+use crate::utils::lexicon::Lexicon;
+use crate::utils::weighted_group::WeightedGroups;
+use crate::utils::word_check::PlayJudgement;
+
+/// How many requests are answered at the same time.
+pub const WORKERS: usize = 4;
+
+/// # Server
+/// A local HTTP server answering JSON requests from scripts and web
+/// pages. The `Lexicon` is loaded once and shared by every request, so
+/// only the first start pays for building the `TrieTree`.
+///
+/// Every endpoint takes its parameters from the query string or from a
+/// JSON object in the request body, and answers with JSON; failures are
+/// `{"error": "..."}` with status 400, or 404 for an unknown path.
+///
+/// | Endpoint | Parameters |
+/// | --- | --- |
+/// | `GET /health` | |
+/// | `/check` | `words` (list or comma separated), `play` (verdict only) |
+/// | `/anagram` | `letters`, `min` [2], `exact` |
+/// | `/analyze` | `rack`, `board`, `moves`, `top` [10], `time_ms`, `nodes` |
+/// | `/replay` | `moves`, `board`, `players` [2] |
+/// Functions:
+/// - `new()`, `serve()`
+pub struct Server {
+    lexicon: Lexicon,
+    threads: usize,
+    values: WeightedGroups,
+}
+
+/// A failed request: the status code and the message.
+type Failure = (u16, String);
+
+impl Server {
+    /// Answer requests with `lexicon`, using `threads` threads to analyze
+    /// a position.
+    pub fn new(lexicon: Lexicon, threads: usize) -> Server {
+        Server {
+            lexicon,
+            threads,
+            values: WeightedGroups::new(),
+        }
+    }
+
+    /// ### serve()
+    /// Listen on `address` (e.g. `127.0.0.1:8080`) and answer requests
+    /// on `WORKERS` threads until the process is stopped.
+    pub fn serve(&self, address: &str) -> Result<(), String> {
+        let http = tiny_http::Server::http(address)
+            .map_err(|e| format!("could not listen on {}: {}", address, e))?;
+        thread::scope(|scope| {
+            for _ in 0..WORKERS {
+                scope.spawn(|| {
+                    while let Ok(request) = http.recv() {
+                        self.respond(request);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    /// Route one request and send back its JSON.
+    fn respond(&self, mut request: Request) {
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let mut body = String::new();
+        let result = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => Params::new(query, &body).and_then(|params| match (request.method(), path) {
+                (Method::Get, "/health") => Ok(json!({ "status": "ok" })),
+                (Method::Get | Method::Post, "/check") => self.check(&params),
+                (Method::Get | Method::Post, "/anagram") => self.anagram(&params),
+                (Method::Get | Method::Post, "/analyze") => self.analyze(&params),
+                (Method::Get | Method::Post, "/replay") => self.replay(&params),
+                _ => Err((404, format!("no endpoint {} {}", request.method(), path))),
+            }),
+            Err(e) => Err((400, format!("could not read the request body: {}", e))),
+        };
+        let (status, value) = match result {
+            Ok(value) => (200, value),
+            Err((status, message)) => (status, json!({ "error": message })),
+        };
+        let header =
+            Header::from_bytes("Content-Type", "application/json").expect("a valid static header");
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(header);
+        let _ = request.respond(response);
+    }
+
+    /// `/check`: look words up and show their hooks; the words are
+    /// acceptable as a play when all of them are valid. With `play` they
+    /// are judged as one play and only the verdict comes back, as from an
+    /// `Adjudicator`.
+    fn check(&self, params: &Params) -> Result<Value, Failure> {
+        let words = params.list("words");
+        if words.is_empty() {
+            return Err(bad("words is required"));
+        }
+        if params.flag("play") {
+            let verdict = Adjudicator::new(&self.lexicon.trie)
+                .judge(&words)
+                .map_err(bad)?;
+            return Ok(json!({ "verdict": verdict.as_str() }));
+        }
+        let judgement = PlayJudgement::new(&words, &self.lexicon.trie);
+        let results: Vec<Value> = judgement
+            .words
+            .iter()
+            .map(|w| {
+                let hooks = self.lexicon.trie.hooks(&w.word);
+                json!({
+                    "word": w.word,
+                    "valid": w.valid,
+                    "front_hooks": hooks.front.iter().collect::<String>(),
+                    "back_hooks": hooks.back.iter().collect::<String>(),
+                })
+            })
+            .collect();
+        Ok(json!({ "words": results, "acceptable": judgement.is_acceptable() }))
+    }
+
+    /// `/anagram`: every word of at least `min` letters made from the
+    /// letters, or with `exact` only those using all of them.
+    fn anagram(&self, params: &Params) -> Result<Value, Failure> {
+        let letters = params
+            .text("letters")
+            .ok_or_else(|| bad("letters is required"))?;
+        let rack = Rack::from_letters(&letters).map_err(bad)?;
+        let rack_word: String = rack.letters.iter().collect();
+        let min_length = params.number("min")?.unwrap_or(2) as usize;
+        let words = if params.flag("exact") {
+            self.lexicon.alphagrams.blank_anagrams(&rack_word)
+        } else {
+            self.lexicon.alphagrams.subanagrams(&rack_word, min_length)
+        };
+        Ok(json!({ "letters": letters, "words": words }))
+    }
+
+    /// `/analyze`: the best plays for `rack` on the posted position,
+    /// which is `board` (empty if missing) after `moves`.
+    fn analyze(&self, params: &Params) -> Result<Value, Failure> {
+        let letters = params.text("rack").ok_or_else(|| bad("rack is required"))?;
+        let rack = Rack::from_letters(&letters).map_err(bad)?;
+        let (position, _) = self.position(params)?;
+        let top = params.number("top")?.unwrap_or(10) as usize;
+        let limits = SearchLimits {
+            time: params.number("time_ms")?.map(Duration::from_millis),
+            nodes: params.number("nodes")?,
+        };
+
+        let result = SearchController::new(limits).run_position(
+            &position,
+            &rack,
+            &self.lexicon.trie,
+            self.threads,
+        );
+        let moves: Vec<Value> = result
+            .moves
+            .iter()
+            .take(top)
+            .map(|play| {
+                let mut value = play.to_json();
+                value["leave"] = json!(rack.leave(&play.tiles));
+                value
+            })
+            .collect();
+        Ok(json!({
+            "board": position.notation(),
+            "rack": rack.letters.iter().collect::<String>(),
+            "moves": moves,
+            "search": result.stats(),
+        }))
+    }
+
+    /// `/replay`: play `moves` in turn for `players` players, checking
+    /// and scoring each one, and report the running totals.
+    fn replay(&self, params: &Params) -> Result<Value, Failure> {
+        if params.list("moves").is_empty() {
+            return Err(bad("moves is required"));
+        }
+        let players = params.number("players")?.unwrap_or(2) as usize;
+        if players == 0 {
+            return Err(bad("players must be at least 1"));
+        }
+        let (position, plays) = self.position(params)?;
+        let mut totals = vec![0; players];
+        let turns: Vec<Value> = plays
+            .iter()
+            .enumerate()
+            .map(|(i, play)| {
+                let player = i % players;
+                let (notation, score) = match play {
+                    Some(play) => (play.notation(), play.score),
+                    None => ("pass".to_string(), 0),
+                };
+                totals[player] += score;
+                json!({
+                    "turn": i + 1,
+                    "player": player + 1,
+                    "move": notation,
+                    "score": score,
+                    "total": totals[player],
+                })
+            })
+            .collect();
+        Ok(json!({ "turns": turns, "scores": totals, "board": position.notation() }))
+    }
+
+    /// The position described by `board` and `moves`, with every move
+    /// checked and scored; `None` stands for a pass.
+    fn position(&self, params: &Params) -> Result<(Position, Vec<Option<Play>>), Failure> {
+        let mut position = match params.text("board") {
+            Some(board) => Position::parse(&board).map_err(bad)?,
+            None => Position::new(),
+        };
+        let mut plays = Vec::new();
+        for (i, notation) in params.list("moves").iter().enumerate() {
+            if notation.trim().eq_ignore_ascii_case("pass") {
+                plays.push(None);
+                continue;
+            }
+            let play = Play::from_notation(notation)
+                .and_then(|play| position.apply(&play, &self.lexicon.trie, &self.values))
+                .map_err(|e| bad(format!("move {}: {}", i + 1, e)))?;
+            plays.push(Some(play));
+        }
+        Ok((position, plays))
+    }
+}
+
+fn bad(message: impl Into<String>) -> Failure {
+    (400, message.into())
+}
+
+/// ## Params
+/// The parameters of a request: the query string, overridden by the
+/// fields of a JSON object body.
+struct Params {
+    fields: Map<String, Value>,
+}
+
+impl Params {
+    fn new(query: &str, body: &str) -> Result<Params, Failure> {
+        let mut fields = Map::new();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            fields.insert(decode(name), Value::String(decode(value)));
+        }
+        if !body.trim().is_empty() {
+            match serde_json::from_str(body) {
+                Ok(Value::Object(body)) => fields.extend(body),
+                Ok(_) => return Err(bad("the request body must be a JSON object")),
+                Err(e) => return Err(bad(format!("the request body is not valid JSON: {}", e))),
+            }
+        }
+        Ok(Params { fields })
+    }
+
+    fn text(&self, name: &str) -> Option<String> {
+        match self.fields.get(name)? {
+            Value::String(text) if !text.is_empty() => Some(text.clone()),
+            Value::Number(number) => Some(number.to_string()),
+            _ => None,
+        }
+    }
+
+    fn number(&self, name: &str) -> Result<Option<u64>, Failure> {
+        match self.fields.get(name) {
+            None => Ok(None),
+            Some(Value::Number(number)) => number
+                .as_u64()
+                .map(Some)
+                .ok_or_else(|| bad(format!("{} expects a whole number", name))),
+            Some(Value::String(text)) => text
+                .parse()
+                .map(Some)
+                .map_err(|_| bad(format!("{} expects a whole number, got \"{}\"", name, text))),
+            Some(_) => Err(bad(format!("{} expects a whole number", name))),
+        }
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        match self.fields.get(name) {
+            Some(Value::Bool(flag)) => *flag,
+            Some(Value::String(text)) => text.is_empty() || text == "true" || text == "1",
+            _ => false,
+        }
+    }
+
+    /// A JSON array of strings, or one string separated by commas.
+    fn list(&self, name: &str) -> Vec<String> {
+        match self.fields.get(name) {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|item| item.as_str())
+                .map(|item| item.to_string())
+                .collect(),
+            Some(Value::String(text)) => text
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Undo the percent-encoding of a query string, where `+` is a space.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> Server {
+        let words = [
+            "QI", "ZA", "QIS", "ZAS", "AT", "TA", "CAT", "ACT", "ZAX", "QUIZ",
+        ];
+        Server::new(Lexicon::from_words(words.iter().map(|w| w.to_string())), 1)
+    }
+
+    fn params(query: &str, body: &str) -> Params {
+        Params::new(query, body).unwrap()
+    }
+
+    #[test]
+    fn check_shows_each_word_and_the_play() {
+        let answer = server().check(&params("words=QI,zz", "")).unwrap();
+        assert_eq!(answer["acceptable"], json!(false));
+        assert_eq!(answer["words"][0]["word"], json!("QI"));
+        assert_eq!(answer["words"][0]["valid"], json!(true));
+        assert_eq!(answer["words"][0]["back_hooks"], json!("S"));
+        assert_eq!(answer["words"][1]["valid"], json!(false));
+    }
+
+    #[test]
+    fn check_with_play_answers_only_the_verdict() {
+        let server = server();
+        let answer = server.check(&params("words=QI,ZZ&play=true", "")).unwrap();
+        assert_eq!(answer, json!({ "verdict": "not acceptable" }));
+        let body = r#"{"words": ["QI", "ZA"], "play": true}"#;
+        let answer = server.check(&params("", body)).unwrap();
+        assert_eq!(answer, json!({ "verdict": "acceptable" }));
+        assert_eq!(server.check(&params("", "")).unwrap_err().0, 400);
+    }
+
+    #[test]
+    fn anagram_lists_words_from_the_letters() {
+        let server = server();
+        let answer = server.anagram(&params("letters=TAC", "")).unwrap();
+        assert_eq!(answer["words"], json!(["ACT", "CAT", "AT", "TA"]));
+        let answer = server
+            .anagram(&params("letters=TA%3F&exact=true", ""))
+            .unwrap();
+        assert_eq!(answer["words"], json!(["ACT", "CAT"]));
+        assert_eq!(
+            server.anagram(&params("letters=T1", "")).unwrap_err().0,
+            400
+        );
+    }
+
+    #[test]
+    fn analyze_finds_the_best_play_on_the_posted_board() {
+        let server = server();
+        let answer = server
+            .analyze(&params("", r#"{"rack": "QUIZ", "top": 1}"#))
+            .unwrap();
+        assert_eq!(answer["moves"].as_array().unwrap().len(), 1);
+        assert_eq!(answer["moves"][0]["score"], json!(44));
+        let body = r#"{"rack": "S", "moves": ["8H QUIZ"]}"#;
+        assert_eq!(
+            server.analyze(&params("", body)).unwrap()["moves"],
+            json!([])
+        );
+        let body = r#"{"rack": "S", "moves": ["8H QUIZZ"]}"#;
+        assert_eq!(server.analyze(&params("", body)).unwrap_err().0, 400);
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::utils::error::Error;
+use crate::utils::trieguy::TrieTree;
+use crate::utils::word_check::PlayJudgement;
+
+/// ## Verdict
+/// The only thing an adjudicator ever tells the players.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verdict {
+    Acceptable,
+    NotAcceptable,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Acceptable => "acceptable",
+            Verdict::NotAcceptable => "not acceptable",
+        }
+    }
+}
+
+/// # Adjudicator
+/// A tournament word judge. It receives every word formed by a play as
+/// one batch and answers with a single `Verdict`; which word failed is
+/// deliberately never exposed, so a challenged player learns nothing
+/// beyond "play is acceptable / not acceptable".
+/// Every decision can be appended to a log file for the tournament director.
+/// Functions:
+/// - `with_log()`, `judge()`, `decisions()`
+pub struct Adjudicator<'a> {
+    tree: &'a TrieTree,
+    log: Option<File>,
+    decisions: u32,
+}
+
+impl<'a> Adjudicator<'a> {
+    pub fn new(tree: &'a TrieTree) -> Self {
+        Adjudicator {
+            tree,
+            log: None,
+            decisions: 0,
+        }
+    }
+
+    /// ### with_log()
+    /// Like `new()`, but every decision is appended to the file at `path`.
+    pub fn with_log(tree: &'a TrieTree, path: &str) -> Result<Self, Error> {
+        let log = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Adjudicator {
+            tree,
+            log: Some(log),
+            decisions: 0,
+        })
+    }
+
+    /// ### judge()
+    /// Check the whole batch and return one `Verdict`. The log line holds
+    /// the time, the decision number, the words submitted and the verdict,
+    /// but not the validity of the individual words.
+    pub fn judge(&mut self, words: &[String]) -> Result<Verdict, Error> {
+        let verdict = if PlayJudgement::new(words, self.tree).is_acceptable() {
+            Verdict::Acceptable
+        } else {
+            Verdict::NotAcceptable
+        };
+        self.decisions += 1;
+
+        if let Some(log) = &mut self.log {
+            let seconds = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let words: Vec<String> = words.iter().map(|w| w.trim().to_uppercase()).collect();
+            writeln!(
+                log,
+                "{}\t#{}\t{}\t{}",
+                seconds,
+                self.decisions,
+                words.join(" "),
+                verdict.as_str()
+            )?;
+        }
+        Ok(verdict)
+    }
+
+    /// ### decisions()
+    /// How many batches have been judged so far.
+    pub fn decisions(&self) -> u32 {
+        self.decisions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::lexicon::Lexicon;
+    use std::fs;
+
+    fn lexicon() -> Lexicon {
+        Lexicon::from_words(["QI", "ZA", "QIS"].iter().map(|w| w.to_string()))
+    }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn one_verdict_per_batch() {
+        let lexicon = lexicon();
+        let mut adjudicator = Adjudicator::new(&lexicon.trie);
+        let verdict = adjudicator.judge(&words(&["qi", "ZA"])).unwrap();
+        assert_eq!(verdict, Verdict::Acceptable);
+        // The verdict is the same whichever word of the batch is the phony
+        let first = adjudicator.judge(&words(&["ZQ", "ZA"])).unwrap();
+        let second = adjudicator.judge(&words(&["QI", "ZQ"])).unwrap();
+        assert_eq!(
+            (first, second),
+            (Verdict::NotAcceptable, Verdict::NotAcceptable)
+        );
+        assert_eq!(adjudicator.decisions(), 3);
+    }
+
+    #[test]
+    fn the_log_holds_the_words_and_verdict_but_no_word_validity() {
+        let lexicon = lexicon();
+        let path = std::env::temp_dir().join(format!("adjudicator-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut adjudicator = Adjudicator::with_log(&lexicon.trie, path).unwrap();
+        adjudicator.judge(&words(&["QI", "ZA"])).unwrap();
+        adjudicator.judge(&words(&["QIS", "ZQ"])).unwrap();
+        let log = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let lines: Vec<Vec<&str>> = log.lines().map(|l| l.split('\t').collect()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0][1..], ["#1", "QI ZA", "acceptable"]);
+        assert_eq!(lines[1][1..], ["#2", "QIS ZQ", "not acceptable"]);
+        // Time, decision, words and verdict: nothing word by word
+        assert!(lines.iter().all(|fields| fields.len() == 4));
+    }
+}
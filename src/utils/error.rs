@@ -0,0 +1,68 @@
+use std::fmt;
+use std::io;
+
+/// # Error
+/// Everything that can go wrong when using the crate. Bad input never
+/// panics; it comes back as one of these, and its `Display` is the
+/// message shown to the user.
+#[derive(Debug)]
+pub enum Error {
+    /// The word list at `path` could not be read
+    Lexicon { path: String, source: io::Error },
+    /// Any other file could not be read or written
+    Io(io::Error),
+    /// Letters that don't make a rack, e.g. too many tiles or a digit
+    InvalidRack(String),
+    /// A play that can't be made on the board
+    IllegalMove(String),
+    /// Text that isn't a square, a play, a board or a pattern
+    Parse(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Lexicon { path, source } => {
+                write!(f, "could not load lexicon {}: {}", path, source)
+            }
+            Error::Io(source) => write!(f, "{}", source),
+            Error::InvalidRack(message) | Error::IllegalMove(message) | Error::Parse(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl Error {
+    /// An `Io` error that names the file it happened to, `action` being
+    /// what was being done, e.g. "read".
+    pub fn in_file(action: &str, filename: &str, error: io::Error) -> Error {
+        Error::Io(io::Error::new(
+            error.kind(),
+            format!("cannot {} {}: {}", action, filename, error),
+        ))
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Lexicon { source, .. } | Error::Io(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+/// Lets `?` pass an `Error` up through code that reports plain messages,
+/// like the command line and the engine protocol.
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,168 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+use crate::anagrams::alphagram::{alphagram, AlphagramIndex};
+use crate::utils::error::Error;
+use crate::utils::trieguy::{TrieNode, TrieTree};
+
+/// First line of a lexicon compiled by `scrabble lexicon build`. Every
+/// following line is an alphagram and the words spelled with it, e.g.
+/// `AEHRT EARTH HATER HEART`.
+pub const COMPILED_HEADER: &str = "#scrabble-lexicon alphagrams";
+
+/// What every compiled lexicon header starts with, whatever its format.
+const COMPILED_PREFIX: &str = "#scrabble-lexicon";
+
+/// # Lexicon
+/// Everything the agent knows about the word list: the `TrieTree` for
+/// letter-by-letter lookups and the `AlphagramIndex` for anagrams.
+/// A plain word list is indexed while loading; a compiled lexicon
+/// already holds the index and only has to be read.
+/// Functions:
+/// - `load()`, `from_words()`, `write_compiled()`
+pub struct Lexicon {
+    pub trie: TrieTree,
+    pub alphagrams: AlphagramIndex,
+}
+
+impl Lexicon {
+    /// ### load()
+    /// Load a plain word list (one word per line) or a compiled lexicon,
+    /// telling them apart by the first line. A compiled lexicon in a format
+    /// this build doesn't know, or with words filed under the wrong
+    /// alphagram, is an `Error::Parse`.
+    pub fn load(filename: &str) -> Result<Lexicon, Error> {
+        let failed = |source| Error::Lexicon {
+            path: filename.to_string(),
+            source,
+        };
+        let mut reader = BufReader::new(File::open(filename).map_err(failed)?);
+        let mut first = String::new();
+        reader.read_line(&mut first).map_err(failed)?;
+        let first = first.trim();
+        if first.starts_with(COMPILED_PREFIX) && first != COMPILED_HEADER {
+            return Err(Error::Parse(format!(
+                "{}:1: unknown lexicon header \"{}\", expected \"{}\"",
+                filename, first, COMPILED_HEADER
+            )));
+        }
+        if first != COMPILED_HEADER {
+            let trie = TrieTree::from_file(filename)?;
+            let mut alphagrams = AlphagramIndex::new();
+            let mut words = trie.words();
+            words.sort();
+            for word in words {
+                alphagrams.insert(&word);
+            }
+            return Ok(Lexicon { trie, alphagrams });
+        }
+
+        let mut root = TrieNode::new();
+        let mut alphagrams = AlphagramIndex::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line.map_err(failed)?;
+            let mut fields = line.split_whitespace();
+            let key = match fields.next() {
+                Some(key) => key,
+                None => continue,
+            };
+            let words: Vec<String> = fields.map(|w| w.to_string()).collect();
+            if let Some(word) = words.iter().find(|word| alphagram(word) != key) {
+                return Err(Error::Parse(format!(
+                    "{}:{}: {} is not spelled with {}",
+                    filename,
+                    number + 2,
+                    word,
+                    key
+                )));
+            }
+            for word in &words {
+                TrieTree::insert(&mut root, word);
+            }
+            alphagrams.insert_group(key, words);
+        }
+        Ok(Lexicon {
+            trie: TrieTree {
+                root: Box::new(root),
+            },
+            alphagrams,
+        })
+    }
+
+    /// ### from_words()
+    /// Build a lexicon from words that are already upper-case.
+    pub fn from_words<I: IntoIterator<Item = String>>(words: I) -> Lexicon {
+        let mut root = TrieNode::new();
+        let mut alphagrams = AlphagramIndex::new();
+        for word in words {
+            TrieTree::insert(&mut root, &word);
+            alphagrams.insert(&word);
+        }
+        Lexicon {
+            trie: TrieTree {
+                root: Box::new(root),
+            },
+            alphagrams,
+        }
+    }
+
+    /// ### write_compiled()
+    /// Write the lexicon with its alphagram index so later loads skip
+    /// building it.
+    pub fn write_compiled(&self, filename: &str) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        writeln!(writer, "{}", COMPILED_HEADER)?;
+        for (key, words) in self.alphagrams.groups() {
+            writeln!(writer, "{} {}", key, words.join(" "))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{}.txt", name, std::process::id()));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn a_compiled_lexicon_loads_back_the_same() {
+        let words = ["HEART", "EARTH", "HATER", "QI", "ZA"];
+        let lexicon = Lexicon::from_words(words.iter().map(|w| w.to_string()));
+        let path = temp_file("lexicon-compiled");
+        lexicon.write_compiled(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let loaded = Lexicon::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(text.lines().next(), Some(COMPILED_HEADER));
+        let loaded = loaded.unwrap();
+        let mut expected = lexicon.trie.words();
+        let mut found = loaded.trie.words();
+        expected.sort();
+        found.sort();
+        assert_eq!(found, expected);
+        assert_eq!(loaded.alphagrams.groups(), lexicon.alphagrams.groups());
+        assert_eq!(
+            loaded.alphagrams.anagrams("RATHE"),
+            ["HEART", "EARTH", "HATER"]
+        );
+    }
+
+    #[test]
+    fn a_bad_compiled_lexicon_is_a_parse_error() {
+        let path = temp_file("lexicon-bad");
+        fs::write(&path, "#scrabble-lexicon trie\nAEHRT HEART\n").unwrap();
+        let header = Lexicon::load(&path);
+        fs::write(&path, format!("{}\nAEHRT HEART QI\n", COMPILED_HEADER)).unwrap();
+        let misfiled = Lexicon::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(header, Err(Error::Parse(_))));
+        assert!(matches!(misfiled, Err(Error::Parse(_))));
+    }
+}
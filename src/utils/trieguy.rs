@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader};
+
+use crate::utils::error::Error;
 
 /// # TrieNode
 /// Each node of the TrieGuy contains a child, or it
@@ -11,6 +13,12 @@ pub struct TrieNode {
     pub is_end_of_word: bool,
 }
 
+impl Default for TrieNode {
+    fn default() -> Self {
+        TrieNode::new()
+    }
+}
+
 impl TrieNode {
     pub fn new() -> Self {
         TrieNode {
@@ -20,22 +28,38 @@ impl TrieNode {
     }
 }
 
+/// ## Hooks
+/// The single letters that can be placed in front of (`front`) or
+/// behind (`back`) a word to form another valid word, in A-Z order.
+pub struct Hooks {
+    pub front: Vec<char>,
+    pub back: Vec<char>,
+}
+
 /// # TrieTree
 /// A TrieTree is a data structure that loads up the dictionary
 /// of scrabble words. It's a convenient data struct for generating
 /// anagrams because it allows for quick lookups.
 /// Functions:
-/// - `load_dictionoary()`, `insert()`, `search()`
-pub(crate) struct TrieTree {
+/// - `from_file()`, `load_dictionoary()`, `insert()`, `search()`, `find()`, `hooks()`, `words()`
+pub struct TrieTree {
     pub root: Box<TrieNode>,
 }
 
+/// The word list shipped with the crate, used when no lexicon is given.
+pub const DEFAULT_DICTIONARY: &str = "src/data/dictionary.txt";
+
 impl TrieTree {
-    pub fn new() -> Self {
-        let root = TrieTree::load_dictionary("src/data/dictionary.txt");
-        TrieTree {
+    /// ### from_file()
+    /// Build the tree from any word list with one word per line.
+    pub fn from_file(filename: &str) -> Result<Self, Error> {
+        let root = TrieTree::load_dictionary(filename).map_err(|source| Error::Lexicon {
+            path: filename.to_string(),
+            source,
+        })?;
+        Ok(TrieTree {
             root: Box::new(root),
-        }
+        })
     }
 
     /// ### load_dictionary()
@@ -43,15 +67,15 @@ impl TrieTree {
     /// create a new reader object. It will then iterate
     /// over each line and convert it to a String. It will
     /// then pass the word into the `insert()` function.
-    fn load_dictionary(filename: &str) -> TrieNode {
-        let file = File::open(filename).expect("Failed to open dictionary file");
+    fn load_dictionary(filename: &str) -> io::Result<TrieNode> {
+        let file = File::open(filename)?;
         let reader = BufReader::new(file);
         let mut root = TrieNode::new();
         for line in reader.lines() {
-            let word = line.unwrap().trim().to_string();
+            let word = line?.trim().to_string();
             TrieTree::insert(&mut root, &word);
         }
-        root
+        Ok(root)
     }
 
     /// ### insert()
@@ -72,14 +96,66 @@ impl TrieTree {
     /// working your way down the tree. If the character
     /// is not in the tree, return `false`. Otherwise
     /// return `true`.
-    pub fn search(&mut self, word: &str) -> bool {
+    pub fn search(&self, word: &str) -> bool {
         let mut node = &self.root;
         for ch in word.chars() {
-            if !node.children.contains_key(&ch) {
-                return false;
-            }
-            node = &*node.children.get(&ch).unwrap();
+            node = match node.children.get(&ch) {
+                Some(child) => child,
+                None => return false,
+            };
         }
         node.is_end_of_word
     }
+
+    /// ### find()
+    /// Walk down the tree along `prefix` and return the node it ends
+    /// on, or `None` if no word starts with it.
+    pub fn find(&self, prefix: &str) -> Option<&TrieNode> {
+        let mut node = &self.root;
+        for ch in prefix.chars() {
+            node = node.children.get(&ch)?;
+        }
+        Some(node)
+    }
+
+    /// ### hooks()
+    /// Back hooks are the children of the word's node that end a word,
+    /// so they come straight from the tree. Front hooks need one lookup
+    /// per letter of the alphabet.
+    pub fn hooks(&self, word: &str) -> Hooks {
+        let mut back: Vec<char> = match self.find(word) {
+            Some(node) => node
+                .children
+                .iter()
+                .filter(|(_, child)| child.is_end_of_word)
+                .map(|(letter, _)| *letter)
+                .collect(),
+            None => Vec::new(),
+        };
+        back.sort();
+        let front = ('A'..='Z')
+            .filter(|letter| self.search(&format!("{}{}", letter, word)))
+            .collect();
+        Hooks { front, back }
+    }
+
+    /// ### words()
+    /// Every word in the tree, in no particular order.
+    pub fn words(&self) -> Vec<String> {
+        let mut words = Vec::new();
+        let mut path = String::new();
+        TrieTree::collect(&self.root, &mut path, &mut words);
+        words
+    }
+
+    fn collect(node: &TrieNode, path: &mut String, words: &mut Vec<String>) {
+        if node.is_end_of_word {
+            words.push(path.clone());
+        }
+        for (letter, child) in &node.children {
+            path.push(*letter);
+            TrieTree::collect(child, path, words);
+            path.pop();
+        }
+    }
 }
@@ -44,10 +44,17 @@ impl Iterator for WeightedGroups {
     }
 }
 
+impl Default for WeightedGroups {
+    fn default() -> Self {
+        WeightedGroups::new()
+    }
+}
+
 /// ## WeigthedGroups
 /// ### Fields
 /// - frequency_groups: Vec<WeightedGroup>
 /// - score_groups: Vec<WeightedGroup>
+///
 /// Two groups are instantiated, frequency - representing the letters in a scrabble bag
 /// and scores, representing the scores
 impl WeightedGroups {
@@ -0,0 +1,54 @@
+use crate::utils::trieguy::TrieTree;
+
+/// ## WordCheck
+/// The result of looking a single word up in the lexicon. Input is
+/// upper-cased first; anything that is not made of letters is never
+/// a valid word.
+pub struct WordCheck {
+    pub word: String,
+    pub valid: bool,
+}
+
+impl WordCheck {
+    pub fn new(word: &str, tree: &TrieTree) -> Self {
+        let word = word.trim().to_uppercase();
+        let valid =
+            !word.is_empty() && word.chars().all(|c| c.is_ascii_uppercase()) && tree.search(&word);
+        WordCheck { word, valid }
+    }
+}
+
+/// # PlayJudgement
+/// Judges a whole play the way a club adjudicator does: the main word
+/// and every cross-word it forms are checked, and the play is only
+/// acceptable when all of them are valid.
+/// Functions:
+/// - `is_acceptable()`, `phonies()`
+pub struct PlayJudgement {
+    pub words: Vec<WordCheck>,
+}
+
+impl PlayJudgement {
+    /// Check every word formed by a play, main word first.
+    pub fn new(words: &[String], tree: &TrieTree) -> Self {
+        PlayJudgement {
+            words: words.iter().map(|w| WordCheck::new(w, tree)).collect(),
+        }
+    }
+
+    /// ### is_acceptable()
+    /// A play with no words is never acceptable.
+    pub fn is_acceptable(&self) -> bool {
+        !self.words.is_empty() && self.words.iter().all(|w| w.valid)
+    }
+
+    /// ### phonies()
+    /// The words of the play that are not in the lexicon.
+    pub fn phonies(&self) -> Vec<&str> {
+        self.words
+            .iter()
+            .filter(|w| !w.valid)
+            .map(|w| w.word.as_str())
+            .collect()
+    }
+}